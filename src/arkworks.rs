@@ -0,0 +1,192 @@
+//! Export of the powers of tau as KZG parameters for arkworks, in the
+//! `CanonicalSerialize` layout (ark-serialize 0.4) of `UniversalParams` and
+//! `Powers` from `ark_poly_commit::kzg10` over `ark_bn254::Bn254`.
+//!
+//! `UniversalParams` holds:
+//!
+//! * `powers_of_g`: the powers τ<sup>i</sup> in G1
+//! * `powers_of_gamma_g`: empty
+//! * `h` and `beta_h`: the generator of G2 and τ in G2
+//! * `neg_powers_of_h`: empty
+//!
+//! and `Powers` holds `powers_of_g` and an empty `powers_of_gamma_g`. The
+//! ceremony has no γ, so the parameters only support non-hiding
+//! commitments, and ark-poly-commit panics on anything that indexes
+//! `powers_of_gamma_g`: building a `VerifierKey` with
+//! `powers_of_gamma_g[&0]` does, so its `gamma_g` has to be set otherwise.
+//!
+//! Vectors and maps are preceded by their length as a little-endian u64.
+//! Coordinates are little-endian integers, with the real part first in G2.
+//! Compressed points hold x only. The top bit of the last byte is set if y is
+//! the larger of ±y (comparing the imaginary part first in G2) and the next
+//! bit for the point at infinity, whose coordinates are zero.
+
+use bn::{AffineG1, AffineG2, Fq, Fq2, G1, G2};
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::io::{self, Write};
+
+use bn256::Bn256;
+use super::{Accumulator, UseCompression};
+
+const FQ_SIZE: usize = 32;
+
+const Y_IS_NEGATIVE: u8 = 1 << 7;
+const INFINITY: u8 = 1 << 6;
+
+/// The public parameters of arkworks' KZG10 polynomial commitments.
+#[derive(PartialEq, Eq, Clone)]
+pub struct UniversalParams {
+    /// tau^i in G1
+    pub powers_of_g: Vec<G1>,
+    /// The generator of G2
+    pub h: G2,
+    /// tau in G2
+    pub beta_h: G2,
+}
+
+impl UniversalParams {
+    /// Takes every power of tau in G1 from `acc`.
+    pub fn new(acc: &Accumulator<Bn256>) -> Self
+    {
+        UniversalParams {
+            powers_of_g: acc.tau_powers_g1.clone(),
+            h: acc.tau_powers_g2[0],
+            beta_h: acc.tau_powers_g2[1],
+        }
+    }
+
+    pub fn serialize<W: Write>(&self, writer: &mut W, compression: UseCompression) -> io::Result<()>
+    {
+        self.serialize_powers(writer, compression)?;
+        write_g2(writer, &self.h, compression)?;
+        write_g2(writer, &self.beta_h, compression)?;
+        // neg_powers_of_h
+        writer.write_u64::<LittleEndian>(0)?;
+
+        Ok(())
+    }
+
+    /// Write the committer key, `Powers`, alone. This is also the start of
+    /// `serialize`.
+    pub fn serialize_powers<W: Write>(&self, writer: &mut W, compression: UseCompression) -> io::Result<()>
+    {
+        writer.write_u64::<LittleEndian>(self.powers_of_g.len() as u64)?;
+        for p in &self.powers_of_g {
+            write_g1(writer, p, compression)?;
+        }
+        // powers_of_gamma_g
+        writer.write_u64::<LittleEndian>(0)?;
+
+        Ok(())
+    }
+}
+
+fn fq_to_big_endian(x: Fq) -> [u8; FQ_SIZE]
+{
+    let mut bytes = [0u8; FQ_SIZE];
+    x.into_u256().to_big_endian(&mut bytes).expect("32 bytes is enough for Fq");
+    bytes
+}
+
+fn fq_to_bytes(x: Fq) -> [u8; FQ_SIZE]
+{
+    let mut bytes = fq_to_big_endian(x);
+    bytes.reverse();
+    bytes
+}
+
+/// Whether y is the larger of ±y as integers.
+fn is_negative(y: Fq) -> bool
+{
+    fq_to_big_endian(y) > fq_to_big_endian(-y)
+}
+
+/// As `is_negative`, ordering by the imaginary part first.
+fn is_negative_fq2(y: Fq2) -> bool
+{
+    if y.imaginary() == Fq::zero() {
+        is_negative(y.real())
+    } else {
+        is_negative(y.imaginary())
+    }
+}
+
+fn write_g1<W: Write>(writer: &mut W, p: &G1, compression: UseCompression) -> io::Result<()>
+{
+    let mut bytes = [0u8; 2 * FQ_SIZE];
+    let size = match compression {
+        UseCompression::Yes => FQ_SIZE,
+        UseCompression::No => 2 * FQ_SIZE,
+    };
+
+    let flags = match AffineG1::from_jacobian(*p) {
+        Some(p) => {
+            bytes[0..FQ_SIZE].copy_from_slice(&fq_to_bytes(p.x()));
+            bytes[FQ_SIZE..].copy_from_slice(&fq_to_bytes(p.y()));
+            if is_negative(p.y()) { Y_IS_NEGATIVE } else { 0 }
+        }
+        None => INFINITY
+    };
+    bytes[size - 1] |= flags;
+    writer.write_all(&bytes[0..size])
+}
+
+fn write_g2<W: Write>(writer: &mut W, p: &G2, compression: UseCompression) -> io::Result<()>
+{
+    let mut bytes = [0u8; 4 * FQ_SIZE];
+    let size = match compression {
+        UseCompression::Yes => 2 * FQ_SIZE,
+        UseCompression::No => 4 * FQ_SIZE,
+    };
+
+    let flags = match AffineG2::from_jacobian(*p) {
+        Some(p) => {
+            bytes[0..FQ_SIZE].copy_from_slice(&fq_to_bytes(p.x().real()));
+            bytes[FQ_SIZE..(2 * FQ_SIZE)].copy_from_slice(&fq_to_bytes(p.x().imaginary()));
+            bytes[(2 * FQ_SIZE)..(3 * FQ_SIZE)].copy_from_slice(&fq_to_bytes(p.y().real()));
+            bytes[(3 * FQ_SIZE)..].copy_from_slice(&fq_to_bytes(p.y().imaginary()));
+            if is_negative_fq2(p.y()) { Y_IS_NEGATIVE } else { 0 }
+        }
+        None => INFINITY
+    };
+    bytes[size - 1] |= flags;
+    writer.write_all(&bytes[0..size])
+}
+
+#[test]
+fn test_universal_params() {
+    use bn::Fr;
+    use configuration::*;
+    use engine::{CurvePoint, ScalarField};
+    use super::PrivateKey;
+
+    // An accumulator with tau = 7, from which the vectors were computed by
+    // test_vectors/gen_test_vectors.py.
+    let mut acc = Accumulator::<Bn256>::new(Configuration::new(16));
+    acc.transform(&PrivateKey::new(Fr::from_u64(7), Fr::from_u64(11), Fr::from_u64(13)));
+    let params = UniversalParams::new(&acc);
+
+    let expected = include_bytes!("../test_vectors/arkworks_params_16_compressed.bin");
+    let mut data = vec![];
+    params.serialize(&mut data, UseCompression::Yes).unwrap();
+    assert_eq!(&data[..], &expected[..]);
+
+    let mut powers = vec![];
+    params.serialize_powers(&mut powers, UseCompression::Yes).unwrap();
+    assert_eq!(&powers[..], &expected[0..(8 + 31 * FQ_SIZE + 8)]);
+
+    let expected = include_bytes!("../test_vectors/arkworks_params_16_uncompressed.bin");
+    let mut data = vec![];
+    params.serialize(&mut data, UseCompression::No).unwrap();
+    assert_eq!(&data[..], &expected[..]);
+
+    let mut powers = vec![];
+    params.serialize_powers(&mut powers, UseCompression::No).unwrap();
+    assert_eq!(&powers[..], &expected[0..(8 + 31 * 2 * FQ_SIZE + 8)]);
+
+    // The point at infinity.
+    let mut data = vec![];
+    write_g1(&mut data, &G1::zero(), UseCompression::No).unwrap();
+    assert_eq!(data[2 * FQ_SIZE - 1], INFINITY);
+    assert!(data[..(2 * FQ_SIZE - 1)].iter().all(|&b| b == 0));
+}
@@ -0,0 +1,688 @@
+//! The random beacon applied in the final contribution of the ceremony.
+//!
+//! A public random value chosen in advance, such as the hash of a future
+//! block, is hashed with 2<sup>N</sup> iterations of SHA-256, so that nobody
+//! can learn the outcome before the value is fixed. The value can also
+//! combine several named sources, such as a block hash, a drand round and a
+//! lottery result, in which case it is the SHA-256 hash of `SOURCES_DOMAIN`,
+//! the number of sources, and the name and value of each source in order,
+//! each preceded by its length (integers as big-endian u32). The intermediate hashes
+//! are recorded every 2<sup>C</sup> iterations, so the chain can be checked
+//! one segment per thread.
+//!
+//! The beacon descriptor written next to the response is a text file with
+//! one `key value` line per entry, hashes in hex:
+//!
+//! * `source NAME HEX`: each source, in order, if the value combines them
+//! * `value HASH`: the beacon value
+//! * `iterations_log2 N`
+//! * `checkpoint_interval_log2 C`
+//! * `checkpoint I HASH`: the hash before iteration I, for each multiple I
+//!   of 2<sup>C</sup> below 2<sup>N</sup>
+//! * `result HASH`: the hash after 2<sup>N</sup> iterations
+//!
+//! While the beacon is computed, the same format, without the result until
+//! the end, holds its state, so that the computation can resume from the
+//! last checkpoint after an interruption.
+//!
+//! The result seeds the RNG from which the beacon's keypair is drawn, so
+//! anyone can regenerate the keypair and check the public key in the
+//! response.
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
+use crossbeam;
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use hex;
+use rand::SeedableRng;
+use rand::chacha::ChaChaRng;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use engine::Engine;
+use secret::SecretBox;
+use super::{keypair, PrivateKey, PublicKey};
+
+/// Size of the beacon value and of each hash in the chain.
+pub const BEACON_HASH_SIZE: usize = 32;
+
+pub type BeaconHash = [u8; BEACON_HASH_SIZE];
+
+/// Prefix of the hash combining the sources of the beacon value.
+pub const SOURCES_DOMAIN: &[u8] = b"powersoftau beacon sources";
+
+/// Errors that might occur when reading a beacon descriptor or checking its
+/// checkpoints.
+#[derive(Debug)]
+pub enum BeaconError {
+    IoError(io::Error),
+    /// The line with this number could not be parsed.
+    InvalidLine(usize),
+    /// The descriptor has no entry for this key.
+    MissingEntry(&'static str),
+    /// The parameters are out of range.
+    InvalidParameters,
+    /// The first checkpoint is not the beacon value.
+    FirstCheckpointNotValue,
+    /// A checkpoint is missing or out of place, at this iteration.
+    UnexpectedCheckpoint(u64),
+    /// The beacon value is not the combination of its sources.
+    SourcesMismatch,
+    /// The source with this name has an empty value, or its name is empty
+    /// or has whitespace, so it cannot be written to a descriptor.
+    InvalidSource(String),
+}
+
+impl fmt::Display for BeaconError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BeaconError::IoError(ref e) => write!(f, "Disk IO error: {}", e),
+            BeaconError::InvalidLine(n) => write!(f, "Invalid line {}", n),
+            BeaconError::MissingEntry(key) => write!(f, "No `{}` entry", key),
+            BeaconError::InvalidParameters => write!(f, "The checkpoint interval is larger than the number of iterations, or there are more than 2^63 iterations"),
+            BeaconError::FirstCheckpointNotValue => write!(f, "The first checkpoint is not the beacon value"),
+            BeaconError::UnexpectedCheckpoint(i) => write!(f, "Missing or unexpected checkpoint at iteration {}", i),
+            BeaconError::SourcesMismatch => write!(f, "The beacon value is not the combination of its sources"),
+            BeaconError::InvalidSource(ref name) => write!(f, "The beacon source `{}` has an empty value or an invalid name", name),
+        }
+    }
+}
+
+impl From<io::Error> for BeaconError {
+    fn from(err: io::Error) -> BeaconError {
+        BeaconError::IoError(err)
+    }
+}
+
+/// A public random value from which the beacon value is derived.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BeaconSource {
+    /// What the value is, e.g. `bitcoin-block-800000`, without whitespace.
+    pub name: String,
+    /// The value, which is not empty.
+    pub value: Vec<u8>,
+}
+
+/// Hash `sources` together into a beacon value.
+pub fn combine_sources(sources: &[BeaconSource]) -> BeaconHash
+{
+    let mut data = SOURCES_DOMAIN.to_vec();
+    data.write_u32::<BigEndian>(sources.len() as u32).unwrap();
+    for source in sources {
+        data.write_u32::<BigEndian>(source.name.len() as u32).unwrap();
+        data.extend_from_slice(source.name.as_bytes());
+        data.write_u32::<BigEndian>(source.value.len() as u32).unwrap();
+        data.extend_from_slice(&source.value);
+    }
+
+    let mut value = [0u8; BEACON_HASH_SIZE];
+    let mut h = Sha256::new();
+    h.input(&data);
+    h.result(&mut value);
+    value
+}
+
+/// The parameters of the beacon.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BeaconParams {
+    /// The sources combined into the value, if any.
+    pub sources: Vec<BeaconSource>,
+    /// The public random value, e.g. the hash of a block chosen in advance.
+    pub value: BeaconHash,
+    /// log2 of the number of SHA-256 iterations.
+    pub iterations_log2: usize,
+    /// log2 of the number of iterations between checkpoints.
+    pub checkpoint_interval_log2: usize,
+}
+
+impl BeaconParams {
+    pub fn check(&self) -> Result<(), BeaconError>
+    {
+        if self.iterations_log2 > 63 || self.checkpoint_interval_log2 > self.iterations_log2 {
+            return Err(BeaconError::InvalidParameters);
+        }
+        for source in &self.sources {
+            if source.name.is_empty() || source.name.contains(char::is_whitespace) || source.value.is_empty() {
+                return Err(BeaconError::InvalidSource(source.name.clone()));
+            }
+        }
+        if !self.sources.is_empty() && combine_sources(&self.sources) != self.value {
+            return Err(BeaconError::SourcesMismatch);
+        }
+
+        Ok(())
+    }
+
+    pub fn iterations(&self) -> u64
+    {
+        1 << self.iterations_log2
+    }
+
+    pub fn checkpoint_interval(&self) -> u64
+    {
+        1 << self.checkpoint_interval_log2
+    }
+
+    /// Write the parameters at the start of a descriptor.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()>
+    {
+        for source in &self.sources {
+            writeln!(writer, "source {} {}", source.name, hex::encode(&source.value))?;
+        }
+        writeln!(writer, "value {}", hex::encode(&self.value))?;
+        writeln!(writer, "iterations_log2 {}", self.iterations_log2)?;
+        writeln!(writer, "checkpoint_interval_log2 {}", self.checkpoint_interval_log2)
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf
+{
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// The path of the beacon descriptor for the response at `response_path`.
+pub fn descriptor_path(response_path: &Path) -> PathBuf
+{
+    with_suffix(response_path, ".beacon")
+}
+
+/// The path of the state of the beacon computation for the response at
+/// `response_path`.
+pub fn state_path(response_path: &Path) -> PathBuf
+{
+    with_suffix(response_path, ".beacon-state")
+}
+
+pub fn write_checkpoint<W: Write>(writer: &mut W, iteration: u64, hash: &BeaconHash) -> io::Result<()>
+{
+    writeln!(writer, "checkpoint {} {}", iteration, hex::encode(hash))
+}
+
+pub fn write_result<W: Write>(writer: &mut W, hash: &BeaconHash) -> io::Result<()>
+{
+    writeln!(writer, "result {}", hex::encode(hash))
+}
+
+/// Apply `iterations` iterations of SHA-256 to `hash`.
+pub fn hash_chain(hash: &mut BeaconHash, iterations: u64)
+{
+    for _ in 0..iterations {
+        let mut h = Sha256::new();
+        h.input(&hash[..]);
+        h.result(&mut hash[..]);
+    }
+}
+
+/// Compute the beacon, calling `on_checkpoint` with each checkpoint, and
+/// return the result.
+pub fn compute<F>(params: &BeaconParams, mut on_checkpoint: F) -> BeaconHash
+    where F: FnMut(u64, &BeaconHash)
+{
+    on_checkpoint(0, &params.value);
+    resume(params, 0, params.value, on_checkpoint)
+}
+
+/// Continue computing the beacon from the checkpoint `start_hash` at
+/// iteration `start`, calling `on_checkpoint` with each later checkpoint, and
+/// return the result.
+pub fn resume<F>(params: &BeaconParams, start: u64, start_hash: BeaconHash, mut on_checkpoint: F) -> BeaconHash
+    where F: FnMut(u64, &BeaconHash)
+{
+    let mut cur_hash = start_hash;
+    let mut i = start;
+    loop {
+        hash_chain(&mut cur_hash, params.checkpoint_interval());
+        i += params.checkpoint_interval();
+        if i >= params.iterations() {
+            break;
+        }
+        on_checkpoint(i, &cur_hash);
+    }
+
+    cur_hash
+}
+
+/// Create the RNG of the beacon participant from the result of the beacon.
+pub fn beacon_rng(result: &BeaconHash) -> SecretBox<ChaChaRng>
+{
+    let mut digest = &result[..];
+
+    let mut seed = SecretBox::new([0u8; 32]);
+    for i in 0..8 {
+        let bytes = digest.read_u32::<BigEndian>().unwrap().to_be_bytes();
+        seed[(4 * i) .. ((4 * i) + 4)].copy_from_slice(&bytes);
+    }
+
+    SecretBox::new(ChaChaRng::from_seed(*seed))
+}
+
+/// The keypair of the beacon participant for the challenge whose BLAKE2b
+/// hash is `digest`, given the result of the beacon.
+pub fn beacon_keypair<E: Engine>(result: &BeaconHash, digest: &[u8]) -> (PublicKey<E>, PrivateKey<E>)
+{
+    keypair::<E, _>(&mut *beacon_rng(result), digest)
+}
+
+/// A part of the hash chain, between consecutive checkpoints or between the
+/// last checkpoint and the result.
+#[derive(Clone, Debug)]
+pub struct Segment {
+    pub start: u64,
+    pub start_hash: BeaconHash,
+    pub end: u64,
+    pub end_hash: BeaconHash,
+}
+
+impl Segment {
+    pub fn verify(&self) -> bool
+    {
+        let mut hash = self.start_hash;
+        hash_chain(&mut hash, self.end - self.start);
+        hash == self.end_hash
+    }
+}
+
+/// Check `segments` on `num_threads` threads, calling `on_segment` with the
+/// index and outcome of each as it is checked.
+pub fn verify_segments<F>(segments: &[Segment], num_threads: usize, on_segment: F) -> Vec<bool>
+    where F: Fn(usize, bool) + Sync
+{
+    let mut results = vec![false; segments.len()];
+    if segments.is_empty() {
+        return results;
+    }
+
+    let num_threads = num_threads.max(1);
+    let chunk_size = (segments.len() + num_threads - 1) / num_threads;
+    let on_segment = &on_segment;
+
+    crossbeam::scope(|scope| {
+        for (chunk, (segments, results)) in segments.chunks(chunk_size)
+            .zip(results.chunks_mut(chunk_size))
+            .enumerate()
+        {
+            scope.spawn(move || {
+                for (j, (segment, result)) in segments.iter().zip(results.iter_mut()).enumerate() {
+                    *result = segment.verify();
+                    on_segment(chunk * chunk_size + j, *result);
+                }
+            });
+        }
+    });
+
+    results
+}
+
+fn parse_hash(s: &str) -> Option<BeaconHash>
+{
+    let bytes = hex::decode(s).ok()?;
+    if bytes.len() != BEACON_HASH_SIZE {
+        return None;
+    }
+
+    let mut hash = [0u8; BEACON_HASH_SIZE];
+    hash.copy_from_slice(&bytes);
+    Some(hash)
+}
+
+/// Check that `checkpoints` are the first checkpoints of the beacon, in
+/// order, starting from the beacon value.
+fn check_checkpoints(params: &BeaconParams, checkpoints: &[(u64, BeaconHash)]) -> Result<(), BeaconError>
+{
+    let interval = params.checkpoint_interval();
+    let num_checkpoints = params.iterations() / interval;
+
+    for (k, &(i, _)) in checkpoints.iter().enumerate() {
+        if i != (k as u64) * interval || k as u64 >= num_checkpoints {
+            return Err(BeaconError::UnexpectedCheckpoint(i));
+        }
+    }
+    match checkpoints.first() {
+        Some(&(_, hash)) if hash != params.value => Err(BeaconError::FirstCheckpointNotValue),
+        _ => Ok(()),
+    }
+}
+
+/// The state of a beacon computation: its parameters, the checkpoints so
+/// far and the result once it is finished.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BeaconState {
+    pub params: BeaconParams,
+    /// The iteration and hash of each checkpoint, in order.
+    pub checkpoints: Vec<(u64, BeaconHash)>,
+    pub result: Option<BeaconHash>,
+}
+
+impl BeaconState {
+    pub fn new(params: BeaconParams) -> Self
+    {
+        BeaconState {
+            params: params,
+            checkpoints: vec![],
+            result: None,
+        }
+    }
+
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, BeaconError>
+    {
+        let mut sources = vec![];
+        let mut value = None;
+        let mut iterations_log2 = None;
+        let mut checkpoint_interval_log2 = None;
+        let mut checkpoints = vec![];
+        let mut result = None;
+
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_number = n + 1;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            let parsed = match (fields.get(0), fields.len()) {
+                (None, _) => Some(()),
+                (Some(&"source"), 3) => hex::decode(fields[2]).ok().map(|v| sources.push(BeaconSource {
+                    name: fields[1].to_string(),
+                    value: v,
+                })),
+                (Some(&"value"), 2) => parse_hash(fields[1]).map(|h| value = Some(h)),
+                (Some(&"iterations_log2"), 2) => fields[1].parse().ok().map(|n| iterations_log2 = Some(n)),
+                (Some(&"checkpoint_interval_log2"), 2) => fields[1].parse().ok().map(|c| checkpoint_interval_log2 = Some(c)),
+                (Some(&"checkpoint"), 3) => match (fields[1].parse().ok(), parse_hash(fields[2])) {
+                    (Some(i), Some(h)) => {
+                        checkpoints.push((i, h));
+                        Some(())
+                    }
+                    _ => None,
+                },
+                (Some(&"result"), 2) => parse_hash(fields[1]).map(|h| result = Some(h)),
+                _ => None,
+            };
+            if parsed.is_none() {
+                return Err(BeaconError::InvalidLine(line_number));
+            }
+        }
+
+        let params = BeaconParams {
+            sources: sources,
+            value: value.ok_or(BeaconError::MissingEntry("value"))?,
+            iterations_log2: iterations_log2.ok_or(BeaconError::MissingEntry("iterations_log2"))?,
+            checkpoint_interval_log2: checkpoint_interval_log2.ok_or(BeaconError::MissingEntry("checkpoint_interval_log2"))?,
+        };
+        params.check()?;
+
+        Ok(BeaconState {
+            params: params,
+            checkpoints: checkpoints,
+            result: result,
+        })
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()>
+    {
+        self.params.write(writer)?;
+        for &(i, ref hash) in &self.checkpoints {
+            write_checkpoint(writer, i, hash)?;
+        }
+        if let Some(ref result) = self.result {
+            write_result(writer, result)?;
+        }
+
+        Ok(())
+    }
+
+    /// The last checkpoint, from which to resume the computation, after
+    /// checking that the checkpoints are in place.
+    pub fn last_checkpoint(&self) -> Result<Option<(u64, BeaconHash)>, BeaconError>
+    {
+        check_checkpoints(&self.params, &self.checkpoints)?;
+
+        Ok(self.checkpoints.last().cloned())
+    }
+}
+
+/// The contents of a beacon descriptor.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BeaconLog {
+    pub params: BeaconParams,
+    /// The iteration and hash of each checkpoint, in order.
+    pub checkpoints: Vec<(u64, BeaconHash)>,
+    pub result: BeaconHash,
+}
+
+impl BeaconLog {
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, BeaconError>
+    {
+        let state = BeaconState::read(reader)?;
+
+        Ok(BeaconLog {
+            params: state.params,
+            checkpoints: state.checkpoints,
+            result: state.result.ok_or(BeaconError::MissingEntry("result"))?,
+        })
+    }
+
+    /// Split the hash chain into segments, after checking that the
+    /// checkpoints are all there, in order, starting from the beacon value.
+    pub fn segments(&self) -> Result<Vec<Segment>, BeaconError>
+    {
+        let interval = self.params.checkpoint_interval();
+        let num_checkpoints = self.params.iterations() / interval;
+
+        check_checkpoints(&self.params, &self.checkpoints)?;
+        if (self.checkpoints.len() as u64) < num_checkpoints {
+            return Err(BeaconError::UnexpectedCheckpoint((self.checkpoints.len() as u64) * interval));
+        }
+
+        let ends = self.checkpoints[1..].iter()
+            .cloned()
+            .chain(Some((self.params.iterations(), self.result)));
+
+        Ok(self.checkpoints.iter().zip(ends).map(|(&(start, start_hash), (end, end_hash))| Segment {
+            start: start,
+            start_hash: start_hash,
+            end: end,
+            end_hash: end_hash,
+        }).collect())
+    }
+}
+
+#[test]
+fn test_hash_chain() {
+    // SHA-256 of 32 zero bytes, and of that.
+    let mut hash = [0u8; BEACON_HASH_SIZE];
+    hash_chain(&mut hash, 1);
+    assert_eq!(hex::encode(&hash), "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925");
+    hash_chain(&mut hash, 1);
+    assert_eq!(hex::encode(&hash), "2b32db6c2c0a6235fb1397e8225ea85e0f0e6e8c7b126d0016ccbde0e667151e");
+}
+
+#[test]
+fn test_beacon_log() {
+    let params = BeaconParams {
+        sources: vec![],
+        value: [7u8; BEACON_HASH_SIZE],
+        iterations_log2: 10,
+        checkpoint_interval_log2: 6,
+    };
+
+    let mut descriptor = vec![];
+    params.write(&mut descriptor).unwrap();
+    let result = compute(&params, |i, hash| write_checkpoint(&mut descriptor, i, hash).unwrap());
+    write_result(&mut descriptor, &result).unwrap();
+
+    let mut expected = params.value;
+    hash_chain(&mut expected, 1 << 10);
+    assert_eq!(result, expected);
+
+    let log = BeaconLog::read(&descriptor[..]).unwrap();
+    assert_eq!(log.params, params);
+    assert_eq!(log.checkpoints.len(), 16);
+    assert_eq!(log.result, result);
+
+    let segments = log.segments().unwrap();
+    assert_eq!(segments.len(), 16);
+    assert!(verify_segments(&segments, 3, |_, _| ()).iter().all(|&ok| ok));
+
+    // A wrong checkpoint fails the segments on either side of it.
+    let mut bad_log = log.clone();
+    bad_log.checkpoints[5].1[0] ^= 1;
+    let results = verify_segments(&bad_log.segments().unwrap(), 3, |_, _| ());
+    for (k, &ok) in results.iter().enumerate() {
+        assert_eq!(ok, k != 4 && k != 5);
+    }
+
+    // As does a wrong result for the last segment.
+    let mut bad_log = log.clone();
+    bad_log.result[0] ^= 1;
+    let results = verify_segments(&bad_log.segments().unwrap(), 4, |_, _| ());
+    assert_eq!(results.iter().filter(|&&ok| !ok).count(), 1);
+    assert!(!results[15]);
+
+    // Missing checkpoints are rejected.
+    let mut bad_log = log.clone();
+    bad_log.checkpoints.remove(3);
+    match bad_log.segments() {
+        Err(BeaconError::UnexpectedCheckpoint(i)) => assert_eq!(i, 4 * 64),
+        _ => panic!("expected a missing checkpoint"),
+    }
+
+    assert!(BeaconLog::read(&b"value 00\n"[..]).is_err());
+}
+
+#[test]
+fn test_beacon_resume() {
+    let params = BeaconParams {
+        sources: vec![],
+        value: [7u8; BEACON_HASH_SIZE],
+        iterations_log2: 10,
+        checkpoint_interval_log2: 6,
+    };
+
+    let mut state = BeaconState::new(params.clone());
+    let result = compute(&params, |i, hash| state.checkpoints.push((i, *hash)));
+    state.result = Some(result);
+
+    // The state is written and read in the descriptor format.
+    let mut data = vec![];
+    state.write(&mut data).unwrap();
+    assert!(BeaconState::read(&data[..]).unwrap() == state);
+    assert_eq!(BeaconLog::read(&data[..]).unwrap().result, result);
+
+    // Resuming from any checkpoint gives the same checkpoints and result.
+    for k in 1..17 {
+        let mut partial = BeaconState::new(params.clone());
+        partial.checkpoints = state.checkpoints[0..k].to_vec();
+
+        let mut data = vec![];
+        partial.write(&mut data).unwrap();
+        let mut partial = BeaconState::read(&data[..]).unwrap();
+        assert_eq!(partial.result, None);
+
+        let (i, hash) = partial.last_checkpoint().unwrap().unwrap();
+        assert_eq!(i, (k as u64 - 1) * 64);
+        let resumed = resume(&params, i, hash, |i, hash| partial.checkpoints.push((i, *hash)));
+        assert_eq!(resumed, result);
+        assert!(partial.checkpoints == state.checkpoints);
+    }
+
+    assert_eq!(BeaconState::new(params.clone()).last_checkpoint().unwrap(), None);
+
+    // A state whose checkpoints do not start from the value is rejected.
+    let mut bad_state = BeaconState::new(params.clone());
+    bad_state.checkpoints.push((0, [8u8; BEACON_HASH_SIZE]));
+    match bad_state.last_checkpoint() {
+        Err(BeaconError::FirstCheckpointNotValue) => (),
+        _ => panic!("expected the first checkpoint to be rejected"),
+    }
+}
+
+#[test]
+fn test_beacon_keypair() {
+    use bn256::Bn256;
+    use rand::RngCore;
+
+    let result = [42u8; BEACON_HASH_SIZE];
+    let digest = [1u8; 64];
+
+    // The seed is the result itself.
+    let mut rng = beacon_rng(&result);
+    let mut expected = ChaChaRng::from_seed(result);
+    assert_eq!(rng.next_u64(), expected.next_u64());
+
+    let (pubkey, _) = beacon_keypair::<Bn256>(&result, &digest);
+    let (same_pubkey, _) = beacon_keypair::<Bn256>(&result, &digest);
+    assert!(pubkey == same_pubkey);
+
+    let (other_pubkey, _) = beacon_keypair::<Bn256>(&[43u8; BEACON_HASH_SIZE], &digest);
+    assert!(pubkey != other_pubkey);
+}
+
+#[test]
+fn test_beacon_sources() {
+    let sources = vec![
+        BeaconSource {
+            name: "bitcoin-block-514200".to_string(),
+            value: hex::decode("00000000000000000034b33e842ac1c50456abe5fa92b60f6b3dfc5d247f7b58").unwrap(),
+        },
+        BeaconSource {
+            name: "lottery".to_string(),
+            value: b"04 11 23 32 45 08".to_vec(),
+        },
+    ];
+
+    // Computed independently.
+    let value = combine_sources(&sources);
+    assert_eq!(hex::encode(&value), "baee34bcfdd8a3dfa572c594b3cccfb1c0640daa13500daeb076085810c4115f");
+
+    // The order of the sources matters, and so does where one ends.
+    let reversed: Vec<_> = sources.iter().rev().cloned().collect();
+    assert!(combine_sources(&reversed) != value);
+    let mut moved = sources.clone();
+    moved[0].name.push('0');
+    moved[1].name.remove(0);
+    assert!(combine_sources(&moved) != value);
+
+    let params = BeaconParams {
+        sources: sources.clone(),
+        value: value,
+        iterations_log2: 4,
+        checkpoint_interval_log2: 2,
+    };
+    assert!(params.check().is_ok());
+
+    let mut state = BeaconState::new(params.clone());
+    let result = compute(&params, |i, hash| state.checkpoints.push((i, *hash)));
+    state.result = Some(result);
+    let mut data = vec![];
+    state.write(&mut data).unwrap();
+    let log = BeaconLog::read(&data[..]).unwrap();
+    assert!(log.params == params);
+    assert!(verify_segments(&log.segments().unwrap(), 2, |_, _| ()).iter().all(|&ok| ok));
+
+    // A value that does not match its sources is rejected.
+    let mut bad_params = params.clone();
+    bad_params.sources[1].value[0] ^= 1;
+    match bad_params.check() {
+        Err(BeaconError::SourcesMismatch) => (),
+        _ => panic!("expected the sources not to match"),
+    }
+    let mut data = vec![];
+    BeaconState::new(bad_params).write(&mut data).unwrap();
+    assert!(BeaconState::read(&data[..]).is_err());
+
+    // A source with an empty value could not be read back.
+    let mut empty_sources = sources.clone();
+    empty_sources[1].value.clear();
+    let empty_params = BeaconParams {
+        value: combine_sources(&empty_sources),
+        sources: empty_sources,
+        iterations_log2: 4,
+        checkpoint_interval_log2: 2,
+    };
+    match empty_params.check() {
+        Err(BeaconError::InvalidSource(ref name)) if name == "lottery" => (),
+        _ => panic!("expected an invalid source"),
+    }
+}
@@ -0,0 +1,384 @@
+//! Conversion of BN256 challenges and responses to and from the layout of
+//! the bellman-ce powers of tau, as read by phase2-bn254 and other Groth16
+//! toolchains. Files in that layout have no header:
+//!
+//! * a challenge is the 64-byte hash of the previous response followed by the
+//!   uncompressed accumulator
+//! * a response is the 64-byte hash of the challenge, the compressed
+//!   accumulator and the public key, which is always uncompressed
+//!
+//! with the accumulator and public key in the same order as in this crate.
+//!
+//! Points are affine with big-endian coordinates, the imaginary part first in
+//! G2. Compressed points hold x only, with the top bit of the first byte set
+//! if y is the larger of ±y (comparing the imaginary part first in G2). The
+//! next bit marks the point at infinity, whose other bits are zero.
+//!
+//! The hashes are copied unchanged, so they refer to the files in the
+//! encoding they were computed over. bellman-ce hashes its own encoding: a
+//! response it computed starts with the BLAKE2b hash of the bellman-ce
+//! challenge, the one `export_bellman` prints, and its proofs of knowledge
+//! are over that hash. `verify_transform` hashes the challenge in this
+//! crate's encoding instead, so it rejects such a response once imported.
+
+use bn::{AffineG1, AffineG2, Fq, Fq2, G1, G2};
+use std::io::{self, Read, Write};
+
+use bn256::Bn256;
+use configuration::Configuration;
+use engine::{CurvePoint, Engine};
+use super::{
+    check_all, Accumulator, CheckForCorrectness, DeserializationError, PointError, PublicKey,
+    UseCompression
+};
+
+const FQ_SIZE: usize = 32;
+
+const GREATEST: u8 = 1 << 7;
+const INFINITY: u8 = 1 << 6;
+
+/// The size of a public key in the bellman-ce layout.
+pub const PUBLIC_KEY_SIZE: usize = 6 * 2 * FQ_SIZE + 3 * 4 * FQ_SIZE;
+
+/// The size of an accumulator in the bellman-ce layout, without the hash
+/// that precedes it.
+pub fn accumulator_size(config: &Configuration, compression: UseCompression) -> usize
+{
+    let (g1_size, g2_size) = match compression {
+        UseCompression::Yes => (FQ_SIZE, 2 * FQ_SIZE),
+        UseCompression::No => (2 * FQ_SIZE, 4 * FQ_SIZE),
+    };
+
+    (config.num_powers_g1 + 2 * config.num_powers) * g1_size + (config.num_powers + 1) * g2_size
+}
+
+/// Write the accumulator in the bellman-ce layout.
+pub fn write_accumulator<W: Write>(
+    writer: &mut W,
+    acc: &Accumulator<Bn256>,
+    compression: UseCompression
+) -> io::Result<()>
+{
+    for p in &acc.tau_powers_g1 {
+        write_g1(writer, p, compression)?;
+    }
+    for p in &acc.tau_powers_g2 {
+        write_g2(writer, p, compression)?;
+    }
+    for p in acc.alpha_tau_powers_g1.iter().chain(acc.beta_tau_powers_g1.iter()) {
+        write_g1(writer, p, compression)?;
+    }
+    write_g2(writer, &acc.beta_g2, compression)?;
+
+    Ok(())
+}
+
+/// Read an accumulator in the bellman-ce layout. `checked` indicates whether
+/// each point should be checked to be on the curve, in the prime order
+/// subgroup and not the point at infinity, as in `Accumulator::deserialize`.
+pub fn read_accumulator<R: Read>(
+    reader: &mut R,
+    config: Configuration,
+    compression: UseCompression,
+    checked: CheckForCorrectness
+) -> Result<Accumulator<Bn256>, DeserializationError>
+{
+    fn read_points<R: Read, C: CurvePoint>(
+        reader: &mut R,
+        size: usize,
+        read: fn(&mut R, UseCompression) -> Result<C, DeserializationError>,
+        compression: UseCompression,
+        checked: CheckForCorrectness,
+        section: &'static str
+    ) -> Result<Vec<C>, DeserializationError>
+    {
+        let points = (0..size)
+            .map(|_| read(reader, compression))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let CheckForCorrectness::Yes = checked {
+            check_all(&points, section, 0)?;
+        }
+
+        Ok(points)
+    }
+
+    assert_eq!(config.curve, Bn256::CURVE, "configuration is for a different curve");
+
+    Ok(Accumulator {
+        tau_powers_g1: read_points(
+            reader, config.num_powers_g1, read_g1, compression, checked, "tau_powers_g1")?,
+        tau_powers_g2: read_points(
+            reader, config.num_powers, read_g2, compression, checked, "tau_powers_g2")?,
+        alpha_tau_powers_g1: read_points(
+            reader, config.num_powers, read_g1, compression, checked, "alpha_tau_powers_g1")?,
+        beta_tau_powers_g1: read_points(
+            reader, config.num_powers, read_g1, compression, checked, "beta_tau_powers_g1")?,
+        beta_g2: read_points(reader, 1, read_g2, compression, checked, "beta_g2")?[0],
+        config: config,
+    })
+}
+
+/// Write the public key in the bellman-ce layout, uncompressed.
+pub fn write_public_key<W: Write>(writer: &mut W, key: &PublicKey<Bn256>) -> io::Result<()>
+{
+    let g1s = [key.tau_g1, key.alpha_g1, key.beta_g1];
+    for &(s, s_x) in &g1s {
+        write_g1(writer, &s, UseCompression::No)?;
+        write_g1(writer, &s_x, UseCompression::No)?;
+    }
+    for p in &[key.tau_g2, key.alpha_g2, key.beta_g2] {
+        write_g2(writer, p, UseCompression::No)?;
+    }
+
+    Ok(())
+}
+
+/// Read a public key in the bellman-ce layout. As in
+/// `PublicKey::deserialize`, every point is checked.
+pub fn read_public_key<R: Read>(reader: &mut R) -> Result<PublicKey<Bn256>, DeserializationError>
+{
+    fn checked<C: CurvePoint>(v: C, index: usize) -> Result<C, DeserializationError>
+    {
+        match v.check() {
+            Ok(()) => Ok(v),
+            Err(PointError::PointAtInfinity) => Err(DeserializationError::PointAtInfinity),
+            Err(e) => Err(DeserializationError::InvalidPoint("public_key", index, e))
+        }
+    }
+
+    let mut g1s = [G1::zero(); 6];
+    for (i, p) in g1s.iter_mut().enumerate() {
+        *p = checked(read_g1(reader, UseCompression::No)?, i)?;
+    }
+    let mut g2s = [G2::zero(); 3];
+    for (i, p) in g2s.iter_mut().enumerate() {
+        *p = checked(read_g2(reader, UseCompression::No)?, 6 + i)?;
+    }
+
+    Ok(PublicKey {
+        tau_g1: (g1s[0], g1s[1]),
+        alpha_g1: (g1s[2], g1s[3]),
+        beta_g1: (g1s[4], g1s[5]),
+        tau_g2: g2s[0],
+        alpha_g2: g2s[1],
+        beta_g2: g2s[2],
+    })
+}
+
+fn fq_to_bytes(x: Fq) -> [u8; FQ_SIZE]
+{
+    let mut bytes = [0u8; FQ_SIZE];
+    x.into_u256().to_big_endian(&mut bytes).expect("32 bytes is enough for Fq");
+    bytes
+}
+
+fn fq_from_bytes(bytes: &[u8]) -> Result<Fq, DeserializationError>
+{
+    Fq::from_slice(bytes).map_err(|_| DeserializationError::InvalidEncoding)
+}
+
+/// Whether y is the larger of ±y as integers.
+fn is_greatest(y: Fq) -> bool
+{
+    fq_to_bytes(y) > fq_to_bytes(-y)
+}
+
+/// As `is_greatest`, ordering by the imaginary part first.
+fn is_greatest_fq2(y: Fq2) -> bool
+{
+    if y.imaginary() == Fq::zero() {
+        is_greatest(y.real())
+    } else {
+        is_greatest(y.imaginary())
+    }
+}
+
+fn write_g1<W: Write>(writer: &mut W, p: &G1, compression: UseCompression) -> io::Result<()>
+{
+    let mut bytes = [0u8; 2 * FQ_SIZE];
+    let size = match compression {
+        UseCompression::Yes => FQ_SIZE,
+        UseCompression::No => 2 * FQ_SIZE,
+    };
+
+    match AffineG1::from_jacobian(*p) {
+        Some(p) => {
+            bytes[0..FQ_SIZE].copy_from_slice(&fq_to_bytes(p.x()));
+            bytes[FQ_SIZE..].copy_from_slice(&fq_to_bytes(p.y()));
+            if compression == UseCompression::Yes && is_greatest(p.y()) {
+                bytes[0] |= GREATEST;
+            }
+        }
+        None => bytes[0] = INFINITY
+    }
+    writer.write_all(&bytes[0..size])
+}
+
+fn write_g2<W: Write>(writer: &mut W, p: &G2, compression: UseCompression) -> io::Result<()>
+{
+    let mut bytes = [0u8; 4 * FQ_SIZE];
+    let size = match compression {
+        UseCompression::Yes => 2 * FQ_SIZE,
+        UseCompression::No => 4 * FQ_SIZE,
+    };
+
+    match AffineG2::from_jacobian(*p) {
+        Some(p) => {
+            bytes[0..FQ_SIZE].copy_from_slice(&fq_to_bytes(p.x().imaginary()));
+            bytes[FQ_SIZE..(2 * FQ_SIZE)].copy_from_slice(&fq_to_bytes(p.x().real()));
+            bytes[(2 * FQ_SIZE)..(3 * FQ_SIZE)].copy_from_slice(&fq_to_bytes(p.y().imaginary()));
+            bytes[(3 * FQ_SIZE)..].copy_from_slice(&fq_to_bytes(p.y().real()));
+            if compression == UseCompression::Yes && is_greatest_fq2(p.y()) {
+                bytes[0] |= GREATEST;
+            }
+        }
+        None => bytes[0] = INFINITY
+    }
+    writer.write_all(&bytes[0..size])
+}
+
+/// Reads the flags from the first byte of a point, clearing them. Returns
+/// whether the point is at infinity and whether y is the greatest.
+fn read_flags(bytes: &mut [u8], compression: UseCompression) -> Result<(bool, bool), DeserializationError>
+{
+    let infinity = bytes[0] & INFINITY != 0;
+    let greatest = bytes[0] & GREATEST != 0;
+    bytes[0] &= !(INFINITY | GREATEST);
+
+    if (greatest && (infinity || compression == UseCompression::No))
+        || (infinity && bytes.iter().any(|&b| b != 0))
+    {
+        return Err(DeserializationError::InvalidEncoding);
+    }
+
+    Ok((infinity, greatest))
+}
+
+fn read_g1<R: Read>(reader: &mut R, compression: UseCompression) -> Result<G1, DeserializationError>
+{
+    let mut bytes = [0u8; 2 * FQ_SIZE];
+    let bytes = match compression {
+        UseCompression::Yes => &mut bytes[0..FQ_SIZE],
+        UseCompression::No => &mut bytes[..],
+    };
+    reader.read_exact(bytes)?;
+
+    let (infinity, greatest) = read_flags(bytes, compression)?;
+    if infinity {
+        return Ok(G1::zero());
+    }
+
+    let x = fq_from_bytes(&bytes[0..FQ_SIZE])?;
+    let y = match compression {
+        UseCompression::No => fq_from_bytes(&bytes[FQ_SIZE..])?,
+        UseCompression::Yes => {
+            let y = (x * x * x + G1::b()).sqrt().ok_or(DeserializationError::InvalidEncoding)?;
+            if is_greatest(y) == greatest { y } else { -y }
+        }
+    };
+    Ok(G1::new(x, y, Fq::one()))
+}
+
+fn read_g2<R: Read>(reader: &mut R, compression: UseCompression) -> Result<G2, DeserializationError>
+{
+    let mut bytes = [0u8; 4 * FQ_SIZE];
+    let bytes = match compression {
+        UseCompression::Yes => &mut bytes[0..(2 * FQ_SIZE)],
+        UseCompression::No => &mut bytes[..],
+    };
+    reader.read_exact(bytes)?;
+
+    let (infinity, greatest) = read_flags(bytes, compression)?;
+    if infinity {
+        return Ok(G2::zero());
+    }
+
+    let x = Fq2::new(
+        fq_from_bytes(&bytes[FQ_SIZE..(2 * FQ_SIZE)])?,
+        fq_from_bytes(&bytes[0..FQ_SIZE])?);
+    let y = match compression {
+        UseCompression::No => Fq2::new(
+            fq_from_bytes(&bytes[(3 * FQ_SIZE)..])?,
+            fq_from_bytes(&bytes[(2 * FQ_SIZE)..(3 * FQ_SIZE)])?),
+        UseCompression::Yes => {
+            let y = (x * x * x + G2::b()).sqrt().ok_or(DeserializationError::InvalidEncoding)?;
+            if is_greatest_fq2(y) == greatest { y } else { -y }
+        }
+    };
+    Ok(G2::new(x, y, Fq2::one()))
+}
+
+#[test]
+fn test_bellman_accumulator() {
+    use rand::{thread_rng, Rng};
+    use configuration::*;
+    use super::keypair;
+
+    let config = Configuration::for_engine::<Bn256>(16);
+    let rng = &mut thread_rng();
+    let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+    let mut acc = Accumulator::<Bn256>::new(config);
+    let (pk, sk) = keypair::<Bn256, _>(rng, &digest);
+    acc.transform(&sk);
+
+    for &compression in &[UseCompression::No, UseCompression::Yes] {
+        let mut data = vec![];
+        write_accumulator(&mut data, &acc, compression).unwrap();
+        assert_eq!(data.len(), accumulator_size(&config, compression));
+
+        let read = read_accumulator(&mut &data[..], config, compression, CheckForCorrectness::Yes).unwrap();
+        assert!(read == acc);
+    }
+    assert_eq!(accumulator_size(&config, UseCompression::No), 63 * 64 + 17 * 128);
+    assert_eq!(accumulator_size(&config, UseCompression::Yes), 63 * 32 + 17 * 64);
+
+    let mut data = vec![];
+    write_public_key(&mut data, &pk).unwrap();
+    assert_eq!(data.len(), PUBLIC_KEY_SIZE);
+    assert!(read_public_key(&mut &data[..]).unwrap() == pk);
+
+    // Points at infinity are not allowed in the public key.
+    for b in &mut data[0..(2 * FQ_SIZE)] {
+        *b = 0;
+    }
+    data[0] = INFINITY;
+    assert!(match read_public_key(&mut &data[..]) {
+        Err(DeserializationError::PointAtInfinity) => true,
+        _ => false
+    });
+}
+
+#[test]
+fn test_bellman_points() {
+    // The generator of G1 is (1, 2), and 2 is the smaller of ±2.
+    let mut data = vec![];
+    write_g1(&mut data, &G1::one(), UseCompression::No).unwrap();
+    let mut expected = [0u8; 2 * FQ_SIZE];
+    expected[FQ_SIZE - 1] = 1;
+    expected[2 * FQ_SIZE - 1] = 2;
+    assert_eq!(&data[..], &expected[..]);
+
+    let mut data = vec![];
+    write_g1(&mut data, &-G1::one(), UseCompression::Yes).unwrap();
+    let mut expected = [0u8; FQ_SIZE];
+    expected[0] = GREATEST;
+    expected[FQ_SIZE - 1] = 1;
+    assert_eq!(&data[..], &expected[..]);
+    assert!(read_g1(&mut &data[..], UseCompression::Yes).unwrap() == -G1::one());
+
+    for &compression in &[UseCompression::No, UseCompression::Yes] {
+        for p in &[G2::one(), -G2::one(), G2::zero()] {
+            let mut data = vec![];
+            write_g2(&mut data, p, compression).unwrap();
+            assert!(read_g2(&mut &data[..], compression).unwrap() == *p);
+        }
+    }
+
+    // The greatest flag is only valid on compressed points.
+    let mut data = [0u8; 2 * FQ_SIZE];
+    data[0] = GREATEST;
+    data[FQ_SIZE - 1] = 1;
+    data[2 * FQ_SIZE - 1] = 2;
+    assert!(read_g1(&mut &data[..], UseCompression::No).is_err());
+}
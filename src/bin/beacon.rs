@@ -1,108 +1,134 @@
 extern crate powersoftau;
 extern crate rand;
 extern crate blake2;
-extern crate byteorder;
-
-#[macro_use]
-extern crate hex_literal;
-
-extern crate crypto;
+extern crate getopts;
+extern crate hex;
 
 use powersoftau::*;
+use powersoftau::beacon::*;
+use powersoftau::bls12::Bls12;
+use powersoftau::bn256::Bn256;
+use powersoftau::cmd_utils::*;
+use powersoftau::configuration::Configuration;
+use powersoftau::engine::{CurveId, Engine};
 
-use std::fs::OpenOptions;
+use rand::Rng;
+use std::fs::{self, File};
 use std::io::{Read, BufReader, Write, BufWriter};
+use std::path::{Path, PathBuf};
 
-fn main() {
-    let config = cmd_utils::parse_simple_options();
-    // Create an RNG based on the outcome of the random beacon
-    let mut rng = {
-        use byteorder::{ReadBytesExt, BigEndian};
-        use rand::{SeedableRng};
-        use rand::chacha::ChaChaRng;
-        use crypto::sha2::Sha256;
-        use crypto::digest::Digest;
-
-        // Place block hash here (block number #514200)
-        let mut cur_hash: [u8; 32] = hex!("00000000000000000034b33e842ac1c50456abe5fa92b60f6b3dfc5d247f7b58");
-
-        // Performs 2^n hash iterations over it
-        const N: usize = 42;
-
-        for i in 0..(1u64<<N) {
-            // Print 1024 of the interstitial states
-            // so that verification can be
-            // parallelized
-            if i % (1u64<<(N-10)) == 0 {
-                print!("{}: ", i);
-                for b in cur_hash.iter() {
-                    print!("{:02x}", b);
-                }
-                println!("");
-            }
+/// Iterations of SHA-256 applied to the beacon value by default, as log2.
+const DEFAULT_ITERATIONS_LOG2: usize = 42;
 
-            let mut h = Sha256::new();
-            h.input(&cur_hash);
-            h.result(&mut cur_hash);
-        }
+/// Checkpoints printed by default, as log2, so that verification can be
+/// parallelized.
+const DEFAULT_CHECKPOINTS_LOG2: usize = 10;
 
-        print!("Final result of beacon: ");
-        for b in cur_hash.iter() {
-            print!("{:02x}", b);
-        }
-        println!("");
+fn parse_hex_value(s: &str) -> BeaconHash
+{
+    let bytes = hex::decode(s.trim())
+        .unwrap_or_else(|e| panic!("invalid beacon value: {}", e));
+    if bytes.len() != BEACON_HASH_SIZE {
+        panic!("the beacon value should be {} bytes, but it's {}", BEACON_HASH_SIZE, bytes.len());
+    }
+    let mut value = [0u8; BEACON_HASH_SIZE];
+    value.copy_from_slice(&bytes);
+    value
+}
 
-        let mut digest = &cur_hash[..];
+/// Parse a '--beacon-source NAME=HEX' option.
+fn parse_source(s: &str) -> BeaconSource
+{
+    let mut parts = s.splitn(2, '=');
+    let name = parts.next().unwrap();
+    let hex_value = parts.next()
+        .unwrap_or_else(|| panic!("beacon source `{}` should be NAME=HEX", s));
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        panic!("the name of beacon source `{}` should be non-empty and without whitespace", s);
+    }
+    if hex_value.trim().is_empty() {
+        panic!("the value of beacon source `{}` should not be empty", name);
+    }
 
-        let mut seed : [u8;32] = [0;32];
-        for i in 0..8 {
-            let bytes = digest.read_u32::<BigEndian>().unwrap().to_be_bytes();
-            seed[(4 * i) .. ((4 * i) + 4)].copy_from_slice(&bytes);
-        }
+    BeaconSource {
+        name: name.to_string(),
+        value: hex::decode(hex_value.trim())
+            .unwrap_or_else(|e| panic!("invalid value for beacon source `{}`: {}", name, e)),
+    }
+}
 
-        ChaChaRng::from_seed(seed)
-    };
+fn beacon_params(matches: &getopts::Matches) -> BeaconParams
+{
+    let sources: Vec<BeaconSource> = matches.opt_strs("beacon-source").iter()
+        .map(|s| parse_source(s))
+        .collect();
 
-    // Try to load `./challenge` from disk.
-    let reader = OpenOptions::new()
-                            .read(true)
-                            .open("challenge").expect("unable open `./challenge` in this directory");
+    let value = match (get_opt::<String>(matches, "beacon-hash"), get_opt::<String>(matches, "beacon-hash-file"), sources.is_empty()) {
+        (Some(s), None, true) => parse_hex_value(&s),
+        (None, Some(path), true) => parse_hex_value(&fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("unable to read beacon value from `{}`: {}", path, e))),
+        (None, None, false) => combine_sources(&sources),
+        _ => panic!("the beacon value must be given with exactly one of --beacon-hash, --beacon-hash-file and --beacon-source"),
+    };
 
-    {
-        let metadata = reader.metadata().expect("unable to get filesystem metadata for `./challenge`");
-        if metadata.len() != (config.accumulator_size_bytes as u64) {
-            panic!(
-                "The size of `./challenge` should be {}, but it's {}, so something isn't right.",
-                config.accumulator_size_bytes,
-                metadata.len());
-        }
-    }
+    let iterations_log2 = get_opt_default(matches, "iterations-log2", DEFAULT_ITERATIONS_LOG2);
+    let checkpoint_interval_log2 = get_opt_default(
+        matches,
+        "checkpoint-interval-log2",
+        iterations_log2.saturating_sub(DEFAULT_CHECKPOINTS_LOG2));
 
-    let reader = BufReader::new(reader);
-    let mut reader = HashReader::new(reader);
+    let params = BeaconParams {
+        sources: sources,
+        value: value,
+        iterations_log2: iterations_log2,
+        checkpoint_interval_log2: checkpoint_interval_log2,
+    };
+    params.check().unwrap_or_else(|e| panic!("invalid beacon parameters: {}", e));
 
-    // Create `./response` in this directory
-    let writer = OpenOptions::new()
-                            .read(false)
-                            .write(true)
-                            .create_new(true)
-                            .open("response").expect("unable to create `./response` in this directory");
+    params
+}
 
-    let writer = BufWriter::new(writer);
-    let mut writer = HashWriter::new(writer);
+/// Save the state of the beacon, replacing the previous one only once it is
+/// complete on disk, so that an interruption leaves a state to resume from.
+fn save_state(state: &BeaconState, path: &Path)
+{
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
 
-    println!("Reading `./challenge` into memory...");
+    let file = File::create(&tmp_path)
+        .unwrap_or_else(|e| panic!("unable to create `{}`: {}", tmp_path.display(), e));
+    let mut writer = BufWriter::new(file);
+    state.write(&mut writer)
+        .and_then(|_| writer.flush())
+        .and_then(|_| writer.get_ref().sync_all())
+        .and_then(|_| fs::rename(&tmp_path, path))
+        .unwrap_or_else(|e| panic!("unable to save beacon state to `{}`: {}", path.display(), e));
+}
 
-    // Read the BLAKE2b hash of the previous contribution
-    {
-        // We don't need to do anything with it, but it's important for
-        // the hash chain.
-        let mut tmp = [0; 64];
-        reader.read_exact(&mut tmp).expect("unable to read BLAKE2b hash of previous contribution");
+/// Create an output file, replacing the one an interrupted run may have left
+/// if `resuming`.
+fn create_output(files: &FileOptions, path: &Path, resuming: bool) -> File
+{
+    if resuming {
+        File::create(path).unwrap_or_else(|e| panic!("unable to create `{}`: {}", path.display(), e))
+    } else {
+        files.create_path(path)
     }
+}
 
+/// Transform the accumulator read from the challenge with a keypair drawn
+/// from the beacon, and write the result and the public key to the response.
+fn contribute<E: Engine, R: Rng>(
+    config: Configuration,
+    mut reader: HashReader<BufReader<File>>,
+    writer: &mut HashWriter<BufWriter<File>>,
+    rng: &mut R,
+    response_path: &Path
+)
+{
     // Load the current accumulator into memory
-    let mut current_accumulator = Accumulator::deserialize(
+    let mut current_accumulator = Accumulator::<E>::deserialize(
         config,
         &mut reader,
         UseCompression::No,
@@ -113,29 +139,161 @@ fn main() {
     let current_accumulator_hash = reader.into_hash();
 
     // Construct our keypair using the RNG we created above
-    let (pubkey, privkey) = keypair(&mut rng, current_accumulator_hash.as_ref());
+    let (pubkey, privkey) = keypair::<E, _>(rng, current_accumulator_hash.as_ref());
 
     // Perform the transformation
     println!("Computing, this could take a while...");
     current_accumulator.transform(&privkey);
-    println!("Writing your contribution to `./response`...");
+    println!("Writing your contribution to `{}`...", response_path.display());
 
     // Write the hash of the input accumulator
     writer.write_all(&current_accumulator_hash.as_ref()).expect("unable to write BLAKE2b hash of input accumulator");
 
     // Write the transformed accumulator (in compressed form, to save upload bandwidth for disadvantaged
     // players.)
-    current_accumulator.serialize(&mut writer, UseCompression::Yes).expect("unable to write transformed accumulator");
+    current_accumulator.serialize(writer, UseCompression::Yes).expect("unable to write transformed accumulator");
 
     // Write the public key
-    pubkey.serialize(&mut writer).expect("unable to write public key");
+    pubkey.serialize(writer).expect("unable to write public key");
+}
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[CHALLENGE], &[RESPONSE]);
+    files.add_options(&mut opts);
+    opts.optopt("", "beacon-hash", "the beacon value, as 32 bytes in hex", "HEX");
+    opts.optopt("", "beacon-hash-file", "file holding the beacon value, as 32 bytes in hex", "PATH");
+    opts.optmulti("", "beacon-source", "a source of the beacon value, which combines all of them in order", "NAME=HEX");
+    opts.optopt("", "iterations-log2", &format!("log2 of the number of SHA-256 iterations (default: {})", DEFAULT_ITERATIONS_LOG2), "N");
+    opts.optopt("", "checkpoint-interval-log2", &format!("log2 of the number of iterations between checkpoints (default: N - {})", DEFAULT_CHECKPOINTS_LOG2), "C");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    let params = beacon_params(&matches);
+
+    let response_path = files.path(&RESPONSE);
+    let descriptor_path = descriptor_path(response_path);
+
+    // Resume from the state left by an interrupted run, if any
+    let state_path = state_path(response_path);
+    let resuming = state_path.exists();
+    let mut state = if resuming {
+        let reader = BufReader::new(File::open(&state_path)
+            .unwrap_or_else(|e| panic!("unable to open `{}`: {}", state_path.display(), e)));
+        let state = BeaconState::read(reader)
+            .unwrap_or_else(|e| panic!("unable to read beacon state `{}`: {}", state_path.display(), e));
+        if state.params != params {
+            panic!(
+                "`{}` is the state of a beacon with other parameters; remove it to start again",
+                state_path.display());
+        }
+        state
+    } else {
+        BeaconState::new(params.clone())
+    };
+
+    // An interrupted run may have left the descriptor and the response
+    // behind, which are written again from the state.
+    if !resuming && descriptor_path.exists() && !matches.opt_present("force") {
+        panic!("unable to create `{}`: it already exists (use --force to overwrite it)", descriptor_path.display());
+    }
+
+    let cur_hash = match state.result {
+        Some(result) => {
+            println!("The beacon was already computed in `{}`", state_path.display());
+            result
+        }
+        None => {
+            let last_checkpoint = state.last_checkpoint()
+                .unwrap_or_else(|e| panic!("invalid beacon state `{}`: {}", state_path.display(), e));
+
+            // Save each checkpoint, so that an interrupted run can resume
+            // from the last one
+            let mut record = |i: u64, hash: &BeaconHash| {
+                // Print the interstitial states so that verification can be
+                // parallelized
+                println!("{}: {}", i, hex::encode(hash));
+                state.checkpoints.push((i, *hash));
+                save_state(&state, &state_path);
+            };
+
+            // Performs 2^n hash iterations over the beacon value
+            let result = match last_checkpoint {
+                Some((i, hash)) => {
+                    println!("Resuming from the checkpoint at iteration {} in `{}`", i, state_path.display());
+                    resume(&params, i, hash, &mut record)
+                }
+                None => compute(&params, &mut record),
+            };
+
+            state.result = Some(result);
+            save_state(&state, &state_path);
+            result
+        }
+    };
+
+    println!("Final result of beacon: {}", hex::encode(&cur_hash));
+
+    // Record the parameters, checkpoints and result next to the response
+    let mut descriptor = BufWriter::new(create_output(&files, &descriptor_path, resuming));
+    state.write(&mut descriptor)
+        .and_then(|_| descriptor.flush())
+        .expect("unable to write the beacon descriptor");
+    println!("The beacon parameters and checkpoints have been written to `{}`", descriptor_path.display());
+
+    // Create an RNG based on the outcome of the random beacon
+    let mut rng = beacon_rng(&cur_hash);
+
+    // Try to load the challenge from disk.
+    let challenge_path = files.path(&CHALLENGE);
+    let reader = files.open(&CHALLENGE);
+
+    let metadata = reader.metadata().expect("unable to get filesystem metadata for the challenge file");
+
+    let mut reader = BufReader::new(reader);
+    let config = format.read_header(&mut reader, UseCompression::No, challenge_path);
+
+    if metadata.len() != ((format.header_size() + config.accumulator_size_bytes) as u64) {
+        panic!(
+            "The size of `{}` should be {}, but it's {}, so something isn't right.",
+            challenge_path.display(),
+            format.header_size() + config.accumulator_size_bytes,
+            metadata.len());
+    }
+
+    let mut reader = HashReader::new(reader);
+
+    // Create the response file
+    let writer = create_output(&files, response_path, resuming);
+
+    let mut writer = BufWriter::new(writer);
+    format.write_header(&mut writer, &config, UseCompression::Yes, response_path);
+    let mut writer = HashWriter::new(writer);
+
+    println!("Reading `{}` into memory...", challenge_path.display());
+
+    // Read the BLAKE2b hash of the previous contribution
+    {
+        // We don't need to do anything with it, but it's important for
+        // the hash chain.
+        let mut tmp = [0; 64];
+        reader.read_exact(&mut tmp).expect("unable to read BLAKE2b hash of previous contribution");
+    }
+
+    match config.curve {
+        CurveId::Bn256 => contribute::<Bn256, _>(config, reader, &mut writer, &mut *rng, response_path),
+        CurveId::Bls12_381 => contribute::<Bls12, _>(config, reader, &mut writer, &mut *rng, response_path),
+    }
 
     // Get the hash of the contribution, so the user can compare later
     let contribution_hash = writer.into_hash();
 
     print!("Done!\n\n\
-              Your contribution has been written to `./response`\n\n\
-              The BLAKE2b hash of `./response` is:\n");
+              Your contribution has been written to `{0}`\n\n\
+              The BLAKE2b hash of `{0}` is:\n", response_path.display());
 
     for line in contribution_hash.as_slice().chunks(16) {
         print!("\t");
@@ -149,4 +307,8 @@ fn main() {
     }
 
     println!("\n");
+
+    // The response is written, so the state is no longer needed
+    fs::remove_file(&state_path)
+        .unwrap_or_else(|e| panic!("unable to remove `{}`: {}", state_path.display(), e));
 }
@@ -2,92 +2,145 @@ extern crate powersoftau;
 extern crate rand;
 extern crate blake2;
 extern crate byteorder;
+extern crate crossbeam;
 extern crate getopts;
+extern crate hex;
 
 use powersoftau::*;
+use powersoftau::bls12::Bls12;
+use powersoftau::bn256::Bn256;
 use powersoftau::cmd_utils::*;
+use powersoftau::configuration::Configuration;
+use powersoftau::engine::{CurveId, Engine};
+use powersoftau::sandbox;
+use powersoftau::secret::{self, SecretBox, SecretVec};
+use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
+use rand::Rng;
+use rand::chacha::ChaChaRng;
+use std::env;
 use std::fs::OpenOptions;
-use std::io::{self, Read, BufReader, Write, BufWriter};
+use std::io::{self, Read, BufReader, Write, BufWriter, Seek, SeekFrom};
+use std::process::{Command, Stdio};
 
-fn main() {
-    let mut opts = getopts::Options::new();
-    opts.optflag("h", "help", "print this help");
-    opts.optopt("n", "", "number of tau powers", "NUM_POWERS");
-    opts.optopt("d", "digest", "file to write digest to", "FILE");
-    let matches = match_or_fail(&opts);
-
-    let num_powers : usize =
-        get_opt_default(&matches, "n", configuration::DEFAULT_NUM_POWERS);
-    let config = configuration::Configuration::new(num_powers);
-    let digest_file_opt : Option<String> = get_opt(&matches, "d");
-
-    // Create an RNG based on a mixture of system randomness and user provided randomness
-    let mut rng = {
-        use byteorder::{ReadBytesExt, BigEndian};
-        use blake2::{Blake2b, Digest};
-        use rand::{SeedableRng, Rng, OsRng};
-        use rand::chacha::ChaChaRng;
-
-        let h = {
-            let mut system_rng = OsRng::new().unwrap();
-            let mut h = Blake2b::default();
-
-            // Gather 1024 bytes of entropy from the system
-            for _ in 0..1024 {
-                let r: u8 = system_rng.gen();
-                h.update(&[r]);
-            }
+/// Warn if the private key could be swapped to disk.
+fn check_locked<E: Engine>(privkey: &PrivateKey<E>)
+{
+    if !privkey.is_locked() {
+        eprintln!("Warning: unable to lock the private key into memory, so it could be written to swap. \
+                   Raise the limit on locked memory (`ulimit -l`) to prevent this.");
+    }
+}
 
-            // Ask the user to provide some information for additional entropy
-            let mut user_input = String::new();
-            println!("Type some random text and press [ENTER] to provide additional entropy...");
-            io::stdin().read_line(&mut user_input).expect("expected to read some random text from the user");
+/// Create an RNG based on a mixture of system randomness and the random text
+/// typed by the user, which is overwritten once it has been used, or on
+/// `insecure_seed` if given.
+fn create_rng(insecure_seed: Option<&str>, user_input: &mut [u8]) -> SecretBox<ChaChaRng>
+{
+    use blake2::{Blake2b, Digest};
+    use rand::{SeedableRng, OsRng};
 
-            // Hash it all up to make a seed
-            h.update(&user_input.as_bytes());
-            h.finalize()
-        };
+    if let Some(s) = insecure_seed {
+        let bytes = hex::decode(s).unwrap_or_else(|e| panic!("invalid seed: {}", e));
+        if bytes.len() != 32 {
+            panic!("the seed should be 32 bytes, but it's {}", bytes.len());
+        }
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&bytes);
+        return SecretBox::new(ChaChaRng::from_seed(seed));
+    }
 
-        let mut digest = &h[..];
+    let h = {
+        let mut system_rng = OsRng::new().unwrap();
+        let mut h = SecretBox::new(Blake2b::default());
 
-        let mut seed : [u8;32] = [0;32];
-        for i in 0..8 {
-            let bytes = digest.read_u32::<BigEndian>().unwrap().to_be_bytes();
-            seed[(4 * i) .. ((4 * i) + 4)].copy_from_slice(&bytes);
+        // Gather 1024 bytes of entropy from the system
+        for _ in 0..1024 {
+            let r: u8 = system_rng.gen();
+            h.update(&[r]);
         }
 
-        ChaChaRng::from_seed(seed)
+        // Hash it all up to make a seed, and forget the text
+        h.update(&user_input[..]);
+        secret::zeroize(user_input);
+        SecretBox::new(h.finalize_reset())
     };
 
-    // Try to load `./challenge` from disk.
-    let reader = OpenOptions::new()
-                            .read(true)
-                            .open("challenge").expect("unable open `./challenge` in this directory");
+    let mut digest = &h[..];
 
-    {
-        let metadata = reader.metadata().expect("unable to get filesystem metadata for `./challenge`");
-        if metadata.len() != (config.accumulator_size_bytes as u64) {
-            panic!(
-                "The size of `./challenge` should be {}, but it's {}, so something isn't right.",
-                config.accumulator_size_bytes,
-                metadata.len());
+    let mut seed = SecretBox::new([0u8; 32]);
+    for i in 0..8 {
+        let bytes = digest.read_u32::<BigEndian>().unwrap().to_be_bytes();
+        seed[(4 * i) .. ((4 * i) + 4)].copy_from_slice(&bytes);
+    }
+
+    SecretBox::new(ChaChaRng::from_seed(*seed))
+}
+
+/// Longest random text read from the user. The rest of the line is ignored.
+const MAX_USER_INPUT: usize = 4096;
+
+/// Read a line of random text from the user into secret memory of a fixed
+/// size, so that it is never moved to a larger buffer leaving a copy behind.
+/// Returns the memory and the length of the text.
+fn read_user_input() -> (SecretVec<u8>, usize)
+{
+    let mut user_input = SecretVec::new(MAX_USER_INPUT, 0u8);
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+
+    let mut len = 0;
+    while len < MAX_USER_INPUT {
+        match stdin.read(&mut user_input[len..(len + 1)]) {
+            Ok(0) => break,
+            Ok(_) if user_input[len] == b'\n' => break,
+            Ok(_) => len += 1,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => panic!("expected to read some random text from the user: {}", e),
         }
     }
 
-    let reader = BufReader::new(reader);
-    let mut reader = HashReader::new(reader);
+    (user_input, len)
+}
 
-    // Create `./response` in this directory
-    let writer = OpenOptions::new()
-                            .read(false)
-                            .write(true)
-                            .create_new(true)
-                            .open("response").expect("unable to create `./response` in this directory");
+/// '--insecure-seed HEX', which only exists when built with the
+/// `insecure-seed` feature, for tests that need a deterministic response.
+#[cfg(feature = "insecure-seed")]
+fn add_insecure_seed_option(opts: &mut getopts::Options)
+{
+    opts.optopt("", "insecure-seed", "for testing only: seed the RNG with HEX rather than with entropy, so that the contribution is not secret", "HEX");
+}
 
-    let writer = BufWriter::new(writer);
-    let mut writer = HashWriter::new(writer);
+#[cfg(not(feature = "insecure-seed"))]
+fn add_insecure_seed_option(_opts: &mut getopts::Options)
+{
+}
+
+#[cfg(feature = "insecure-seed")]
+fn get_insecure_seed(matches: &getopts::Matches) -> Option<String>
+{
+    get_opt(matches, "insecure-seed")
+}
+
+#[cfg(not(feature = "insecure-seed"))]
+fn get_insecure_seed(_matches: &getopts::Matches) -> Option<String>
+{
+    None
+}
 
-    println!("Reading `./challenge` into memory...");
+/// Transform the accumulator read from the challenge with a fresh keypair,
+/// and write the hash of the challenge, the result and the public key to
+/// `writer`. `open_challenge` returns a reader of the challenge after its
+/// header. It is called again to read the challenge in batches, since the
+/// keypair depends on the hash of the whole challenge.
+fn contribute<E: Engine, C: Read, F: FnMut() -> C, W: Write, R: Rng>(
+    config: Configuration,
+    mut open_challenge: F,
+    writer: &mut W,
+    rng: &mut R,
+    batch_size_opt: Option<usize>
+)
+{
+    let mut reader = HashReader::new(open_challenge());
 
     // Read the BLAKE2b hash of the previous contribution
     {
@@ -97,41 +150,252 @@ fn main() {
         reader.read_exact(&mut tmp).expect("unable to read BLAKE2b hash of previous contribution");
     }
 
-    // Load the current accumulator into memory
-    let mut current_accumulator = Accumulator::deserialize(
-        config,
-        &mut reader,
-        UseCompression::No,
-        CheckForCorrectness::No)
-        .expect("unable to read uncompressed accumulator");
+    match batch_size_opt {
+        None => {
+            // Load the current accumulator into memory
+            let mut current_accumulator = Accumulator::<E>::deserialize(
+                config,
+                &mut reader,
+                UseCompression::No,
+                CheckForCorrectness::No)
+                .expect("unable to read uncompressed accumulator");
+
+            // Get the hash of the current accumulator
+            let current_accumulator_hash = reader.into_hash();
+
+            // Construct our keypair using the RNG we created above
+            let (pubkey, privkey) = keypair::<E, _>(rng, current_accumulator_hash.as_ref());
+            check_locked(&privkey);
+
+            // Perform the transformation
+            current_accumulator.transform(&privkey);
+
+            // Write the hash of the input accumulator
+            writer.write_all(&current_accumulator_hash.as_ref()).expect("unable to write BLAKE2b hash of input accumulator");
+
+            // Write the transformed accumulator (in compressed form, to save upload bandwidth for disadvantaged
+            // players.)
+            current_accumulator.serialize(writer, UseCompression::Yes).expect("unable to write transformed accumulator");
+
+            // Write the public key
+            pubkey.serialize(writer).expect("unable to write public key");
+        }
+        Some(batch_size) => {
+            // The keypair depends on the hash of the whole challenge, so
+            // hash it before reading it a second time in batches.
+            io::copy(&mut reader, &mut io::sink()).expect("unable to read the challenge file");
+            let current_accumulator_hash = reader.into_hash();
+
+            // Construct our keypair using the RNG we created above
+            let (pubkey, privkey) = keypair::<E, _>(rng, current_accumulator_hash.as_ref());
+            check_locked(&privkey);
+
+            let mut reader = open_challenge();
+            reader.read_exact(&mut [0; 64]).expect("unable to skip BLAKE2b hash of previous contribution");
+
+            // Write the hash of the input accumulator
+            writer.write_all(&current_accumulator_hash.as_ref()).expect("unable to write BLAKE2b hash of input accumulator");
+
+            // Perform the transformation, writing the transformed accumulator
+            // (in compressed form) as we go.
+            streaming::transform_batched::<E, _, _>(
+                config,
+                &mut reader,
+                writer,
+                UseCompression::Yes,
+                &privkey,
+                batch_size).expect("unable to transform accumulator");
+
+            // Write the public key
+            pubkey.serialize(writer).expect("unable to write public key");
+        }
+    }
+}
+
+/// Run `contribute` in a child process confined by `sandbox::enter`, so that
+/// the secrets never exist in this one. The child reads the random text and
+/// the challenge (twice in batches) from its stdin and writes the response
+/// to its stdout, and this process passes them on.
+fn contribute_sandboxed<C: Read, F: FnMut() -> C + Send, W: Write>(
+    config: &Configuration,
+    mut open_challenge: F,
+    writer: &mut W,
+    insecure_seed: Option<&str>,
+    user_input: &mut [u8],
+    batch_size_opt: Option<usize>
+)
+{
+    let mut command = Command::new(env::current_exe().expect("unable to find the path of compute"));
+    command
+        .arg("--sandbox-child")
+        .args(&["--curve", &config.curve.to_string(), "-n", &config.num_powers.to_string()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped());
+    if let Some(batch_size) = batch_size_opt {
+        command.args(&["--batch-size", &batch_size.to_string()]);
+    }
+    if let Some(seed) = insecure_seed {
+        command.args(&["--insecure-seed", seed]);
+    }
+    let mut child = command.spawn().expect("unable to start the sandboxed process");
+
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let passes = if batch_size_opt.is_some() { 2 } else { 1 };
+
+    let sent = crossbeam::scope(|scope| {
+        let sender = scope.spawn(move || -> io::Result<()> {
+            let sent = stdin.write_u32::<BigEndian>(user_input.len() as u32)
+                .and_then(|_| stdin.write_all(user_input));
+            secret::zeroize(user_input);
+            sent?;
+
+            for _ in 0..passes {
+                io::copy(&mut open_challenge(), &mut stdin)?;
+            }
+            Ok(())
+        });
+
+        io::copy(&mut stdout, writer).expect("unable to write the response");
+        sender.join()
+    });
+
+    // If the child failed, it stopped reading, so report its failure first
+    let status = child.wait().expect("unable to wait for the sandboxed process");
+    if !status.success() {
+        panic!("the sandboxed process failed ({})", status);
+    }
+    sent.unwrap_or_else(|e: io::Error| panic!("unable to send the challenge to the sandboxed process: {}", e));
+}
+
+/// The child process of `contribute_sandboxed`.
+fn sandbox_child(format: &FormatOptions, insecure_seed: Option<&str>, batch_size_opt: Option<usize>)
+{
+    let config = format.config();
+    sandbox::enter().unwrap_or_else(|e| panic!("unable to enter the sandbox: {}", e));
 
-    // Get the hash of the current accumulator
-    let current_accumulator_hash = reader.into_hash();
+    let stdin = io::stdin();
+    let mut user_input = {
+        let mut stdin = stdin.lock();
+        let len = stdin.read_u32::<BigEndian>().expect("unable to read the random text");
+        let mut user_input = SecretVec::new(len as usize, 0u8);
+        stdin.read_exact(&mut user_input).expect("unable to read the random text");
+        user_input
+    };
+    let mut rng = create_rng(insecure_seed, &mut user_input);
 
-    // Construct our keypair using the RNG we created above
-    let (pubkey, privkey) = keypair(&mut rng, current_accumulator_hash.as_ref());
+    let open_challenge = || stdin.lock().take(config.accumulator_size_bytes as u64);
+    let stdout = io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
 
-    // Perform the transformation
-    println!("Computing, this could take a while...");
-    current_accumulator.transform(&privkey);
-    println!("Writing your contribution to `./response`...");
+    match config.curve {
+        CurveId::Bn256 => contribute::<Bn256, _, _, _, _>(
+            config, open_challenge, &mut writer, &mut *rng, batch_size_opt),
+        CurveId::Bls12_381 => contribute::<Bls12, _, _, _, _>(
+            config, open_challenge, &mut writer, &mut *rng, batch_size_opt),
+    }
+    writer.flush().expect("unable to write the response");
+}
 
-    // Write the hash of the input accumulator
-    writer.write_all(&current_accumulator_hash.as_ref()).expect("unable to write BLAKE2b hash of input accumulator");
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[CHALLENGE], &[RESPONSE]);
+    files.add_options(&mut opts);
+    opts.optopt("d", "digest", "file to write digest to", "FILE");
+    opts.optopt("b", "batch-size", "process the challenge this many points at a time", "BATCH_SIZE");
+    opts.optflag("", "no-sandbox", "compute in this process rather than in a sandboxed child process (the default on Linux)");
+    add_insecure_seed_option(&mut opts);
+    opts.optflag("", "sandbox-child", "used internally to run the sandboxed child process");
+    let matches = match_or_fail(&opts);
 
-    // Write the transformed accumulator (in compressed form, to save upload bandwidth for disadvantaged
-    // players.)
-    current_accumulator.serialize(&mut writer, UseCompression::Yes).expect("unable to write transformed accumulator");
+    let format = FormatOptions::from_matches(&matches);
+    let batch_size_opt = get_batch_size(&matches);
+    let insecure_seed = get_insecure_seed(&matches);
+    let insecure_seed = insecure_seed.as_ref().map(|s| s.as_str());
 
-    // Write the public key
-    pubkey.serialize(&mut writer).expect("unable to write public key");
+    if matches.opt_present("sandbox-child") {
+        sandbox_child(&format, insecure_seed, batch_size_opt);
+        return;
+    }
+
+    if insecure_seed.is_some() {
+        eprintln!("WARNING: --insecure-seed makes the secrets of this contribution known to anyone \
+                   with the seed. The response is for testing only and must NEVER be used in a ceremony.");
+    }
+
+    files.parse(&matches);
+    let digest_file_opt : Option<String> = get_opt(&matches, "d");
+    let use_sandbox = sandbox::is_supported() && !matches.opt_present("no-sandbox");
+
+    // Try to load the challenge from disk.
+    let challenge_path = files.path(&CHALLENGE);
+    let reader = files.open(&CHALLENGE);
+
+    let metadata = reader.metadata().expect("unable to get filesystem metadata for the challenge file");
+
+    let mut reader = BufReader::new(reader);
+    let config = format.read_header(&mut reader, UseCompression::No, challenge_path);
+
+    if metadata.len() != ((format.header_size() + config.accumulator_size_bytes) as u64) {
+        panic!(
+            "The size of `{}` should be {}, but it's {}, so something isn't right.",
+            challenge_path.display(),
+            format.header_size() + config.accumulator_size_bytes,
+            metadata.len());
+    }
+
+    // Ask the user to provide some information for additional entropy
+    let (mut user_input, len) = if insecure_seed.is_none() {
+        println!("Type some random text and press [ENTER] to provide additional entropy...");
+        read_user_input()
+    } else {
+        (SecretVec::new(0, 0u8), 0)
+    };
+    let user_input = &mut user_input[..len];
+
+    // Create the response file
+    let response_path = files.path(&RESPONSE);
+    let writer = files.create(&RESPONSE);
+
+    let mut writer = BufWriter::new(writer);
+    format.write_header(&mut writer, &config, UseCompression::Yes, response_path);
+    let mut writer = HashWriter::new(writer);
+
+    // Reads the challenge after its header
+    let open_challenge = || {
+        let mut reader = BufReader::new(files.open(&CHALLENGE));
+        reader.seek(SeekFrom::Start(format.header_size() as u64)).expect("unable to skip the header of the challenge");
+        reader.take(config.accumulator_size_bytes as u64)
+    };
+
+    match batch_size_opt {
+        None => println!("Computing, this could take a while..."),
+        Some(batch_size) => println!("Computing in batches of {} points, this could take a while...", batch_size),
+    }
+
+    if use_sandbox {
+        contribute_sandboxed(&config, open_challenge, &mut writer, insecure_seed, user_input, batch_size_opt);
+    } else {
+        let mut rng = create_rng(insecure_seed, user_input);
+        match config.curve {
+            CurveId::Bn256 => contribute::<Bn256, _, _, _, _>(
+                config, open_challenge, &mut writer, &mut *rng, batch_size_opt),
+            CurveId::Bls12_381 => contribute::<Bls12, _, _, _, _>(
+                config, open_challenge, &mut writer, &mut *rng, batch_size_opt),
+        }
+    }
 
     // Get the hash of the contribution, so the user can compare later
     let contribution_hash = writer.into_hash();
 
     print!("Done!\n\n\
-              Your contribution has been written to `./response`\n\n\
-              The BLAKE2b hash of `./response` is:\n");
+              Your contribution has been written to `{0}`\n\n\
+              The BLAKE2b hash of `{0}` is:\n", response_path.display());
+    if insecure_seed.is_some() {
+        eprintln!("WARNING: `{}` was computed from --insecure-seed and is INSECURE.", response_path.display());
+    }
 
     let hash_str = digest_to_string(contribution_hash.as_slice());
     print!("{}", hash_str);
@@ -0,0 +1,44 @@
+extern crate powersoftau;
+extern crate getopts;
+
+use powersoftau::*;
+use powersoftau::arkworks::*;
+use powersoftau::cmd_utils::*;
+
+use std::io::{BufWriter, Write};
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[CHALLENGE], &[ARKWORKS_PARAMS]);
+    files.add_options(&mut opts);
+    opts.optflag("", "uncompressed", "write uncompressed points");
+    opts.optflag("", "powers", "write the committer key (Powers) rather than UniversalParams. \
+        Both have an empty powers_of_gamma_g, so arkworks panics on anything that indexes it, \
+        such as powers_of_gamma_g[&0] when building a VerifierKey");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    let compression = if matches.opt_present("uncompressed") { UseCompression::No } else { UseCompression::Yes };
+    let powers_only = matches.opt_present("powers");
+
+    let current_accumulator = read_bn256_challenge(&format, &files, "arkworks KZG parameters");
+
+    let params_path = files.path(&ARKWORKS_PARAMS);
+    let writer = files.create(&ARKWORKS_PARAMS);
+
+    let mut writer = BufWriter::new(writer);
+
+    let params = UniversalParams::new(&current_accumulator);
+    if powers_only {
+        params.serialize_powers(&mut writer, compression)
+    } else {
+        params.serialize(&mut writer, compression)
+    }.expect("unable to write arkworks KZG parameters");
+    writer.flush().expect("unable to flush arkworks KZG parameters to disk");
+
+    println!("Wrote the arkworks KZG parameters to `{}`", params_path.display());
+    println!("They have no powers of gamma: build a VerifierKey without indexing powers_of_gamma_g, which panics");
+}
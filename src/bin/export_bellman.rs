@@ -0,0 +1,87 @@
+extern crate powersoftau;
+extern crate getopts;
+
+use powersoftau::*;
+use powersoftau::bellman;
+use powersoftau::bn256::Bn256;
+use powersoftau::cmd_utils::*;
+use powersoftau::engine::CurveId;
+
+use std::io::{BufReader, BufWriter, Read, Write};
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[CHALLENGE, RESPONSE], &[BELLMAN]);
+    files.add_options(&mut opts);
+    opts.optopt("", "kind", "kind of file to convert: challenge (default) or response", "KIND");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    let (input, compression) = match get_opt::<String>(&matches, "kind").as_ref().map(|k| k.as_str()) {
+        None | Some("challenge") => (CHALLENGE, UseCompression::No),
+        Some("response") => (RESPONSE, UseCompression::Yes),
+        Some(kind) => panic!("unknown kind of file `{}`, expected challenge or response", kind),
+    };
+
+    // Try to load the input from disk. It should have been checked with
+    // `verify_transform` first, as the accumulator is not checked here.
+    let input_path = files.path(&input);
+    let reader = files.open(&input);
+
+    let metadata = reader.metadata().expect("unable to get filesystem metadata for the input file");
+
+    let mut reader = BufReader::with_capacity(1024 * 1024, reader);
+    let config = format.read_header(&mut reader, compression, input_path);
+
+    if config.curve != CurveId::Bn256 {
+        panic!("bellman-ce files can only be exported for bn256, not {}", config.curve);
+    }
+
+    let expected_size = match compression {
+        UseCompression::Yes => config.contribution_size_bytes,
+        UseCompression::No => config.accumulator_size_bytes,
+    };
+    if metadata.len() != ((format.header_size() + expected_size) as u64) {
+        panic!(
+            "The size of `{}` should be {}, but it's {}, so something isn't right.",
+            input_path.display(),
+            format.header_size() + expected_size,
+            metadata.len());
+    }
+
+    println!("Reading `{}` into memory...", input_path.display());
+
+    let mut hash = [0; 64];
+    reader.read_exact(&mut hash).expect("unable to read BLAKE2b hash from the input file");
+
+    let accumulator = Accumulator::<Bn256>::deserialize(
+        config,
+        &mut reader,
+        compression,
+        CheckForCorrectness::No
+    ).expect("unable to read accumulator");
+
+    let pubkey = match compression {
+        UseCompression::Yes => Some(PublicKey::<Bn256>::deserialize(&mut reader)
+            .expect("wasn't able to deserialize the response file's public key")),
+        UseCompression::No => None,
+    };
+
+    let bellman_path = files.path(&BELLMAN);
+    let writer = files.create(&BELLMAN);
+
+    let mut writer = HashWriter::new(BufWriter::new(writer));
+
+    writer.write_all(&hash).expect("unable to write BLAKE2b hash");
+    bellman::write_accumulator(&mut writer, &accumulator, compression).expect("unable to write accumulator");
+    if let Some(pubkey) = pubkey {
+        bellman::write_public_key(&mut writer, &pubkey).expect("unable to write public key");
+    }
+    writer.flush().expect("unable to flush bellman-ce file to disk");
+
+    print!("Wrote `{0}`. The BLAKE2b hash of `{0}` is:\n{1}",
+        bellman_path.display(), digest_to_string(writer.into_hash().as_slice()));
+}
@@ -0,0 +1,35 @@
+extern crate powersoftau;
+extern crate getopts;
+
+use powersoftau::*;
+use powersoftau::cmd_utils::*;
+use powersoftau::gnark::*;
+
+use std::io::{BufWriter, Write};
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[CHALLENGE], &[GNARK_SRS]);
+    files.add_options(&mut opts);
+    opts.optflag("", "uncompressed", "write uncompressed points, as WriteRawTo does");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    let compression = if matches.opt_present("uncompressed") { UseCompression::No } else { UseCompression::Yes };
+
+    let current_accumulator = read_bn256_challenge(&format, &files, "gnark SRS files");
+
+    let srs_path = files.path(&GNARK_SRS);
+    let writer = files.create(&GNARK_SRS);
+
+    let mut writer = BufWriter::new(writer);
+
+    GnarkSrs::new(&current_accumulator).serialize(&mut writer, compression)
+        .expect("unable to write gnark SRS");
+    writer.flush().expect("unable to flush gnark SRS to disk");
+
+    println!("Wrote the gnark SRS to `{}`", srs_path.display());
+}
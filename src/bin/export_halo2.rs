@@ -0,0 +1,42 @@
+extern crate powersoftau;
+extern crate getopts;
+
+use powersoftau::cmd_utils::*;
+use powersoftau::halo2::*;
+
+use std::io::{BufWriter, Write};
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[CHALLENGE], &[]);
+    files.add_options(&mut opts);
+    opts.optopt("k", "", "log2 of the domain size (default: all of the powers)", "K");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    let k_opt : Option<u32> = get_opt(&matches, "k");
+
+    let current_accumulator = read_bn256_challenge(&format, &files, "halo2 KZG parameters");
+    let config = current_accumulator.config;
+
+    let k = k_opt.unwrap_or(config.num_powers_log2 as u32);
+    if k as usize > config.num_powers_log2 {
+        panic!("k can be at most {} for `{}`, but it's {}", config.num_powers_log2, files.path(&CHALLENGE).display(), k);
+    }
+
+    let path = files.out_dir().join(params_kzg_filename(k));
+
+    println!("Creating {}", path.display());
+
+    let params = ParamsKzg::new(&current_accumulator, k);
+
+    let writer = files.create_path(&path);
+
+    let mut writer = BufWriter::new(writer);
+
+    params.serialize(&mut writer).expect("unable to write halo2 KZG parameters");
+    writer.flush().expect("unable to flush halo2 KZG parameters to disk");
+}
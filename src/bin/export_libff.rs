@@ -0,0 +1,65 @@
+extern crate powersoftau;
+extern crate getopts;
+
+use powersoftau::cmd_utils::*;
+use powersoftau::lagrange::*;
+use powersoftau::libff::*;
+
+use std::io::{BufWriter, Write};
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[CHALLENGE], &[LIBFF]);
+    files.add_options(&mut opts);
+    opts.optflag("", "text", "write decimal text, for libff built without BINARY_OUTPUT");
+    opts.optflag("", "plain", "write plain coordinates, for libff built without MONTGOMERY_OUTPUT");
+    opts.optflag("", "uncompressed", "write uncompressed points, for libff built without USE_PT_COMPRESSION");
+    opts.optflag("", "lagrange", "also write the Lagrange coefficients for every domain size");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    let libff_format = LibffFormat {
+        binary: !matches.opt_present("text"),
+        montgomery: !matches.opt_present("plain"),
+        compressed: !matches.opt_present("uncompressed"),
+    };
+    let write_lagrange_files = matches.opt_present("lagrange");
+
+    let current_accumulator = read_bn256_challenge(&format, &files, "libff files");
+    let config = current_accumulator.config;
+
+    let libff_path = files.path(&LIBFF);
+    let writer = files.create(&LIBFF);
+
+    let mut writer = BufWriter::new(writer);
+
+    write_accumulator(&mut writer, &current_accumulator, libff_format).expect("unable to write libff accumulator");
+    writer.flush().expect("unable to flush libff accumulator to disk");
+
+    println!("Wrote the accumulator to `{}`", libff_path.display());
+
+    if !write_lagrange_files {
+        return;
+    }
+
+    // Write the Lagrange coefficients for every domain size up to the
+    // number of powers in the accumulator, in the output directory.
+    for log_m in 0..(config.num_powers_log2 + 1) {
+        let m = 1 << log_m;
+        let path = files.out_dir().join(format!("{}.libff", lagrange_filename(log_m)));
+
+        println!("Creating {}", path.display());
+
+        let lagrange = LagrangeCoefficients::new(&current_accumulator, m);
+
+        let writer = files.create_path(&path);
+
+        let mut writer = BufWriter::new(writer);
+
+        write_lagrange(&mut writer, &lagrange, libff_format).expect("unable to write libff Lagrange coefficients");
+        writer.flush().expect("unable to flush libff Lagrange coefficients to disk");
+    }
+}
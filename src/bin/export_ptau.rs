@@ -0,0 +1,153 @@
+extern crate powersoftau;
+extern crate getopts;
+
+use powersoftau::*;
+use powersoftau::bn256::Bn256;
+use powersoftau::cmd_utils::*;
+use powersoftau::engine::CurveId;
+use powersoftau::ptau::*;
+
+use std::fs::OpenOptions;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[TRANSCRIPT], &[PTAU]);
+    files.add_options(&mut opts);
+    opts.optopt("", "initial-challenge", "first challenge of a ceremony that starts from an imported accumulator", "PATH");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    let initial_challenge_opt : Option<String> = get_opt(&matches, "initial-challenge");
+
+    // Try to load the transcript from disk. It should have been checked with
+    // `verify` first, as the accumulators are not checked here.
+    let transcript_path = files.path(&TRANSCRIPT);
+    let reader = files.open(&TRANSCRIPT);
+
+    let metadata = reader.metadata().expect("unable to get filesystem metadata for the transcript");
+
+    let mut reader = BufReader::with_capacity(1024 * 1024, reader);
+    let config = format.read_header(&mut reader, UseCompression::Yes, transcript_path);
+
+    if config.curve != CurveId::Bn256 {
+        panic!("snarkjs .ptau files can only be exported for bn256, not {}", config.curve);
+    }
+
+    // Each round of the transcript is a response file without its leading
+    // hash.
+    let round_size = (config.contribution_size_bytes - 64) as u64;
+    let body_size = metadata.len() - format.header_size() as u64;
+    if body_size == 0 || body_size % round_size != 0 {
+        panic!(
+            "The size of `{}` should be a positive multiple of {} after the header, but it's {}.",
+            transcript_path.display(),
+            round_size,
+            body_size);
+    }
+    let num_rounds = (body_size / round_size) as usize;
+
+    // The first challenge holds a fresh accumulator after a blank hash,
+    // unless the ceremony starts from another accumulator.
+    let mut last_challenge_file_hash = [0; 64];
+    match initial_challenge_opt {
+        None => {
+            let mut sink = HashWriter::new(io::sink());
+            sink.write_all(blank_hash().as_slice()).unwrap();
+            Accumulator::<Bn256>::new(config).serialize(&mut sink, UseCompression::No).unwrap();
+            last_challenge_file_hash.copy_from_slice(sink.into_hash().as_slice());
+        }
+        Some(ref path) => {
+            last_challenge_file_hash = initial_challenge_hash(&format, config, Path::new(path));
+        }
+    }
+
+    let mut contributions = vec![];
+    let mut current_accumulator = None;
+
+    for round in 0..num_rounds {
+        println!("Reading contribution {} of {}...", round + 1, num_rounds);
+
+        let response_file_accumulator = Accumulator::<Bn256>::deserialize(
+            config,
+            &mut reader,
+            UseCompression::Yes,
+            CheckForCorrectness::No
+        ).expect("unable to read compressed accumulator");
+
+        let response_file_pubkey = PublicKey::<Bn256>::deserialize(&mut reader)
+            .expect("wasn't able to deserialize the response file's public key");
+
+        // Compute the hash of the response file.
+        let mut response_file_hash = [0; 64];
+        {
+            let mut sink = HashWriter::new(io::sink());
+            sink.write_all(&last_challenge_file_hash).unwrap();
+            response_file_accumulator.serialize(&mut sink, UseCompression::Yes).unwrap();
+            response_file_pubkey.serialize(&mut sink).unwrap();
+            response_file_hash.copy_from_slice(sink.into_hash().as_slice());
+        }
+
+        let contribution = PtauContribution::new(
+            &response_file_accumulator, response_file_pubkey, &response_file_hash);
+        last_challenge_file_hash = contribution.next_challenge_hash;
+        contributions.push(contribution);
+
+        current_accumulator = Some(response_file_accumulator);
+    }
+
+    let current_accumulator = current_accumulator.expect("the transcript has at least one round");
+
+    let ptau_path = files.path(&PTAU);
+    let writer = files.create(&PTAU);
+
+    let mut writer = BufWriter::new(writer);
+
+    write_ptau(&mut writer, &current_accumulator, &contributions).expect("unable to write .ptau file");
+    writer.flush().expect("unable to flush .ptau file to disk");
+
+    println!("Wrote {} contributions to `{}`", num_rounds, ptau_path.display());
+}
+
+/// Hash the first challenge of a ceremony that does not start from a fresh
+/// accumulator (e.g. as written by `import_ptau`), as the first response
+/// was computed over it. Like the transcript, it should have been checked
+/// with `verify` first.
+fn initial_challenge_hash(
+    format: &FormatOptions,
+    config: configuration::Configuration,
+    path: &Path
+) -> [u8; 64]
+{
+    let reader = OpenOptions::new()
+        .read(true)
+        .open(path)
+        .unwrap_or_else(|e| panic!("unable to open `{}`: {}", path.display(), e));
+
+    let metadata = reader.metadata().expect("unable to get filesystem metadata for the initial challenge");
+    let expected_size = (format.header_size() + config.accumulator_size_bytes) as u64;
+    if metadata.len() != expected_size {
+        panic!(
+            "The size of `{}` should be {}, but it's {}, so something isn't right.",
+            path.display(),
+            expected_size,
+            metadata.len());
+    }
+
+    let mut reader = BufReader::with_capacity(1024 * 1024, reader);
+    if format.read_header(&mut reader, UseCompression::No, path) != config {
+        panic!("`{}` is not for the same ceremony as the transcript", path.display());
+    }
+
+    // The challenge file is hashed without its header.
+    let mut sink = HashWriter::new(io::sink());
+    io::copy(&mut reader, &mut sink).expect("unable to read the initial challenge");
+
+    let mut hash = [0; 64];
+    hash.copy_from_slice(sink.into_hash().as_slice());
+    hash
+}
@@ -0,0 +1,97 @@
+extern crate powersoftau;
+extern crate getopts;
+
+use powersoftau::*;
+use powersoftau::bellman;
+use powersoftau::cmd_utils::*;
+use powersoftau::engine::CurveId;
+
+use std::io::{BufReader, BufWriter, Read, Write};
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[BELLMAN], &[CHALLENGE, RESPONSE]);
+    files.add_options(&mut opts);
+    opts.optopt("", "kind", "kind of file to convert: challenge (default) or response", "KIND");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    let (output, compression) = match get_opt::<String>(&matches, "kind").as_ref().map(|k| k.as_str()) {
+        None | Some("challenge") => (CHALLENGE, UseCompression::No),
+        Some("response") => (RESPONSE, UseCompression::Yes),
+        Some(kind) => panic!("unknown kind of file `{}`, expected challenge or response", kind),
+    };
+
+    // bellman-ce files have no header, so the number of powers comes from
+    // the command line.
+    let config = format.config();
+    if config.curve != CurveId::Bn256 {
+        panic!("bellman-ce files can only be imported for bn256, not {}", config.curve);
+    }
+
+    let bellman_path = files.path(&BELLMAN);
+    let reader = files.open(&BELLMAN);
+
+    let metadata = reader.metadata().expect("unable to get filesystem metadata for the bellman-ce file");
+
+    let mut expected_size = 64 + bellman::accumulator_size(&config, compression);
+    if compression == UseCompression::Yes {
+        expected_size += bellman::PUBLIC_KEY_SIZE;
+    }
+    if metadata.len() != expected_size as u64 {
+        panic!(
+            "The size of `{}` should be {} for {} powers, but it's {}, so something isn't right.",
+            bellman_path.display(),
+            expected_size,
+            config.num_powers,
+            metadata.len());
+    }
+
+    let mut reader = BufReader::with_capacity(1024 * 1024, reader);
+
+    println!("Reading `{}` into memory...", bellman_path.display());
+
+    let mut hash = [0; 64];
+    reader.read_exact(&mut hash).expect("unable to read BLAKE2b hash from the bellman-ce file");
+
+    // Points from another implementation are always checked.
+    let accumulator = bellman::read_accumulator(&mut reader, config, compression, CheckForCorrectness::Yes)
+        .unwrap_or_else(|e| panic!("unable to read accumulator from `{}`: {}", bellman_path.display(), e));
+
+    let pubkey = match compression {
+        UseCompression::Yes => Some(bellman::read_public_key(&mut reader)
+            .unwrap_or_else(|e| panic!("unable to read public key from `{}`: {}", bellman_path.display(), e))),
+        UseCompression::No => None,
+    };
+
+    let output_path = files.path(&output);
+    let writer = files.create(&output);
+
+    let mut writer = BufWriter::new(writer);
+    format.write_header(&mut writer, &config, compression, output_path);
+    let mut writer = HashWriter::new(writer);
+
+    writer.write_all(&hash).expect("unable to write BLAKE2b hash");
+    accumulator.serialize(&mut writer, compression).expect("unable to write accumulator");
+    if let Some(pubkey) = pubkey {
+        pubkey.serialize(&mut writer).expect("unable to write public key");
+    }
+    writer.flush().expect("unable to flush accumulator to disk");
+
+    print!("Wrote `{0}`. The BLAKE2b hash of `{0}` is:\n{1}",
+        output_path.display(), digest_to_string(writer.into_hash().as_slice()));
+
+    if compression == UseCompression::Yes {
+        // bellman-ce computed the proofs of knowledge over the hash of the
+        // challenge in its own encoding, which verify_transform doesn't
+        // recompute.
+        print!("The response was computed for the bellman-ce challenge whose BLAKE2b hash is:\n{}\
+            Check that it is the hash export_bellman printed for that challenge. The proofs of \
+            knowledge are over this hash, so verify_transform, which hashes the challenge in \
+            this crate's encoding, will reject the response.\n",
+            digest_to_string(&hash));
+    }
+}
@@ -0,0 +1,60 @@
+extern crate powersoftau;
+
+use powersoftau::*;
+use powersoftau::cmd_utils::*;
+use powersoftau::engine::CurveId;
+use powersoftau::ptau::*;
+
+use std::io::{self, BufReader, BufWriter, Write};
+use std::process;
+
+fn main() {
+    let (format, files) = parse_simple_options(&[PTAU], &[CHALLENGE]);
+
+    if let Some(curve) = format.curve {
+        if curve != CurveId::Bn256 {
+            panic!("snarkjs .ptau files can only be imported for bn256, not {}", curve);
+        }
+    }
+
+    // Read the accumulator of the other ceremony, keeping `-n` powers if
+    // given, and hash the whole file so that the challenge is bound to it.
+    let ptau_path = files.path(&PTAU);
+    let reader = files.open(&PTAU);
+
+    let reader = BufReader::with_capacity(1024 * 1024, reader);
+    let mut reader = HashReader::new(reader);
+
+    println!("Reading `{}`...", ptau_path.display());
+
+    let acc = read_ptau(&mut reader, format.num_powers)
+        .unwrap_or_else(|e| panic!("unable to read `{}`: {}", ptau_path.display(), e))
+        .accumulator;
+    io::copy(&mut reader, &mut io::sink()).expect("unable to read the .ptau file");
+    let ptau_hash = reader.into_hash();
+
+    print!("The BLAKE2b hash of `{}` is:\n{}", ptau_path.display(), digest_to_string(ptau_hash.as_slice()));
+
+    println!("Checking the {} imported powers...", acc.config.num_powers);
+    if let Err(e) = acc.verify_consistency() {
+        println!(" ... FAILED: {}", e);
+        println!("INVALID ACCUMULATOR!");
+        process::exit(e.exit_code());
+    }
+
+    let challenge_path = files.path(&CHALLENGE);
+    let writer = files.create(&CHALLENGE);
+
+    let mut writer = BufWriter::new(writer);
+
+    format.write_header(&mut writer, &acc.config, UseCompression::No, challenge_path);
+
+    // The hash of the imported file takes the place of the blank hash that
+    // precedes a fresh accumulator.
+    writer.write_all(ptau_hash.as_slice()).expect("unable to write hash to the challenge file");
+
+    acc.serialize(&mut writer, UseCompression::No).expect("unable to write imported accumulator to the challenge file");
+    writer.flush().expect("unable to flush accumulator to disk");
+
+    println!("Wrote the imported accumulator to `{}`", challenge_path.display());
+}
@@ -1,24 +1,29 @@
 extern crate powersoftau;
 use powersoftau::*;
-use std::fs::OpenOptions;
+use powersoftau::bls12::Bls12;
+use powersoftau::bn256::Bn256;
+use powersoftau::cmd_utils::*;
+use powersoftau::engine::CurveId;
 use std::io::{Write, BufWriter};
 
 fn main() {
-    let config = cmd_utils::parse_simple_options();
-    let writer = OpenOptions::new()
-                            .read(false)
-                            .write(true)
-                            .create_new(true)
-                            .open("challenge").expect("unable to create `./challenge`");
+    let (format, files) = parse_simple_options(&[], &[CHALLENGE]);
+    let config = format.config();
+    let challenge_path = files.path(&CHALLENGE);
+    let writer = files.create(&CHALLENGE);
 
     let mut writer = BufWriter::new(writer);
 
+    format.write_header(&mut writer, &config, UseCompression::No, challenge_path);
+
     // Write a blank BLAKE2b hash:
-    writer.write_all(&blank_hash().as_slice()).expect("unable to write blank hash to `./challenge`");
+    writer.write_all(&blank_hash().as_slice()).expect("unable to write blank hash to the challenge file");
 
-    let acc = Accumulator::new(config);
-    acc.serialize(&mut writer, UseCompression::No).expect("unable to write fresh accumulator to `./challenge`");
+    match config.curve {
+        CurveId::Bn256 => Accumulator::<Bn256>::new(config).serialize(&mut writer, UseCompression::No),
+        CurveId::Bls12_381 => Accumulator::<Bls12>::new(config).serialize(&mut writer, UseCompression::No),
+    }.expect("unable to write fresh accumulator to the challenge file");
     writer.flush().expect("unable to flush accumulator to disk");
 
-    println!("Wrote a fresh accumulator to `./challenge`");
+    println!("Wrote a fresh accumulator to `{}`", challenge_path.display());
 }
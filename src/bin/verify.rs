@@ -5,10 +5,15 @@ extern crate blake2;
 extern crate byteorder;
 
 use powersoftau::*;
+use powersoftau::bls12::Bls12;
+use powersoftau::bn256::Bn256;
 use powersoftau::cmd_utils::*;
+use powersoftau::engine::{CurveId, Engine};
+use powersoftau::lagrange::*;
 use std::str;
-use std::fs::OpenOptions;
-use std::io::{self, BufReader, Write, Read};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write, Read, Seek, SeekFrom};
+use std::path::Path;
 
 fn into_hex(h: &[u8]) -> String {
     let mut f = String::new();
@@ -23,8 +28,8 @@ fn into_hex(h: &[u8]) -> String {
 // Computes the hash of the challenge file for the player,
 // given the current state of the accumulator and the last
 // response file hash.
-fn get_challenge_file_hash(
-    acc: &Accumulator,
+fn get_challenge_file_hash<E: Engine>(
+    acc: &Accumulator<E>,
     last_response_file_hash: &[u8; 64]
 ) -> [u8; 64]
 {
@@ -48,9 +53,9 @@ fn get_challenge_file_hash(
 // Computes the hash of the response file, given the new
 // accumulator, the player's public key, and the challenge
 // file's hash.
-fn get_response_file_hash(
-    acc: &Accumulator,
-    pubkey: &PublicKey,
+fn get_response_file_hash<E: Engine>(
+    acc: &Accumulator<E>,
+    pubkey: &PublicKey<E>,
     last_challenge_file_hash: &[u8; 64]
 ) -> [u8; 64]
 {
@@ -73,50 +78,100 @@ fn get_response_file_hash(
     tmp
 }
 
-fn main() {
-    let mut opts = getopts::Options::new();
-    opts.optflag("h", "help", "print this help");
-    opts.optopt("n", "", "number of tau powers", "NUM_POWERS");
-    opts.optopt("r", "rounds", "number of rounds", "NUM_ROUNDS");
-    opts.optopt("d", "digest", "check contribution with given digest", "FILE");
-    opts.optflag("s", "skip-lagrange", "skip generation of phase1radix2m files");
-    let matches = match_or_fail(&opts);
+/// Exit with the code identifying the check that a response failed.
+fn reject_response(e: VerificationError) -> ! {
+    println!(" ... FAILED: {}", e);
+    println!("INVALID RESPONSE FILE!");
+    std::process::exit(e.exit_code());
+}
 
-    let config = configuration::Configuration::new(
-        get_opt_default(&matches, "n", configuration::DEFAULT_NUM_POWERS));
-    // 89 hard-coded into original code
-    let num_rounds = get_opt_default(&matches, "r", 89);
-    let skip_lagrange = matches.opt_present("s");
-    let digest_file_opt : Option<String> = get_opt(&matches, "d");
-    let contrib_digest_opt : Option<[u8;DIGEST_LENGTH]> = digest_file_opt
-        .as_ref()
-        .map(|digest_file| {
-            let mut digest_reader = OpenOptions::new()
-                .read(true).open(digest_file).expect("failed to open digest file");
-            let mut digest_buffer = [0u8; DIGEST_STRING_LENGTH];
-            digest_reader.read_exact(&mut digest_buffer).expect("invalid digest file size");
-            let digest_string = String::from(
-                str::from_utf8(&digest_buffer).expect("invalid digest data"));
-            let digest : [u8; DIGEST_LENGTH] =
-                digest_from_string(&digest_string).expect("invalid digest file");
-            digest
-        });
+/// Open the transcript, skipping the first `header_size` bytes.
+fn open_transcript(files: &FileOptions, header_size: usize) -> BufReader<File> {
+    // Try to load the transcript from disk.
+    let reader = files.open(&TRANSCRIPT);
+
+    let mut reader = BufReader::with_capacity(1024 * 1024, reader);
+    reader.seek(SeekFrom::Start(header_size as u64)).expect("unable to skip header of the transcript");
+    reader
+}
+
+/// The first challenge of a ceremony that does not start from a fresh
+/// accumulator: the hash it begins with, and a reader of its accumulator.
+type InitialChallenge = ([u8; 64], BufReader<File>);
+
+/// Exit with the code identifying the check that the initial challenge
+/// failed.
+fn reject_initial_challenge(e: VerificationError) -> ! {
+    println!(" ... FAILED: {}", e);
+    println!("INVALID INITIAL CHALLENGE!");
+    std::process::exit(e.exit_code());
+}
 
-    // Try to load `./transcript` from disk.
+/// Open the first challenge of the ceremony (e.g. as written by
+/// `import_ptau`), checking that it matches the transcript. Its accumulator
+/// is checked as it is read, as import_ptau checks it, since the transcript
+/// is only verified relative to it.
+fn open_initial_challenge(
+    format: &FormatOptions,
+    config: configuration::Configuration,
+    path: &Path
+) -> InitialChallenge
+{
     let reader = OpenOptions::new()
-                            .read(true)
-                            .open("transcript")
-                            .expect("unable open `./transcript` in this directory");
+        .read(true)
+        .open(path)
+        .unwrap_or_else(|e| panic!("unable to open `{}`: {}", path.display(), e));
 
     let mut reader = BufReader::with_capacity(1024 * 1024, reader);
+    if format.read_header(&mut reader, UseCompression::No, path) != config {
+        panic!("`{}` is not for the same ceremony as the transcript", path.display());
+    }
 
-    // Initialize the accumulator
-    let mut current_accumulator = Accumulator::new(config);
+    let mut hash = [0; 64];
+    reader.read_exact(&mut hash).expect("unable to read hash of the initial challenge");
 
-    // The "last response file hash" is just a blank BLAKE2b hash
-    // at the beginning of the hash chain.
+    (hash, reader)
+}
+
+/// Verify every round of the transcript, holding the accumulators before and
+/// after each round in memory. Returns the final accumulator, and whether a
+/// response with the given digest was found.
+fn verify_in_memory<E: Engine>(
+    config: configuration::Configuration,
+    files: &FileOptions,
+    header_size: usize,
+    num_rounds: usize,
+    contrib_digest_opt: &Option<[u8; DIGEST_LENGTH]>,
+    initial: Option<InitialChallenge>
+) -> (Accumulator<E>, bool)
+{
+    let mut reader = open_transcript(files, header_size);
+
+    // Initialize the accumulator. The "last response file hash" is just a
+    // blank BLAKE2b hash at the beginning of the hash chain, unless the
+    // ceremony starts from another accumulator.
     let mut last_response_file_hash = [0; 64];
-    last_response_file_hash.copy_from_slice(blank_hash().as_slice());
+    let mut current_accumulator = match initial {
+        None => {
+            last_response_file_hash.copy_from_slice(blank_hash().as_slice());
+            Accumulator::<E>::new(config)
+        }
+        Some((hash, mut initial_reader)) => {
+            last_response_file_hash = hash;
+            let acc = Accumulator::<E>::deserialize(
+                config,
+                &mut initial_reader,
+                UseCompression::No,
+                CheckForCorrectness::Yes
+            ).unwrap_or_else(|e| panic!("unable to read the initial accumulator: {}", e));
+            print!("Checking the initial accumulator");
+            if let Err(e) = acc.verify_consistency() {
+                reject_initial_challenge(e);
+            }
+            println!("");
+            acc
+        }
+    };
 
     // If a digest was specified, check the transcript to ensure it is
     // included.
@@ -164,22 +219,281 @@ fn main() {
         // Verify the transformation from the previous accumulator to the new
         // one. This also verifies the correctness of the accumulators and the
         // public keys, with respect to the transcript so far.
-        if !verify_transform(
+        if let Err(e) = verify_transform(
             &current_accumulator,
             &response_file_accumulator,
             &response_file_pubkey,
             &last_challenge_file_hash
         )
         {
-            println!(" ... FAILED");
-            panic!("INVALID RESPONSE FILE!");
-        } else {
-            println!("");
+            reject_response(e);
         }
+        println!("");
 
         current_accumulator = response_file_accumulator;
     }
 
+    (current_accumulator, found_digest)
+}
+
+/// The file, in the output directory, that holds the accumulator before each
+/// round while verifying in batches.
+const SCRATCH_FILENAME: &'static str = "verify.accumulator";
+
+/// Verify every round of the transcript, reading it once, `batch_size`
+/// points at a time. The accumulator after each round is checked as it is
+/// read, and written uncompressed to a scratch file, from which the hash of
+/// the next challenge file is computed without decompressing the round
+/// again. Returns the final accumulator if `load_final` is set, and whether a
+/// response with the given digest was found.
+fn verify_batched<E: Engine>(
+    config: configuration::Configuration,
+    files: &FileOptions,
+    header_size: usize,
+    num_rounds: usize,
+    contrib_digest_opt: &Option<[u8; DIGEST_LENGTH]>,
+    batch_size: usize,
+    load_final: bool,
+    mut initial: Option<InitialChallenge>
+) -> (Option<Accumulator<E>>, bool)
+{
+    let mut reader = open_transcript(files, header_size);
+
+    let scratch_path = files.out_dir().join(SCRATCH_FILENAME);
+    let scratch = files.create_path(&scratch_path);
+
+    // The "last response file hash" is just a blank BLAKE2b hash
+    // at the beginning of the hash chain, unless the ceremony starts from
+    // another accumulator. The challenge file that the player should have
+    // received is hashed as the accumulator before the first round is read.
+    let mut challenge_hasher = HashWriter::new(io::sink());
+    let mut before = match initial {
+        None => {
+            challenge_hasher.write_all(blank_hash().as_slice()).unwrap();
+            streaming::summarize_batched::<E, _, _>(
+                config,
+                &mut streaming::NewAccumulatorReader::new::<E>(config, UseCompression::No),
+                UseCompression::No,
+                CheckForCorrectness::No,
+                &mut challenge_hasher,
+                batch_size
+            ).expect("unable to read the new accumulator")
+        }
+        Some((hash, ref mut initial_reader)) => {
+            challenge_hasher.write_all(&hash).unwrap();
+
+            print!("Checking the initial accumulator");
+            let summary = streaming::summarize_batched::<E, _, _>(
+                config,
+                initial_reader,
+                UseCompression::No,
+                CheckForCorrectness::Yes,
+                &mut challenge_hasher,
+                batch_size
+            ).unwrap_or_else(|e| panic!("unable to read the initial accumulator: {}", e));
+            if let Err(e) = summary.verify_consistency() {
+                reject_initial_challenge(e);
+            }
+            println!("");
+            summary
+        }
+    };
+    let mut last_challenge_file_hash = challenge_hasher.into_hash();
+
+    // If a digest was specified, check the transcript to ensure it is
+    // included.
+    let mut found_digest : bool = contrib_digest_opt.is_none();
+
+    for round in 0..num_rounds {
+        let is_last = round + 1 == num_rounds;
+
+        // The response file begins with the hash of the challenge file, so
+        // the response file hash is computed as the round is read.
+        let (after, response_file_pubkey, last_response_file_hash) = {
+            let mut response_reader = HashReader::new(
+                (&last_challenge_file_hash[..]).chain(reader.by_ref()));
+            let mut tmp = [0; 64];
+            response_reader.read_exact(&mut tmp).unwrap();
+
+            // Check the accumulator provided by the player, keeping it for
+            // the next round unless this is the last one.
+            let mut sink = io::sink();
+            let mut scratch_writer;
+            let after_writer: &mut Write = if is_last && !load_final {
+                &mut sink
+            } else {
+                (&scratch).seek(SeekFrom::Start(0)).expect("unable to seek in the scratch file");
+                scratch_writer = BufWriter::new(&scratch);
+                &mut scratch_writer
+            };
+
+            let after = streaming::summarize_batched::<E, _, _>(
+                config,
+                &mut response_reader,
+                UseCompression::Yes,
+                CheckForCorrectness::Yes,
+                &mut after_writer,
+                batch_size
+            ).expect("unable to read accumulator from the transcript");
+            after_writer.flush().expect("unable to flush the scratch file to disk");
+
+            // Deserialize the public key provided by the player.
+            let response_file_pubkey = PublicKey::<E>::deserialize(&mut response_reader)
+                .expect("wasn't able to deserialize the response file's public key");
+
+            let mut response_file_hash = [0; 64];
+            response_file_hash.copy_from_slice(response_reader.into_hash().as_slice());
+
+            (after, response_file_pubkey, response_file_hash)
+        };
+
+        if !found_digest {
+            found_digest = digest_equal(
+                &last_response_file_hash, &contrib_digest_opt.expect(""));
+        }
+
+        print!("{}", into_hex(&last_response_file_hash));
+
+        // Verify the transformation from the previous accumulator to the new
+        // one. This also verifies the correctness of the accumulators and the
+        // public keys, with respect to the transcript so far.
+        let summary = TransformSummary::chain(&before, &after);
+        if let Err(e) = summary.verify(&response_file_pubkey, last_challenge_file_hash.as_slice()) {
+            fs::remove_file(&scratch_path).ok();
+            reject_response(e);
+        }
+        println!("");
+
+        // Compute the hash of the challenge file for the next round.
+        if !is_last {
+            let mut challenge_hasher = HashWriter::new(io::sink());
+            challenge_hasher.write_all(&last_response_file_hash).unwrap();
+            let mut scratch_reader = BufReader::with_capacity(1024 * 1024, open_scratch(&scratch_path));
+            io::copy(&mut scratch_reader, &mut challenge_hasher)
+                .expect("unable to read the scratch file");
+            last_challenge_file_hash = challenge_hasher.into_hash();
+        }
+
+        before = after;
+    }
+
+    // Load the final accumulator, which has already been checked.
+    let current_accumulator = if !load_final {
+        None
+    } else if num_rounds == 0 {
+        match initial {
+            None => Some(Accumulator::<E>::new(config)),
+            Some((_, ref mut initial_reader)) => {
+                initial_reader.seek(SeekFrom::Start((header_size + 64) as u64))
+                    .expect("unable to seek in the initial challenge");
+                Some(Accumulator::deserialize(
+                    config,
+                    initial_reader,
+                    UseCompression::No,
+                    CheckForCorrectness::No
+                ).expect("unable to read the initial accumulator"))
+            }
+        }
+    } else {
+        let mut scratch_reader = BufReader::with_capacity(1024 * 1024, open_scratch(&scratch_path));
+        Some(Accumulator::deserialize(
+            config,
+            &mut scratch_reader,
+            UseCompression::No,
+            CheckForCorrectness::No
+        ).expect("unable to read final accumulator"))
+    };
+
+    fs::remove_file(&scratch_path)
+        .unwrap_or_else(|e| panic!("unable to remove `{}`: {}", scratch_path.display(), e));
+
+    (current_accumulator, found_digest)
+}
+
+/// Open the scratch file written by `verify_batched`.
+fn open_scratch(path: &Path) -> File {
+    OpenOptions::new()
+        .read(true)
+        .open(path)
+        .unwrap_or_else(|e| panic!("unable to open `{}`: {}", path.display(), e))
+}
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[TRANSCRIPT], &[]);
+    files.add_options(&mut opts);
+    opts.optopt("r", "rounds", "number of rounds", "NUM_ROUNDS");
+    opts.optopt("d", "digest", "check contribution with given digest", "FILE");
+    opts.optflag("s", "skip-lagrange", "skip generation of phase1radix2m files");
+    opts.optopt("b", "batch-size", "verify the transcript this many points at a time", "BATCH_SIZE");
+    opts.optopt("", "initial-challenge", "first challenge of a ceremony that starts from an imported accumulator", "PATH");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    // 89 hard-coded into original code
+    let num_rounds = get_opt_default(&matches, "r", 89);
+    let skip_lagrange = matches.opt_present("s");
+    let digest_file_opt : Option<String> = get_opt(&matches, "d");
+    let batch_size_opt = get_batch_size(&matches);
+    let initial_challenge_opt : Option<String> = get_opt(&matches, "initial-challenge");
+    let contrib_digest_opt : Option<[u8;DIGEST_LENGTH]> = digest_file_opt
+        .as_ref()
+        .map(|digest_file| {
+            let mut digest_reader = OpenOptions::new()
+                .read(true).open(digest_file).expect("failed to open digest file");
+            let mut digest_buffer = [0u8; DIGEST_STRING_LENGTH];
+            digest_reader.read_exact(&mut digest_buffer).expect("invalid digest file size");
+            let digest_string = String::from(
+                str::from_utf8(&digest_buffer).expect("invalid digest data"));
+            let digest : [u8; DIGEST_LENGTH] =
+                digest_from_string(&digest_string).expect("invalid digest file");
+            digest
+        });
+
+    // The transcript holds the response files without their leading
+    // hashes, after a single header.
+    let config = format.read_header(
+        &mut open_transcript(&files, 0), UseCompression::Yes, files.path(&TRANSCRIPT));
+    let header_size = format.header_size();
+    let initial_path = initial_challenge_opt.as_ref().map(|path| Path::new(path));
+
+    match config.curve {
+        CurveId::Bn256 => verify_transcript::<Bn256>(
+            config, &files, header_size, num_rounds, &contrib_digest_opt, batch_size_opt,
+            skip_lagrange, initial_path.map(|path| open_initial_challenge(&format, config, path))),
+        CurveId::Bls12_381 => verify_transcript::<Bls12>(
+            config, &files, header_size, num_rounds, &contrib_digest_opt, batch_size_opt,
+            skip_lagrange, initial_path.map(|path| open_initial_challenge(&format, config, path))),
+    }
+}
+
+/// Verify the transcript and write the Lagrange coefficients of the final
+/// accumulator, unless `skip_lagrange` is set.
+fn verify_transcript<E: Engine>(
+    config: configuration::Configuration,
+    files: &FileOptions,
+    header_size: usize,
+    num_rounds: usize,
+    contrib_digest_opt: &Option<[u8; DIGEST_LENGTH]>,
+    batch_size_opt: Option<usize>,
+    skip_lagrange: bool,
+    initial: Option<InitialChallenge>
+)
+{
+    let (current_accumulator, found_digest) = match batch_size_opt {
+        None => {
+            let (acc, found_digest) = verify_in_memory::<E>(
+                config, files, header_size, num_rounds, contrib_digest_opt, initial);
+            (Some(acc), found_digest)
+        }
+        Some(batch_size) => verify_batched::<E>(
+            config, files, header_size, num_rounds, contrib_digest_opt, batch_size,
+            !skip_lagrange, initial)
+    };
+
     println!("Transcript OK!");
 
     if !found_digest {
@@ -187,6 +501,25 @@ fn main() {
         std::process::exit(1);
     }
     if skip_lagrange {
-        println!("WARNING: --skip-lagrange flag unused");
+        return;
+    }
+    let current_accumulator = current_accumulator.expect("the final accumulator is loaded for the Lagrange coefficients");
+
+    // Write the Lagrange coefficients for every domain size up to the
+    // number of powers in the accumulator, in the output directory.
+    for log_m in 0..(config.num_powers_log2 + 1) {
+        let m = 1 << log_m;
+        let path = files.out_dir().join(lagrange_filename(log_m));
+
+        println!("Creating {}", path.display());
+
+        let lagrange = LagrangeCoefficients::new(&current_accumulator, m);
+
+        let writer = files.create_path(&path);
+
+        let mut writer = BufWriter::new(writer);
+
+        lagrange.serialize(&mut writer).expect("unable to write Lagrange coefficients");
+        writer.flush().expect("unable to flush Lagrange coefficients to disk");
     }
 }
@@ -0,0 +1,70 @@
+extern crate powersoftau;
+extern crate getopts;
+extern crate hex;
+extern crate num_cpus;
+
+use powersoftau::beacon::*;
+use powersoftau::cmd_utils::*;
+
+use std::io::BufReader;
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    let mut files = FileOptions::new(&[BEACON_DESCRIPTOR], &[]);
+    files.add_options(&mut opts);
+    opts.optopt("t", "threads", "number of threads to use (default: number of CPUs)", "THREADS");
+    let matches = match_or_fail(&opts);
+
+    files.parse(&matches);
+    let num_threads = get_opt_default(&matches, "threads", num_cpus::get());
+
+    let descriptor_path = files.path(&BEACON_DESCRIPTOR);
+    let reader = BufReader::new(files.open(&BEACON_DESCRIPTOR));
+    let log = match BeaconLog::read(reader) {
+        Ok(log) => log,
+        Err(e @ BeaconError::SourcesMismatch) => {
+            println!("Verification failed: {}", e);
+            std::process::exit(1);
+        }
+        Err(e) => panic!("unable to read beacon descriptor `{}`: {}", descriptor_path.display(), e),
+    };
+
+    for source in &log.params.sources {
+        println!("Source `{}`: {}", source.name, hex::encode(&source.value));
+    }
+    if !log.params.sources.is_empty() {
+        println!("The beacon value {} combines these sources", hex::encode(&log.params.value));
+    }
+
+    let segments = match log.segments() {
+        Ok(segments) => segments,
+        Err(e) => {
+            println!("Verification failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Checking 2^{} iterations of SHA-256 in {} segments on {} threads, this could take a while...",
+        log.params.iterations_log2,
+        segments.len(),
+        num_threads);
+
+    let results = verify_segments(&segments, num_threads, |k, ok| {
+        println!(
+            "Segment {} (iterations {} to {}): {}",
+            k,
+            segments[k].start,
+            segments[k].end,
+            if ok { "OK" } else { "FAILED" });
+    });
+
+    let num_failed = results.iter().filter(|&&ok| !ok).count();
+    if num_failed > 0 {
+        println!("Verification failed: {} of {} segments do not match their checkpoints", num_failed, segments.len());
+        std::process::exit(1);
+    }
+
+    println!("Verification succeeded! The result of the beacon is {}", hex::encode(&log.result));
+}
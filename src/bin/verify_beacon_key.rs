@@ -0,0 +1,146 @@
+extern crate powersoftau;
+extern crate getopts;
+extern crate hex;
+
+use powersoftau::*;
+use powersoftau::beacon::*;
+use powersoftau::bls12::Bls12;
+use powersoftau::bn256::Bn256;
+use powersoftau::cmd_utils::*;
+use powersoftau::configuration::public_key_size;
+use powersoftau::engine::{CurveId, Engine};
+
+use std::fs::File;
+use std::io::{self, Read, BufReader, Seek, SeekFrom};
+use std::process;
+
+/// Regenerate the beacon's public key for the challenge with hash
+/// `challenge_hash`, and compare it with the one at the end of `reader`.
+fn check_public_key<E: Engine>(
+    result: &BeaconHash,
+    challenge_hash: &[u8],
+    reader: &mut BufReader<File>
+) -> bool
+{
+    reader.seek(SeekFrom::End(-(public_key_size::<E>() as i64)))
+        .expect("unable to seek to the public key");
+    let recorded_pubkey = PublicKey::<E>::deserialize(reader)
+        .expect("unable to read the public key");
+
+    let (pubkey, _) = beacon_keypair::<E>(result, challenge_hash);
+
+    pubkey == recorded_pubkey
+}
+
+fn main() {
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[CHALLENGE, RESPONSE, TRANSCRIPT, BEACON_DESCRIPTOR], &[]);
+    files.add_options(&mut opts);
+    opts.optopt("", "beacon-result", "the result of the beacon in hex, instead of reading it from the descriptor", "HEX");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    let result_opt : Option<String> = get_opt(&matches, "beacon-result");
+    let use_transcript = matches.opt_present("transcript");
+
+    let result = match result_opt {
+        Some(s) => {
+            let bytes = hex::decode(s.trim()).unwrap_or_else(|e| panic!("invalid beacon result: {}", e));
+            if bytes.len() != BEACON_HASH_SIZE {
+                panic!("the beacon result should be {} bytes, but it's {}", BEACON_HASH_SIZE, bytes.len());
+            }
+            let mut result = [0u8; BEACON_HASH_SIZE];
+            result.copy_from_slice(&bytes);
+            result
+        }
+        None => {
+            let descriptor_path = files.path(&BEACON_DESCRIPTOR);
+            BeaconLog::read(BufReader::new(files.open(&BEACON_DESCRIPTOR)))
+                .unwrap_or_else(|e| panic!("unable to read beacon descriptor `{}`: {}", descriptor_path.display(), e))
+                .result
+        }
+    };
+
+    // Hash the challenge before the beacon, as the beacon did.
+    let challenge_path = files.path(&CHALLENGE);
+    let challenge_reader = files.open(&CHALLENGE);
+
+    let metadata = challenge_reader.metadata().expect("unable to get filesystem metadata for the challenge file");
+
+    let mut challenge_reader = BufReader::new(challenge_reader);
+    let config = format.read_header(&mut challenge_reader, UseCompression::No, challenge_path);
+
+    if metadata.len() != ((format.header_size() + config.accumulator_size_bytes) as u64) {
+        panic!(
+            "The size of `{}` should be {}, but it's {}, so something isn't right.",
+            challenge_path.display(),
+            format.header_size() + config.accumulator_size_bytes,
+            metadata.len());
+    }
+
+    println!("Hashing `{}`...", challenge_path.display());
+    let mut challenge_reader = HashReader::new(challenge_reader);
+    io::copy(&mut challenge_reader, &mut io::sink()).expect("unable to read the challenge file");
+    let challenge_hash = challenge_reader.into_hash();
+
+    // The response or transcript must be for an accumulator of the same size.
+    let file = if use_transcript { TRANSCRIPT } else { RESPONSE };
+    let path = files.path(&file);
+    let reader = files.open(&file);
+
+    let metadata = reader.metadata().expect("unable to get filesystem metadata");
+
+    let mut reader = BufReader::new(reader);
+    let file_format = FormatOptions {
+        num_powers: Some(config.num_powers),
+        curve: Some(config.curve),
+        legacy: format.legacy
+    };
+    file_format.read_header(&mut reader, UseCompression::Yes, path);
+
+    if use_transcript {
+        // Each round of the transcript is a response file without its
+        // leading hash, so the beacon's public key ends the last round.
+        let round_size = (config.contribution_size_bytes - 64) as u64;
+        let size = metadata.len() - format.header_size() as u64;
+        if size == 0 || size % round_size != 0 {
+            panic!(
+                "The size of `{}` should be a multiple of {}, but it's {}, so something isn't right.",
+                path.display(),
+                round_size,
+                size);
+        }
+    } else {
+        if metadata.len() != ((format.header_size() + config.contribution_size_bytes) as u64) {
+            panic!(
+                "The size of `{}` should be {}, but it's {}, so something isn't right.",
+                path.display(),
+                format.header_size() + config.contribution_size_bytes,
+                metadata.len());
+        }
+
+        let mut response_challenge_hash = [0; 64];
+        reader.read_exact(&mut response_challenge_hash).expect("couldn't read hash of challenge file from response file");
+        if &response_challenge_hash[..] != challenge_hash.as_slice() {
+            println!("Verification failed: `{}` is not a response to `{}`", path.display(), challenge_path.display());
+            process::exit(1);
+        }
+    }
+
+    println!("Regenerating the beacon's keypair from {}...", hex::encode(&result));
+
+    let ok = match config.curve {
+        CurveId::Bn256 => check_public_key::<Bn256>(&result, challenge_hash.as_slice(), &mut reader),
+        CurveId::Bls12_381 => check_public_key::<Bls12>(&result, challenge_hash.as_slice(), &mut reader),
+    };
+
+    if !ok {
+        println!("Verification failed: the public key in `{}` was not drawn from the beacon", path.display());
+        process::exit(1);
+    }
+
+    println!("Verification succeeded! The public key in `{}` was drawn from the beacon", path.display());
+}
@@ -0,0 +1,79 @@
+extern crate powersoftau;
+
+use powersoftau::*;
+use powersoftau::cmd_utils::*;
+use powersoftau::engine::CurveId;
+use powersoftau::ptau::*;
+
+use std::io::BufReader;
+use std::process;
+
+fn main() {
+    let (format, files) = parse_simple_options(&[PTAU], &[]);
+
+    if let Some(curve) = format.curve {
+        if curve != CurveId::Bn256 {
+            panic!("snarkjs .ptau files can only be verified for bn256, not {}", curve);
+        }
+    }
+
+    let ptau_path = files.path(&PTAU);
+    let reader = files.open(&PTAU);
+
+    let mut reader = BufReader::with_capacity(1024 * 1024, reader);
+
+    println!("Reading `{}`...", ptau_path.display());
+
+    let ptau = read_ptau(&mut reader, format.num_powers)
+        .unwrap_or_else(|e| panic!("unable to read `{}`: {}", ptau_path.display(), e));
+
+    if ptau.contributions.is_empty() {
+        println!("`{}` has no contributions.", ptau_path.display());
+        process::exit(1);
+    }
+
+    // Check every contribution, reporting all of the failures.
+    let mut failed = None;
+    let mut challenge_hash = first_challenge_hash(ptau.ceremony_power);
+    let mut previous = None;
+
+    for (i, contribution) in ptau.contributions.iter().enumerate() {
+        print!("Verifying contribution {} of {}...", i + 1, ptau.contributions.len());
+        match verify_ptau_contribution(previous, contribution, &challenge_hash) {
+            Ok(()) => println!(" OK"),
+            Err(e) => {
+                println!(" FAILED: {}", e);
+                failed = failed.or(Some(e));
+            }
+        }
+        challenge_hash = contribution.next_challenge_hash;
+        previous = Some(contribution);
+    }
+
+    // The powers must be those that the last contribution committed to.
+    let last = ptau.contributions.last().unwrap();
+    let acc = &ptau.accumulator;
+
+    print!("Checking the {} powers of the accumulator...", acc.config.num_powers);
+    if acc.tau_powers_g1[1] != last.tau_g1
+        || acc.tau_powers_g2[1] != last.tau_g2
+        || acc.alpha_tau_powers_g1[0] != last.alpha_g1
+        || acc.beta_tau_powers_g1[0] != last.beta_g1
+        || acc.beta_g2 != last.beta_g2
+    {
+        println!(" FAILED: the accumulator is not that of the last contribution");
+        process::exit(1);
+    }
+    if let Err(e) = acc.verify_consistency() {
+        println!(" FAILED: {}", e);
+        process::exit(e.exit_code());
+    }
+    println!(" OK");
+
+    if let Some(e) = failed {
+        println!("INVALID CONTRIBUTIONS!");
+        process::exit(e.exit_code());
+    }
+
+    println!("All {} contributions of `{}` are valid.", ptau.contributions.len(), ptau_path.display());
+}
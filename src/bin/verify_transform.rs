@@ -1,55 +1,79 @@
 extern crate powersoftau;
+extern crate getopts;
+
 use powersoftau::*;
+use powersoftau::bls12::Bls12;
+use powersoftau::bn256::Bn256;
+use powersoftau::cmd_utils::*;
+use powersoftau::engine::{CurveId, Engine};
 
-use std::fs::OpenOptions;
-use std::io::{Read, Write, BufWriter, BufReader};
+use std::fs::File;
+use std::io::{self, Read, Write, BufWriter, BufReader, Seek, SeekFrom};
+use std::process;
 
 fn main() {
-    let config = cmd_utils::parse_simple_options();
-    // Try to load `./challenge` from disk.
-    let challenge_reader = OpenOptions::new()
-                            .read(true)
-                            .open("challenge").expect("unable open `./challenge` in this directory");
-
-    {
-        let metadata = challenge_reader.metadata().expect("unable to get filesystem metadata for `./challenge`");
-        if metadata.len() != (config.accumulator_size_bytes as u64) {
-            panic!(
-                "The size of `./challenge` should be {}, but it's {}, so something isn't right.",
-                config.accumulator_size_bytes,
-                metadata.len());
-        }
+    let mut opts = getopts::Options::new();
+    opts.optflag("h", "help", "print this help");
+    FormatOptions::add_options(&mut opts);
+    let mut files = FileOptions::new(&[CHALLENGE, RESPONSE], &[NEW_CHALLENGE]);
+    files.add_options(&mut opts);
+    opts.optopt("b", "batch-size", "verify the response this many points at a time", "BATCH_SIZE");
+    let matches = match_or_fail(&opts);
+
+    let format = FormatOptions::from_matches(&matches);
+    files.parse(&matches);
+    let batch_size_opt = get_batch_size(&matches);
+
+    // Try to load the challenge from disk.
+    let challenge_path = files.path(&CHALLENGE);
+    let challenge_reader = files.open(&CHALLENGE);
+
+    let metadata = challenge_reader.metadata().expect("unable to get filesystem metadata for the challenge file");
+
+    let mut challenge_reader = BufReader::new(challenge_reader);
+    let config = format.read_header(&mut challenge_reader, UseCompression::No, challenge_path);
+
+    if metadata.len() != ((format.header_size() + config.accumulator_size_bytes) as u64) {
+        panic!(
+            "The size of `{}` should be {}, but it's {}, so something isn't right.",
+            challenge_path.display(),
+            format.header_size() + config.accumulator_size_bytes,
+            metadata.len());
     }
 
-    let challenge_reader = BufReader::new(challenge_reader);
     let mut challenge_reader = HashReader::new(challenge_reader);
 
-    // Try to load `./response` from disk.
-    let response_reader = OpenOptions::new()
-                            .read(true)
-                            .open("response").expect("unable open `./response` in this directory");
-
-    {
-        let metadata = response_reader.metadata().expect("unable to get filesystem metadata for `./response`");
-        if metadata.len() != (config.contribution_size_bytes as u64) {
-            panic!(
-                "The size of `./response` should be {}, but it's {}, so something isn't right.",
-                config.contribution_size_bytes,
-                metadata.len());
-        }
+    // Try to load the response from disk.
+    let response_path = files.path(&RESPONSE);
+    let response_reader = files.open(&RESPONSE);
+
+    let metadata = response_reader.metadata().expect("unable to get filesystem metadata for the response file");
+
+    // The response must be for an accumulator of the same size.
+    let mut response_reader = BufReader::new(response_reader);
+    let response_format = FormatOptions {
+        num_powers: Some(config.num_powers),
+        curve: Some(config.curve),
+        legacy: format.legacy
+    };
+    response_format.read_header(&mut response_reader, UseCompression::Yes, response_path);
+
+    if metadata.len() != ((format.header_size() + config.contribution_size_bytes) as u64) {
+        panic!(
+            "The size of `{}` should be {}, but it's {}, so something isn't right.",
+            response_path.display(),
+            format.header_size() + config.contribution_size_bytes,
+            metadata.len());
     }
 
-    let response_reader = BufReader::new(response_reader);
     let mut response_reader = HashReader::new(response_reader);
 
-    // Create new_challenge file
-    let writer = OpenOptions::new()
-                            .read(false)
-                            .write(true)
-                            .create_new(true)
-                            .open("new_challenge").expect("unable to create `./new_challenge`");
+    // Create the new challenge file
+    let new_challenge_path = files.path(&NEW_CHALLENGE);
+    let writer = files.create(&NEW_CHALLENGE);
 
     let mut writer = BufWriter::new(writer);
+    format.write_header(&mut writer, &config, UseCompression::No, new_challenge_path);
 
     // Deserialize the current challenge
 
@@ -61,8 +85,40 @@ fn main() {
         challenge_reader.read_exact(&mut tmp).expect("unable to read BLAKE2b hash of previous contribution");
     }
 
+    // Read the hash of the challenge file the participant responded to
+    let mut response_challenge_hash = [0; 64];
+    response_reader.read_exact(&mut response_challenge_hash).expect("couldn't read hash of challenge file from response file");
+
+    match (config.curve, batch_size_opt) {
+        (CurveId::Bn256, None) => verify_in_memory::<Bn256>(
+            config, challenge_reader, response_reader, &response_challenge_hash, &mut writer, &files),
+        (CurveId::Bls12_381, None) => verify_in_memory::<Bls12>(
+            config, challenge_reader, response_reader, &response_challenge_hash, &mut writer, &files),
+        (CurveId::Bn256, Some(batch_size)) => verify_batched::<Bn256>(
+            config, challenge_reader, response_reader, &response_challenge_hash, &mut writer, &files,
+            format.header_size(), batch_size),
+        (CurveId::Bls12_381, Some(batch_size)) => verify_batched::<Bls12>(
+            config, challenge_reader, response_reader, &response_challenge_hash, &mut writer, &files,
+            format.header_size(), batch_size),
+    }
+
+    println!("Done! `{}` contains the new challenge file. The other files", new_challenge_path.display());
+    println!("were left alone.");
+}
+
+/// Load both accumulators into memory, verify the transformation and write
+/// the new challenge.
+fn verify_in_memory<E: Engine>(
+    config: configuration::Configuration,
+    mut challenge_reader: HashReader<BufReader<File>>,
+    mut response_reader: HashReader<BufReader<File>>,
+    response_challenge_hash: &[u8; 64],
+    writer: &mut BufWriter<File>,
+    files: &FileOptions
+)
+{
     // Load the current accumulator into memory
-    let current_accumulator = Accumulator::deserialize(
+    let current_accumulator = Accumulator::<E>::deserialize(
         config,
         &mut challenge_reader,
         UseCompression::No,
@@ -75,13 +131,8 @@ fn main() {
     // Load the response into memory
 
     // Check the hash chain
-    {
-        let mut response_challenge_hash = [0; 64];
-        response_reader.read_exact(&mut response_challenge_hash).expect("couldn't read hash of challenge file from response file");
-
-        if &response_challenge_hash[..] != current_accumulator_hash.as_slice() {
-            panic!("Hash chain failure. This is not the right response.");
-        }
+    if &response_challenge_hash[..] != current_accumulator_hash.as_slice() {
+        panic!("Hash chain failure. This is not the right response.");
     }
 
     // Load the response's accumulator
@@ -92,22 +143,99 @@ fn main() {
         .expect("wasn't able to deserialize the response file's accumulator");
 
     // Load the response's pubkey
-    let public_key = PublicKey::deserialize(&mut response_reader)
+    let public_key = PublicKey::<E>::deserialize(&mut response_reader)
                                            .expect("wasn't able to deserialize the response file's public key");
 
     // Get the hash of the response file
     let response_hash = response_reader.into_hash();
 
-    if !verify_transform(&current_accumulator, &new_accumulator, &public_key, current_accumulator_hash.as_slice()) {
-        println!("Verification failed, contribution was invalid somehow.");
-        panic!("INVALID CONTRIBUTION!!!");
-    } else {
-        println!("Verification succeeded!");
+    if let Err(e) = verify_transform(&current_accumulator, &new_accumulator, &public_key, current_accumulator_hash.as_slice()) {
+        reject_contribution(files, e);
     }
+    println!("Verification succeeded!");
 
+    print_response_hash(response_hash.as_slice());
+
+    println!("Verification succeeded! Writing to `{}`...", files.path(&NEW_CHALLENGE).display());
+
+    writer.write_all(response_hash.as_slice()).expect("couldn't write response file's hash into the new challenge file");
+    new_accumulator.serialize(writer, UseCompression::No).expect("unable to write uncompressed accumulator into the new challenge file");
+    writer.flush().expect("unable to flush the new challenge file to disk");
+}
+
+/// Read both accumulators in step, `batch_size` points at a time, writing the
+/// new accumulator to the new challenge and computing the hashes of both files
+/// in the same pass. The new challenge is removed if verification fails.
+fn verify_batched<E: Engine>(
+    config: configuration::Configuration,
+    mut challenge_reader: HashReader<BufReader<File>>,
+    mut response_reader: HashReader<BufReader<File>>,
+    response_challenge_hash: &[u8; 64],
+    writer: &mut BufWriter<File>,
+    files: &FileOptions,
+    header_size: usize,
+    batch_size: usize
+)
+{
+    // The hash of the response file is only known at the end, so leave
+    // space for it and fill it in afterwards.
+    writer.write_all(&[0; 64]).expect("couldn't write to the new challenge file");
+
+    println!("Verifying in batches of {} points...", batch_size);
+
+    let summary = streaming::summarize_transform_batched::<E, _, _, _, _>(
+        config,
+        &mut challenge_reader,
+        UseCompression::No,
+        &mut response_reader,
+        UseCompression::Yes,
+        &mut io::sink(),
+        writer,
+        batch_size
+    ).expect("wasn't able to read the challenge and response accumulators");
+
+    // Load the response's pubkey
+    let public_key = PublicKey::<E>::deserialize(&mut response_reader)
+                                           .expect("wasn't able to deserialize the response file's public key");
+
+    let current_accumulator_hash = challenge_reader.into_hash();
+    let response_hash = response_reader.into_hash();
+
+    // Check the hash chain
+    if &response_challenge_hash[..] != current_accumulator_hash.as_slice() {
+        files.remove(&NEW_CHALLENGE);
+        panic!("Hash chain failure. This is not the right response.");
+    }
+
+    if let Err(e) = summary.verify(&public_key, current_accumulator_hash.as_slice()) {
+        reject_contribution(files, e);
+    }
+    println!("Verification succeeded!");
+
+    print_response_hash(response_hash.as_slice());
+
+    println!("Verification succeeded! Writing to `{}`...", files.path(&NEW_CHALLENGE).display());
+
+    writer.seek(SeekFrom::Start(header_size as u64)).expect("couldn't seek in the new challenge file");
+    writer.write_all(response_hash.as_slice()).expect("couldn't write response file's hash into the new challenge file");
+    writer.flush().expect("unable to flush the new challenge file to disk");
+}
+
+/// Remove the new challenge and exit with the code identifying the failed
+/// check.
+fn reject_contribution(files: &FileOptions, e: VerificationError) -> !
+{
+    files.remove(&NEW_CHALLENGE);
+    println!("Verification failed: {}", e);
+    println!("INVALID CONTRIBUTION!!!");
+    process::exit(e.exit_code());
+}
+
+fn print_response_hash(response_hash: &[u8])
+{
     println!("Here's the BLAKE2b hash of the participant's response file:");
 
-    for line in response_hash.as_slice().chunks(16) {
+    for line in response_hash.chunks(16) {
         print!("\t");
         for section in line.chunks(4) {
             for b in section {
@@ -117,12 +245,4 @@ fn main() {
         }
         println!("");
     }
-
-    println!("Verification succeeded! Writing to `./new_challenge`...");
-
-    writer.write_all(response_hash.as_slice()).expect("couldn't write response file's hash into the `./new_challenge` file");
-    new_accumulator.serialize(&mut writer, UseCompression::No).expect("unable to write uncompressed accumulator into the `./new_challenge` file");
-
-    println!("Done! `./new_challenge` contains the new challenge file. The other files");
-    println!("were left alone.");
 }
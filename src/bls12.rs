@@ -0,0 +1,218 @@
+//! The BLS12-381 engine, backed by the `bls12_381` crate. Points are encoded
+//! in the big-endian form of that crate (and of Zcash), with flags in the
+//! most significant bits of the x coordinate.
+
+use bls12_381::{G1Affine, G1Projective, G2Affine, G2Projective, Gt, Scalar};
+use byteorder::{ByteOrder, LittleEndian};
+use rand::Rng;
+use std::io::{self, Read, Write};
+
+use engine::*;
+use super::DeserializationError;
+
+/// (r - 1) / 2^32, as little-endian limbs. The root of unity is 7 raised to
+/// this power.
+const ROOT_OF_UNITY_EXP: [u64; 4] =
+    [0xfffe5bfeffffffff, 0x09a1d80553bda402, 0x299d7d483339d808, 0x0000000073eda753];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bls12;
+
+impl Engine for Bls12 {
+    type Fr = Scalar;
+    type G1 = G1Projective;
+    type G2 = G2Projective;
+    type Gt = Gt;
+
+    const CURVE: CurveId = CurveId::Bls12_381;
+
+    fn pairing(p: &G1Projective, q: &G2Projective) -> Gt {
+        ::bls12_381::pairing(&G1Affine::from(p), &G2Affine::from(q))
+    }
+}
+
+impl ScalarField for Scalar {
+    const TWO_ADICITY: u32 = 32;
+
+    fn zero() -> Self {
+        Scalar::zero()
+    }
+
+    fn one() -> Self {
+        Scalar::one()
+    }
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        // Reducing 512 bits gives a negligible bias.
+        let mut bytes = [0u8; 64];
+        rng.fill(&mut bytes[..]);
+        Scalar::from_bytes_wide(&bytes)
+    }
+
+    fn from_u64(v: u64) -> Self {
+        Scalar::from(v)
+    }
+
+    fn pow(&self, exp: u64) -> Self {
+        self.pow_vartime(&[exp, 0, 0, 0])
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        let inv = self.invert();
+        if bool::from(inv.is_some()) {
+            Some(inv.unwrap())
+        } else {
+            None
+        }
+    }
+
+    fn root_of_unity() -> Self {
+        Scalar::from(7).pow_vartime(&ROOT_OF_UNITY_EXP)
+    }
+
+    fn to_limbs(&self) -> [u64; 4] {
+        let bytes = self.to_bytes();
+
+        let mut limbs = [0u64; 4];
+        for i in 0..4 {
+            limbs[i] = LittleEndian::read_u64(&bytes[(8 * i)..(8 * i + 8)]);
+        }
+        limbs
+    }
+}
+
+impl CurvePoint for G1Projective {
+    type Scalar = Scalar;
+
+    const UNCOMPRESSED_SIZE: usize = 96;
+    const COMPRESSED_SIZE: usize = 48;
+
+    fn zero() -> Self {
+        G1Projective::identity()
+    }
+
+    fn one() -> Self {
+        G1Projective::generator()
+    }
+
+    fn is_zero(&self) -> bool {
+        bool::from(self.is_identity())
+    }
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        G1Projective::generator() * <Scalar as ScalarField>::random(rng)
+    }
+
+    fn mul(&self, scalar: &Scalar) -> Self {
+        self * scalar
+    }
+
+    fn write_uncompressed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&G1Affine::from(self).to_uncompressed())
+    }
+
+    fn write_compressed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&G1Affine::from(self).to_compressed())
+    }
+
+    fn read_uncompressed_unchecked<R: Read>(reader: &mut R) -> Result<Self, DeserializationError> {
+        let mut bytes = [0u8; 96];
+        reader.read_exact(&mut bytes)?;
+
+        let p = G1Affine::from_uncompressed_unchecked(&bytes);
+        if bool::from(p.is_some()) {
+            Ok(G1Projective::from(p.unwrap()))
+        } else {
+            Err(DeserializationError::InvalidEncoding)
+        }
+    }
+
+    fn read_compressed<R: Read>(reader: &mut R) -> Result<Self, DeserializationError> {
+        let mut bytes = [0u8; 48];
+        reader.read_exact(&mut bytes)?;
+
+        // Also checks the point is in the subgroup.
+        let p = G1Affine::from_compressed(&bytes);
+        if bool::from(p.is_some()) {
+            Ok(G1Projective::from(p.unwrap()))
+        } else {
+            Err(DeserializationError::InvalidEncoding)
+        }
+    }
+
+    fn is_on_curve(&self) -> bool {
+        bool::from(G1Projective::is_on_curve(self))
+    }
+
+    fn is_in_subgroup(&self) -> bool {
+        bool::from(G1Affine::from(self).is_torsion_free())
+    }
+}
+
+impl CurvePoint for G2Projective {
+    type Scalar = Scalar;
+
+    const UNCOMPRESSED_SIZE: usize = 192;
+    const COMPRESSED_SIZE: usize = 96;
+
+    fn zero() -> Self {
+        G2Projective::identity()
+    }
+
+    fn one() -> Self {
+        G2Projective::generator()
+    }
+
+    fn is_zero(&self) -> bool {
+        bool::from(self.is_identity())
+    }
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        G2Projective::generator() * <Scalar as ScalarField>::random(rng)
+    }
+
+    fn mul(&self, scalar: &Scalar) -> Self {
+        self * scalar
+    }
+
+    fn write_uncompressed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&G2Affine::from(self).to_uncompressed())
+    }
+
+    fn write_compressed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&G2Affine::from(self).to_compressed())
+    }
+
+    fn read_uncompressed_unchecked<R: Read>(reader: &mut R) -> Result<Self, DeserializationError> {
+        let mut bytes = [0u8; 192];
+        reader.read_exact(&mut bytes)?;
+
+        let p = G2Affine::from_uncompressed_unchecked(&bytes);
+        if bool::from(p.is_some()) {
+            Ok(G2Projective::from(p.unwrap()))
+        } else {
+            Err(DeserializationError::InvalidEncoding)
+        }
+    }
+
+    fn read_compressed<R: Read>(reader: &mut R) -> Result<Self, DeserializationError> {
+        let mut bytes = [0u8; 96];
+        reader.read_exact(&mut bytes)?;
+
+        // Also checks the point is in the subgroup.
+        let p = G2Affine::from_compressed(&bytes);
+        if bool::from(p.is_some()) {
+            Ok(G2Projective::from(p.unwrap()))
+        } else {
+            Err(DeserializationError::InvalidEncoding)
+        }
+    }
+
+    fn is_on_curve(&self) -> bool {
+        bool::from(G2Projective::is_on_curve(self))
+    }
+
+    fn is_in_subgroup(&self) -> bool {
+        bool::from(G2Affine::from(self).is_torsion_free())
+    }
+}
@@ -0,0 +1,240 @@
+//! The BN256 engine, backed by the `bn` crate. Points are encoded with
+//! `bincode`, as in the original ceremony.
+
+use bincode::{self, EncodingError};
+use bn::{self, AffineG1, AffineG2, CurveError, Fq, Fq2, Fr, G1, G2, Group, Gt};
+use bn::arith::U256;
+use byteorder::{ByteOrder, BigEndian};
+use rand::Rng;
+use rustc_serialize::Encodable;
+use std::io::{self, Read, Write};
+
+use configuration::*;
+use engine::*;
+use super::DeserializationError;
+
+const INF : bincode::SizeLimit = bincode::SizeLimit::Infinite;
+
+/// A primitive 2^28-th root of unity in Fr, 5^((r - 1) / 2^28).
+const FR_ROOT_OF_UNITY: &str =
+    "19103219067921713944291392827692070036145651957329286315305642004821462161904";
+
+/// 2^256 mod q, the Montgomery factor of the base field.
+const FQ_MONTGOMERY_R: &str =
+    "6350874878119819312338956282401532409788428879151445726012394534686998597021";
+
+/// The Montgomery factor of the base field, by which other implementations
+/// multiply coordinates in their internal representation.
+pub fn montgomery_r() -> Fq
+{
+    Fq::from_str(FQ_MONTGOMERY_R).expect("R is a valid field element")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bn256;
+
+impl Engine for Bn256 {
+    type Fr = Fr;
+    type G1 = G1;
+    type G2 = G2;
+    type Gt = Gt;
+
+    const CURVE: CurveId = CurveId::Bn256;
+
+    fn pairing(p: &G1, q: &G2) -> Gt {
+        bn::pairing(*p, *q)
+    }
+}
+
+impl ScalarField for Fr {
+    const TWO_ADICITY: u32 = 28;
+
+    fn zero() -> Self {
+        Fr::zero()
+    }
+
+    fn one() -> Self {
+        Fr::one()
+    }
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        Fr::random(rng)
+    }
+
+    fn from_u64(v: u64) -> Self {
+        Fr::new(U256::from(v)).expect("small integers are valid field elements")
+    }
+
+    fn pow(&self, exp: u64) -> Self {
+        Fr::pow(self, Self::from_u64(exp))
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        Fr::inverse(self)
+    }
+
+    fn root_of_unity() -> Self {
+        Fr::from_str(FR_ROOT_OF_UNITY).expect("root of unity is a valid field element")
+    }
+
+    fn to_limbs(&self) -> [u64; 4] {
+        let mut bytes = [0u8; 32];
+        self.into_u256().to_big_endian(&mut bytes).expect("32 bytes is enough for Fr");
+
+        let mut limbs = [0u64; 4];
+        for i in 0..4 {
+            limbs[i] = BigEndian::read_u64(&bytes[(24 - 8 * i)..(32 - 8 * i)]);
+        }
+        limbs
+    }
+}
+
+fn encode<W: Write, T: Encodable>(writer: &mut W, v: &T) -> io::Result<()>
+{
+    match bincode::encode_into(v, writer, INF) {
+        Err(EncodingError::IoError(io_err)) => Err(io_err),
+        Err(EncodingError::SizeLimit) => Err(io::ErrorKind::Other)?,
+        Ok(()) => Ok(()),
+    }
+}
+
+/// Reads the leading byte of an uncompressed point, as written by `bn`.
+/// Returns false for the point at infinity.
+fn decode_uncompressed_tag<R: Read>(reader: &mut R) -> Result<bool, DeserializationError>
+{
+    let tag : u8 = bincode::decode_from(reader, INF)?;
+    match tag {
+        0 => Ok(false),
+        4 => Ok(true),
+        _ => Err(DeserializationError::CurveError(CurveError::InvalidEncoding))
+    }
+}
+
+// `bn` checks curve membership whenever it decodes a point, so uncompressed
+// points are decoded from their raw coordinates and validated separately,
+// which allows trusted inputs to skip the checks entirely.
+
+impl CurvePoint for G1 {
+    type Scalar = Fr;
+
+    const UNCOMPRESSED_SIZE: usize = G1_UNCOMPRESSED_BYTE_SIZE;
+    const COMPRESSED_SIZE: usize = G1_COMPRESSED_BYTE_SIZE;
+
+    fn zero() -> Self {
+        <G1 as Group>::zero()
+    }
+
+    fn one() -> Self {
+        <G1 as Group>::one()
+    }
+
+    fn is_zero(&self) -> bool {
+        <G1 as Group>::is_zero(self)
+    }
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        <G1 as Group>::random(rng)
+    }
+
+    fn mul(&self, scalar: &Fr) -> Self {
+        *self * *scalar
+    }
+
+    fn write_uncompressed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode(writer, self)
+    }
+
+    fn write_compressed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode(writer, &<G1 as Group>::as_compressed(self))
+    }
+
+    fn read_uncompressed_unchecked<R: Read>(reader: &mut R) -> Result<Self, DeserializationError> {
+        if !decode_uncompressed_tag(reader)? {
+            return Ok(<G1 as Group>::zero());
+        }
+
+        let x : Fq = bincode::decode_from(reader, INF)?;
+        let y : Fq = bincode::decode_from(reader, INF)?;
+        Ok(G1::new(x, y, Fq::one()))
+    }
+
+    fn read_compressed<R: Read>(reader: &mut R) -> Result<Self, DeserializationError> {
+        let comp : <G1 as Group>::Compressed = bincode::decode_from(reader, INF)?;
+        Ok(<G1 as Group>::from_compressed(&comp)?)
+    }
+
+    fn is_on_curve(&self) -> bool {
+        match AffineG1::from_jacobian(*self) {
+            Some(p) => p.y() * p.y() == p.x() * p.x() * p.x() + G1::b(),
+            None => true
+        }
+    }
+
+    fn is_in_subgroup(&self) -> bool {
+        // G1 has cofactor 1, so every point on the curve is in the subgroup.
+        true
+    }
+}
+
+impl CurvePoint for G2 {
+    type Scalar = Fr;
+
+    const UNCOMPRESSED_SIZE: usize = G2_UNCOMPRESSED_BYTE_SIZE;
+    const COMPRESSED_SIZE: usize = G2_COMPRESSED_BYTE_SIZE;
+
+    fn zero() -> Self {
+        <G2 as Group>::zero()
+    }
+
+    fn one() -> Self {
+        <G2 as Group>::one()
+    }
+
+    fn is_zero(&self) -> bool {
+        <G2 as Group>::is_zero(self)
+    }
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        <G2 as Group>::random(rng)
+    }
+
+    fn mul(&self, scalar: &Fr) -> Self {
+        *self * *scalar
+    }
+
+    fn write_uncompressed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode(writer, self)
+    }
+
+    fn write_compressed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode(writer, &<G2 as Group>::as_compressed(self))
+    }
+
+    fn read_uncompressed_unchecked<R: Read>(reader: &mut R) -> Result<Self, DeserializationError> {
+        if !decode_uncompressed_tag(reader)? {
+            return Ok(<G2 as Group>::zero());
+        }
+
+        let x : Fq2 = bincode::decode_from(reader, INF)?;
+        let y : Fq2 = bincode::decode_from(reader, INF)?;
+        Ok(G2::new(x, y, Fq2::one()))
+    }
+
+    fn read_compressed<R: Read>(reader: &mut R) -> Result<Self, DeserializationError> {
+        let comp : <G2 as Group>::Compressed = bincode::decode_from(reader, INF)?;
+        Ok(<G2 as Group>::from_compressed(&comp)?)
+    }
+
+    fn is_on_curve(&self) -> bool {
+        match AffineG2::from_jacobian(*self) {
+            Some(p) => p.y() * p.y() == p.x() * p.x() * p.x() + G2::b(),
+            None => true
+        }
+    }
+
+    fn is_in_subgroup(&self) -> bool {
+        // The scalar (r - 1) is represented by -1 in Fr, so p * (r - 1) = -p
+        // exactly when the order of p divides r.
+        *self * (-Fr::one()) == -*self
+    }
+}
@@ -1,8 +1,15 @@
 extern crate getopts;
 
+use bn256::Bn256;
 use configuration::*;
+use engine::CurveId;
+use header::*;
 use std::str::FromStr;
 use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use {Accumulator, CheckForCorrectness, UseCompression};
 
 pub const DIGEST_LENGTH : usize = 64;
 pub const DIGEST_STRING_LENGTH : usize = (DIGEST_LENGTH * 2) + 4 * 4;
@@ -89,14 +96,247 @@ pub fn get_opt_default<T>(matches: &getopts::Matches, name: &str, default : T) -
     }
 }
 
-/// Handle the common case of commands that only take '-n NUM_POWERS' option.
-pub fn parse_simple_options() -> Configuration
+/// The '--batch-size' option, which is rejected if it is zero.
+pub fn get_batch_size(matches: &getopts::Matches) -> Option<usize>
 {
+    let batch_size_opt : Option<usize> = get_opt(matches, "batch-size");
+    if batch_size_opt == Some(0) {
+        panic!("arg batch-size: the batch size must be at least 1");
+    }
+    batch_size_opt
+}
+
+/// The file format options shared by all commands: '-n NUM_POWERS',
+/// '--curve CURVE' and '--legacy'.
+pub struct FormatOptions {
+    /// The number of powers given on the command line, if any.
+    pub num_powers: Option<usize>,
+    /// The curve given on the command line, if any.
+    pub curve: Option<CurveId>,
+    /// Read and write files without a header.
+    pub legacy: bool,
+}
+
+impl FormatOptions {
+    pub fn add_options(opts: &mut getopts::Options)
+    {
+        opts.optopt("n", "", "number of tau powers (required with --legacy)", "NUM_POWERS");
+        opts.optopt("", "curve", "curve of the ceremony: bn256 (default) or bls12_381", "CURVE");
+        opts.optflag("", "legacy", "read and write files without a header");
+    }
+
+    pub fn from_matches(matches: &getopts::Matches) -> FormatOptions
+    {
+        let num_powers : Option<usize> = get_opt(matches, "n");
+        if let Some(num_powers) = num_powers {
+            Configuration::check_num_powers(num_powers).unwrap_or_else(|e| panic!("arg n: {}", e));
+        }
+
+        FormatOptions {
+            num_powers: num_powers,
+            curve: get_opt(matches, "curve"),
+            legacy: matches.opt_present("legacy"),
+        }
+    }
+
+    /// Size of the header of each file.
+    pub fn header_size(&self) -> usize
+    {
+        if self.legacy { 0 } else { HEADER_SIZE }
+    }
+
+    /// The configuration to use when no input file describes one.
+    pub fn config(&self) -> Configuration
+    {
+        let num_powers = self.num_powers.unwrap_or(DEFAULT_NUM_POWERS);
+        Configuration::try_for_curve(self.curve.unwrap_or(CurveId::Bn256), num_powers)
+            .unwrap_or_else(|e| panic!("arg n: {}", e))
+    }
+
+    /// Read the header of the input file `path` and check its sections use
+    /// `compression`, returning the configuration it describes. In legacy
+    /// mode, nothing is read and the configuration comes from the command
+    /// line.
+    pub fn read_header<R: Read>(
+        &self,
+        reader: &mut R,
+        compression: UseCompression,
+        path: &Path
+    ) -> Configuration
+    {
+        if self.legacy {
+            return self.config();
+        }
+
+        let header = FileHeader::deserialize(reader)
+            .unwrap_or_else(|e| panic!("unable to read header of `{}`: {}", path.display(), e));
+        header.check(compression, self.num_powers, self.curve)
+            .unwrap_or_else(|e| panic!("unexpected header in `{}`: {}", path.display(), e));
+
+        header.config()
+    }
+
+    /// Write the header of an output file, unless in legacy mode.
+    pub fn write_header<W: Write>(
+        &self,
+        writer: &mut W,
+        config: &Configuration,
+        compression: UseCompression,
+        path: &Path
+    )
+    {
+        if !self.legacy {
+            FileHeader::new(config, compression).serialize(writer)
+                .unwrap_or_else(|e| panic!("unable to write header to `{}`: {}", path.display(), e));
+        }
+    }
+}
+
+/// A file read or written by a command, whose path can be given with
+/// '--OPTION PATH'.
+#[derive(Clone, Copy)]
+pub struct FileArg {
+    pub option: &'static str,
+    /// File name used when the option is not given.
+    pub default: &'static str,
+}
+
+pub const CHALLENGE: FileArg = FileArg { option: "challenge", default: "challenge" };
+pub const RESPONSE: FileArg = FileArg { option: "response", default: "response" };
+pub const NEW_CHALLENGE: FileArg = FileArg { option: "new-challenge", default: "new_challenge" };
+pub const TRANSCRIPT: FileArg = FileArg { option: "transcript", default: "transcript" };
+pub const PTAU: FileArg = FileArg { option: "ptau", default: "powersoftau.ptau" };
+pub const GNARK_SRS: FileArg = FileArg { option: "gnark-srs", default: "kzg_bn254.gnark" };
+pub const ARKWORKS_PARAMS: FileArg = FileArg { option: "arkworks-params", default: "kzg_bn254.arkworks" };
+pub const BELLMAN: FileArg = FileArg { option: "bellman", default: "challenge.bellman" };
+pub const LIBFF: FileArg = FileArg { option: "libff", default: "powersoftau.libff" };
+pub const BEACON_DESCRIPTOR: FileArg = FileArg { option: "beacon-descriptor", default: "response.beacon" };
+
+/// The paths of the files read and written by a command. Inputs default to
+/// the current directory and outputs to '--out-dir'. Existing outputs are
+/// only overwritten with '--force'.
+pub struct FileOptions {
+    inputs: Vec<FileArg>,
+    outputs: Vec<FileArg>,
+    out_dir: PathBuf,
+    force: bool,
+    paths: Vec<(&'static str, PathBuf)>,
+}
+
+impl FileOptions {
+    pub fn new(inputs: &[FileArg], outputs: &[FileArg]) -> FileOptions
+    {
+        FileOptions {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+            out_dir: PathBuf::from("."),
+            force: false,
+            paths: vec![],
+        }
+    }
+
+    pub fn add_options(&self, opts: &mut getopts::Options)
+    {
+        for f in &self.inputs {
+            opts.optopt("", f.option, &format!("file to read (default: ./{})", f.default), "PATH");
+        }
+        for f in &self.outputs {
+            opts.optopt("", f.option, &format!("file to write (default: OUT_DIR/{})", f.default), "PATH");
+        }
+        opts.optopt("", "out-dir", "directory to write output files to (default: .)", "OUT_DIR");
+        opts.optflag("", "force", "overwrite existing output files");
+    }
+
+    pub fn parse(&mut self, matches: &getopts::Matches)
+    {
+        if let Some(out_dir) = get_opt::<String>(matches, "out-dir") {
+            self.out_dir = PathBuf::from(out_dir);
+        }
+        self.force = matches.opt_present("force");
+
+        for f in &self.inputs {
+            let path = get_opt::<String>(matches, f.option)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| Path::new(".").join(f.default));
+            self.paths.push((f.option, path));
+        }
+        for f in &self.outputs {
+            let path = get_opt::<String>(matches, f.option)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| self.out_dir.join(f.default));
+            self.paths.push((f.option, path));
+        }
+    }
+
+    pub fn out_dir(&self) -> &Path
+    {
+        &self.out_dir
+    }
+
+    pub fn path(&self, file: &FileArg) -> &Path
+    {
+        self.paths.iter()
+            .find(|p| p.0 == file.option)
+            .map(|p| p.1.as_path())
+            .unwrap_or_else(|| panic!("no path for `{}`", file.option))
+    }
+
+    /// Open one of the input files.
+    pub fn open(&self, file: &FileArg) -> File
+    {
+        let path = self.path(file);
+        OpenOptions::new()
+            .read(true)
+            .open(path)
+            .unwrap_or_else(|e| panic!("unable to open `{}`: {}", path.display(), e))
+    }
+
+    /// Create the file at `path` (and its directory). Fails if the file
+    /// exists, unless '--force' was given.
+    pub fn create_path(&self, path: &Path) -> File
+    {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .unwrap_or_else(|e| panic!("unable to create directory `{}`: {}", dir.display(), e));
+        }
+
+        OpenOptions::new()
+            .read(false)
+            .write(true)
+            .create_new(!self.force)
+            .create(self.force)
+            .truncate(self.force)
+            .open(path)
+            .unwrap_or_else(|e| panic!("unable to create `{}`: {}", path.display(), e))
+    }
+
+    /// Create one of the output files.
+    pub fn create(&self, file: &FileArg) -> File
+    {
+        self.create_path(self.path(file))
+    }
+
+    /// Remove one of the output files, after a failure.
+    pub fn remove(&self, file: &FileArg)
+    {
+        let path = self.path(file);
+        fs::remove_file(path)
+            .unwrap_or_else(|e| panic!("unable to remove `{}`: {}", path.display(), e));
+    }
+}
+
+/// Handle the common case of commands that only take the format and file
+/// options.
+pub fn parse_simple_options(inputs: &[FileArg], outputs: &[FileArg]) -> (FormatOptions, FileOptions)
+{
+    let mut files = FileOptions::new(inputs, outputs);
     let mut opts = getopts::Options::new();
     opts.optflag("h", "help", "print this help");
-    opts.optopt("n", "", "number of tau powers", "NUM_POWERS");
+    FormatOptions::add_options(&mut opts);
+    files.add_options(&mut opts);
     let matches = match_or_fail(&opts);
-    return Configuration::new(get_opt_default(&matches, "n", DEFAULT_NUM_POWERS));
+    files.parse(&matches);
+    (FormatOptions::from_matches(&matches), files)
 }
 
 #[test]
@@ -115,3 +355,44 @@ fn test_digest_strings()
     assert_eq!(0x3bu8, digest[63]);
     assert_eq!(s, digest_string);
 }
+
+/// Read the BN256 accumulator from the challenge file for an export to
+/// `target`. The challenge should be the new challenge written after
+/// verifying the last contribution, as the accumulator is not checked here.
+pub fn read_bn256_challenge(format: &FormatOptions, files: &FileOptions, target: &str) -> Accumulator<Bn256>
+{
+    let challenge_path = files.path(&CHALLENGE);
+    let reader = files.open(&CHALLENGE);
+
+    let metadata = reader.metadata().expect("unable to get filesystem metadata for the challenge file");
+
+    let mut reader = BufReader::with_capacity(1024 * 1024, reader);
+    let config = format.read_header(&mut reader, UseCompression::No, challenge_path);
+
+    if config.curve != CurveId::Bn256 {
+        panic!("{} can only be exported for bn256, not {}", target, config.curve);
+    }
+
+    if metadata.len() != ((format.header_size() + config.accumulator_size_bytes) as u64) {
+        panic!(
+            "The size of `{}` should be {}, but it's {}, so something isn't right.",
+            challenge_path.display(),
+            format.header_size() + config.accumulator_size_bytes,
+            metadata.len());
+    }
+
+    println!("Reading `{}` into memory...", challenge_path.display());
+
+    // Skip the BLAKE2b hash of the previous contribution
+    {
+        let mut tmp = [0; 64];
+        reader.read_exact(&mut tmp).expect("unable to read BLAKE2b hash of previous contribution");
+    }
+
+    Accumulator::<Bn256>::deserialize(
+        config,
+        &mut reader,
+        UseCompression::No,
+        CheckForCorrectness::No
+    ).expect("unable to read uncompressed accumulator")
+}
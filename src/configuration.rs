@@ -1,6 +1,11 @@
 extern crate getopts;
 
-// This ceremony is based on the BN256 elliptic curve construction.
+use bls12::Bls12;
+use bn256::Bn256;
+use std::fmt;
+use engine::{CurveId, CurvePoint, Engine};
+
+// The default curve for this ceremony is BN256.
 pub const G1_UNCOMPRESSED_BYTE_SIZE: usize = 1 + 32 + 32;
 pub const G2_UNCOMPRESSED_BYTE_SIZE: usize = 1 + 64 + 64;
 pub const G1_COMPRESSED_BYTE_SIZE: usize = 1 + 32;
@@ -8,22 +13,50 @@ pub const G2_COMPRESSED_BYTE_SIZE: usize = 1 + 64;
 
 pub const DEFAULT_NUM_POWERS: usize = (1 << 21);
 
+/// The largest number of powers supported on any curve: the largest domain
+/// of the Lagrange coefficients on every supported curve, which is the
+/// minimum of the 2-adicities of their scalar fields (28 for BN256, 32 for
+/// BLS12-381).
+pub const MAX_NUM_POWERS: usize = (1 << 28);
+
 /// The "public key" is used to verify a contribution was correctly
 /// computed.
 pub const PUBLIC_KEY_SIZE: usize =
     3 * G2_UNCOMPRESSED_BYTE_SIZE + // tau, alpha, and beta in g2
     6 * G1_UNCOMPRESSED_BYTE_SIZE; // (s1, s1*tau), (s2, s2*alpha), (s3, s3*beta) in g1
 
-fn is_pow2(v: usize) -> bool {
-    0 == (v & (v-1))
+/// The size of the public key for the curve of `E`.
+pub fn public_key_size<E: Engine>() -> usize {
+    3 * E::G2::UNCOMPRESSED_SIZE + // tau, alpha, and beta in g2
+    6 * E::G1::UNCOMPRESSED_SIZE // (s1, s1*tau), (s2, s2*alpha), (s3, s3*beta) in g1
 }
 
 fn log2(v: usize) -> usize {
     (v as f64).log2() as usize
 }
 
+/// Errors that might occur when choosing the number of powers.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ConfigurationError {
+    /// The number of powers is not a power of two, or is more than
+    /// `MAX_NUM_POWERS`.
+    InvalidNumPowers(usize),
+}
+
+impl fmt::Display for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigurationError::InvalidNumPowers(n) =>
+                write!(f, "Invalid number of powers {} (a power of two, at most {})", n, MAX_NUM_POWERS),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub struct Configuration {
+    /// The curve of the ceremony
+    pub curve: CurveId,
+
     /// The maximum number of gates to be supported by circuits
     pub num_powers: usize,
 
@@ -44,34 +77,74 @@ pub struct Configuration {
 }
 
 impl Configuration {
+    /// A configuration for a ceremony on the default curve, BN256.
     pub fn new(num_powers: usize) -> Self
     {
-        assert!(is_pow2(num_powers));
+        Self::for_engine::<Bn256>(num_powers)
+    }
+
+    /// Checks that `num_powers` is a power of two that every curve supports.
+    pub fn check_num_powers(num_powers: usize) -> Result<(), ConfigurationError>
+    {
+        if !num_powers.is_power_of_two() || num_powers > MAX_NUM_POWERS {
+            return Err(ConfigurationError::InvalidNumPowers(num_powers));
+        }
+        Ok(())
+    }
+
+    /// As `for_curve`, for a number of powers that may be invalid, such as
+    /// one given on the command line.
+    pub fn try_for_curve(curve: CurveId, num_powers: usize) -> Result<Self, ConfigurationError>
+    {
+        Self::check_num_powers(num_powers)?;
+        Ok(Self::for_curve(curve, num_powers))
+    }
+
+    pub fn for_curve(curve: CurveId, num_powers: usize) -> Self
+    {
+        match curve {
+            CurveId::Bn256 => Self::for_engine::<Bn256>(num_powers),
+            CurveId::Bls12_381 => Self::for_engine::<Bls12>(num_powers),
+        }
+    }
+
+    /// As `try_for_engine`, panicking if `num_powers` is invalid.
+    pub fn for_engine<E: Engine>(num_powers: usize) -> Self
+    {
+        Self::try_for_engine::<E>(num_powers).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// A configuration for `num_powers` powers on the curve of `E`, which
+    /// must be a power of two, at most `MAX_NUM_POWERS`.
+    pub fn try_for_engine<E: Engine>(num_powers: usize) -> Result<Self, ConfigurationError>
+    {
+        Self::check_num_powers(num_powers)?;
         let num_powers_log2 = log2(num_powers);
         assert!(num_powers == 1 << num_powers_log2);
         let num_powers_g1 = (num_powers << 1) - 1;
         let accumulator_size =
-            (num_powers_g1 * G1_UNCOMPRESSED_BYTE_SIZE) + // g1 tau powers
-            (num_powers * G2_UNCOMPRESSED_BYTE_SIZE) + // g2 tau powers
-            (num_powers * G1_UNCOMPRESSED_BYTE_SIZE) + // alpha tau powers
-            (num_powers * G1_UNCOMPRESSED_BYTE_SIZE) // beta tau powers
-            + G2_UNCOMPRESSED_BYTE_SIZE // beta in g2
+            (num_powers_g1 * E::G1::UNCOMPRESSED_SIZE) + // g1 tau powers
+            (num_powers * E::G2::UNCOMPRESSED_SIZE) + // g2 tau powers
+            (num_powers * E::G1::UNCOMPRESSED_SIZE) + // alpha tau powers
+            (num_powers * E::G1::UNCOMPRESSED_SIZE) // beta tau powers
+            + E::G2::UNCOMPRESSED_SIZE // beta in g2
             + 64; // blake2b hash of previous contribution
         let contribution_size =
-            (num_powers_g1 * G1_COMPRESSED_BYTE_SIZE) + // g1 tau powers
-            (num_powers * G2_COMPRESSED_BYTE_SIZE) + // g2 tau powers
-            (num_powers * G1_COMPRESSED_BYTE_SIZE) + // alpha tau powers
-            (num_powers * G1_COMPRESSED_BYTE_SIZE) // beta tau powers
-            + G2_COMPRESSED_BYTE_SIZE // beta in g2
+            (num_powers_g1 * E::G1::COMPRESSED_SIZE) + // g1 tau powers
+            (num_powers * E::G2::COMPRESSED_SIZE) + // g2 tau powers
+            (num_powers * E::G1::COMPRESSED_SIZE) + // alpha tau powers
+            (num_powers * E::G1::COMPRESSED_SIZE) // beta tau powers
+            + E::G2::COMPRESSED_SIZE // beta in g2
             + 64 // blake2b hash of input accumulator
-            + PUBLIC_KEY_SIZE; // public key
-        Configuration {
+            + public_key_size::<E>(); // public key
+        Ok(Configuration {
+            curve: E::CURVE,
             num_powers: num_powers,
             num_powers_log2: num_powers_log2,
             num_powers_g1: num_powers_g1,
             accumulator_size_bytes: accumulator_size,
             contribution_size_bytes: contribution_size
-        }
+        })
     }
 
     pub fn default() -> Self
@@ -79,3 +152,15 @@ impl Configuration {
         Self::new(DEFAULT_NUM_POWERS)
     }
 }
+
+#[test]
+fn test_num_powers() {
+    assert!(Configuration::try_for_curve(CurveId::Bn256, 16).unwrap() == Configuration::new(16));
+    assert!(Configuration::try_for_curve(CurveId::Bls12_381, MAX_NUM_POWERS).is_ok());
+    for &n in &[0, 3, 17, MAX_NUM_POWERS << 1] {
+        match Configuration::try_for_curve(CurveId::Bn256, n) {
+            Err(ConfigurationError::InvalidNumPowers(m)) => assert_eq!(m, n),
+            Ok(_) => panic!("accepted {} powers", n)
+        }
+    }
+}
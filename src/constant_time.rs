@@ -0,0 +1,260 @@
+//! Multiplication of group elements by secret scalars in constant time.
+//!
+//! `CurvePoint::mul` and `WnafGroup::wnaf_mul` skip zero digits and index
+//! their tables by the digits of the scalar, so the time they take and the
+//! memory they touch depend on it. With the `constant-time` feature, every
+//! multiplication by τ, α or β, or by a power of τ, goes through `mul`
+//! instead.
+//!
+//! `mul` uses a fixed window of `WINDOW_SIZE` bits with the regular signed
+//! recoding, in which every digit is odd and so non-zero: the same sequence
+//! of doublings and additions is run for every scalar. Each digit selects its
+//! multiple by reading the whole table and masking, and is negated by
+//! masking, so no branch or memory address depends on the scalar.
+//!
+//! This covers the scalar multiplication itself, not the field arithmetic of
+//! the curve libraries, nor the exceptional cases of their addition formulas
+//! (adding a point to itself, its negation, or zero), which only arise for a
+//! negligible fraction of scalars.
+
+use std::mem;
+use std::ptr;
+use std::slice;
+
+use engine::{CurvePoint, ScalarField};
+
+/// Width of the window. The table holds the 2<sup>w-1</sup> odd multiples
+/// up to (2<sup>w</sup> - 1)P.
+const WINDOW_SIZE: usize = 4;
+
+/// Digits of the recoding of a 256-bit scalar, the last of which is 1.
+const NUM_DIGITS: usize = 256 / WINDOW_SIZE + 1;
+
+/// Returns 1 if `x` is zero, and 0 otherwise.
+fn ct_is_zero(x: u64) -> u64
+{
+    ((x | x.wrapping_neg()) >> 63) ^ 1
+}
+
+/// Returns `b` if `choice` is 1, and `a` if it is 0. The points are selected
+/// byte by byte, which relies on the point types being plain data without
+/// padding, as those of both curves are.
+fn ct_select<G: Copy>(a: &G, b: &G, choice: u64) -> G
+{
+    // Read the mask through a volatile load so that the compiler cannot
+    // turn the selection back into a branch.
+    let mask = 0u8.wrapping_sub(choice as u8);
+    let mask = unsafe { ptr::read_volatile(&mask) };
+
+    let mut out = *a;
+    {
+        let size = mem::size_of::<G>();
+        let out = unsafe { slice::from_raw_parts_mut(&mut out as *mut G as *mut u8, size) };
+        let b = unsafe { slice::from_raw_parts(b as *const G as *const u8, size) };
+        for (o, b) in out.iter_mut().zip(b.iter()) {
+            *o ^= mask & (*o ^ *b);
+        }
+    }
+    out
+}
+
+/// Returns the regular signed recoding of the odd little-endian integer
+/// `limbs`, least significant digit first: each digit is odd and less than
+/// 2<sup>w</sup> in magnitude, and the last is positive.
+fn recode(mut limbs: [u64; 5]) -> [i64; NUM_DIGITS]
+{
+    let mut digits = [0i64; NUM_DIGITS];
+
+    for digit in digits[..(NUM_DIGITS - 1)].iter_mut() {
+        // The odd digit u with limbs = u + 2^w mod 2^(w + 1), after which
+        // (limbs - u) / 2^w is odd again.
+        let u = (limbs[0] & ((1 << (WINDOW_SIZE + 1)) - 1)) as i64 - (1 << WINDOW_SIZE);
+        *digit = u;
+
+        // limbs += -u, sign-extended
+        let v = -u;
+        let extension = (v >> 63) as u64;
+        let mut carry = 0;
+        for (i, l) in limbs.iter_mut().enumerate() {
+            let (s, c1) = l.overflowing_add(if i == 0 { v as u64 } else { extension });
+            let (s, c2) = s.overflowing_add(carry);
+            *l = s;
+            carry = (c1 | c2) as u64;
+        }
+
+        // limbs >>= w
+        for i in 0..4 {
+            limbs[i] = (limbs[i] >> WINDOW_SIZE) | (limbs[i + 1] << (64 - WINDOW_SIZE));
+        }
+        limbs[4] >>= WINDOW_SIZE;
+    }
+    digits[NUM_DIGITS - 1] = limbs[0] as i64;
+
+    digits
+}
+
+/// Returns `digit * P` from the odd multiples of P in `table`, reading every
+/// entry.
+fn lookup<G: CurvePoint>(table: &[G], digit: i64) -> G
+{
+    let sign = (digit >> 63) as u64;
+    let magnitude = ((digit as u64) ^ sign).wrapping_sub(sign);
+    let index = magnitude >> 1;
+
+    let mut p = table[0];
+    for (i, entry) in table.iter().enumerate().skip(1) {
+        p = ct_select(&p, entry, ct_is_zero(index ^ (i as u64)));
+    }
+    ct_select(&p, &-p, sign & 1)
+}
+
+/// Computes `base * scalar` in constant time with respect to `scalar`.
+pub fn mul<G: CurvePoint>(base: &G, scalar: &G::Scalar) -> G
+{
+    // The recoding needs an odd integer. For an even scalar k, r - k is odd,
+    // since the order r is, and kP = -(r - k)P. Zero is recoded as 1, and
+    // the result replaced by zero at the end.
+    let limbs = scalar.to_limbs();
+    let neg_limbs = (-*scalar).to_limbs();
+    let is_even = (limbs[0] & 1) ^ 1;
+    let is_zero = ct_is_zero(limbs[0] | limbs[1] | limbs[2] | limbs[3]);
+
+    let mut n = [0u64; 5];
+    for (n, (l, neg_l)) in n.iter_mut().zip(limbs.iter().zip(neg_limbs.iter())) {
+        *n = ct_select(l, neg_l, is_even);
+    }
+    n[0] |= 1;
+    let digits = recode(n);
+
+    // P, 3P, 5P, ...
+    let double = *base + *base;
+    let mut table = [*base; 1 << (WINDOW_SIZE - 1)];
+    let mut multiple = *base;
+    for entry in table.iter_mut().skip(1) {
+        multiple = multiple + double;
+        *entry = multiple;
+    }
+
+    let mut acc = lookup(&table, digits[NUM_DIGITS - 1]);
+    for digit in digits[..(NUM_DIGITS - 1)].iter().rev() {
+        for _ in 0..WINDOW_SIZE {
+            acc = acc + acc;
+        }
+        acc = acc + lookup(&table, *digit);
+    }
+
+    let acc = ct_select(&acc, &-acc, is_even);
+    ct_select(&acc, &G::zero(), is_zero)
+}
+
+#[test]
+fn test_recode() {
+    for &n in &[1u64, 3, 15, 17, 31, 33, 0x1234_5678_9abc_def1, u64::max_value()] {
+        let digits = recode([n, 0, 0, 0, 0]);
+        assert!(digits.iter().all(|d| d % 2 != 0 && d.abs() < (1 << WINDOW_SIZE)));
+        assert!(digits[NUM_DIGITS - 1] > 0);
+
+        // The digits sum to n, so their sum modulo 2^128 is n.
+        let value = digits.iter().rev()
+            .fold(0u128, |acc, d| (acc << WINDOW_SIZE).wrapping_add(*d as i128 as u128));
+        assert_eq!(value, n as u128);
+    }
+}
+
+#[test]
+fn test_mul() {
+    use rand::thread_rng;
+    use bls12::Bls12;
+    use bn256::Bn256;
+    use engine::Engine;
+
+    fn test_engine<E: Engine>() {
+        let rng = &mut thread_rng();
+
+        let mut scalars = (0..50).map(|_| E::Fr::random(rng)).collect::<Vec<_>>();
+        scalars.push(E::Fr::zero());
+        scalars.push(E::Fr::one());
+        scalars.push(E::Fr::from_u64(2));
+        scalars.push(E::Fr::from_u64(16));
+        scalars.push(-E::Fr::one());
+        scalars.push(-E::Fr::from_u64(2));
+
+        for k in scalars {
+            let g1 = E::G1::random(rng);
+            let g2 = E::G2::random(rng);
+            assert!(mul(&g1, &k) == g1.mul(&k));
+            assert!(mul(&g2, &k) == g2.mul(&k));
+            assert!(mul(&E::G1::zero(), &k) == E::G1::zero());
+        }
+    }
+
+    test_engine::<Bn256>();
+    test_engine::<Bls12>();
+}
+
+/// Welch's t-statistic between the times taken by `f` for a fixed scalar and
+/// for random ones, run in random order. Following dudect, the slowest tenth
+/// of each class is discarded as noise from the rest of the system.
+#[cfg(test)]
+fn timing_t_statistic<G, F>(f: F, fixed: G::Scalar, samples: usize) -> f64
+    where G: CurvePoint, F: Fn(&G, &G::Scalar) -> G
+{
+    use rand::{thread_rng, Rng};
+    use std::time::Instant;
+
+    let rng = &mut thread_rng();
+    let base = G::one();
+
+    let mut times = [Vec::with_capacity(samples), Vec::with_capacity(samples)];
+    while times[0].len() < samples || times[1].len() < samples {
+        let class = rng.gen::<bool>() as usize;
+        let scalar = if class == 0 { fixed } else { G::Scalar::random(rng) };
+
+        let start = Instant::now();
+        let p = f(&base, &scalar);
+        let elapsed = start.elapsed();
+        assert!(!p.is_zero());
+
+        times[class].push(elapsed.as_secs() as f64 * 1e9 + elapsed.subsec_nanos() as f64);
+    }
+
+    let stats = |t: &mut Vec<f64>| {
+        t.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let len = t.len() * 9 / 10;
+        let t = &t[..len];
+        let mean = t.iter().sum::<f64>() / len as f64;
+        let var = t.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (len - 1) as f64;
+        (mean, var, len as f64)
+    };
+    let (mean0, var0, n0) = stats(&mut times[0]);
+    let (mean1, var1, n1) = stats(&mut times[1]);
+
+    (mean0 - mean1) / (var0 / n0 + var1 / n1).sqrt()
+}
+
+/// Checks that the time `mul` takes does not depend on the scalar, and that
+/// the check detects the variation of `wnaf_mul`. Timing depends on the
+/// machine and its load, so this is not run by default:
+///
+/// cargo test --release -- --ignored test_timing_variance
+#[test]
+#[ignore]
+fn test_timing_variance() {
+    use bn::{Fr, G1};
+    use wnaf::WnafGroup;
+
+    // The threshold used by dudect for a definite leak.
+    const THRESHOLD: f64 = 10.0;
+
+    // The scalar with the fewest non-zero digits.
+    let fixed = Fr::one();
+
+    let t = timing_t_statistic::<G1, _>(|p, k| mul(p, k), fixed, 2000);
+    println!("constant-time mul: t = {:.2}", t);
+    assert!(t.abs() < THRESHOLD, "the time taken by mul depends on the scalar (t = {:.2})", t);
+
+    let params = G1::wnaf_params();
+    let t = timing_t_statistic::<G1, _>(|p, k| p.wnaf_mul(k, &params), fixed, 2000);
+    println!("wnaf_mul: t = {:.2}", t);
+    assert!(t.abs() > THRESHOLD, "the timing check did not detect wnaf_mul (t = {:.2})", t);
+}
@@ -0,0 +1,268 @@
+//! Radix-2 evaluation domains over the scalar field of an engine, used to
+//! perform FFTs "in the exponent" over vectors of group elements.
+
+use std::ops::*;
+use crossbeam;
+use num_cpus;
+
+use engine::{CurvePoint, ScalarField};
+
+/// A vector of group elements indexed by a multiplicative subgroup of the
+/// scalar field of size m = 2^exp.
+pub struct EvaluationDomain<G: CurvePoint> {
+    coeffs: Vec<G>,
+    exp: u32,
+    omega: G::Scalar,
+    omegainv: G::Scalar,
+    minv: G::Scalar,
+}
+
+impl<G: CurvePoint> EvaluationDomain<G> {
+    /// Constructs a domain from `coeffs`, padding with zeros up to the next
+    /// power of two. Returns `None` if the domain would be too large for the
+    /// scalar field.
+    pub fn from_coeffs(coeffs: Vec<G>) -> Option<Self>
+    {
+        Self::from_coeffs_with_root(coeffs, G::Scalar::root_of_unity())
+    }
+
+    /// As `from_coeffs`, with the subgroup generated by powers of `root`, a
+    /// primitive 2<sup>`TWO_ADICITY`</sup>-th root of unity, in place of
+    /// `ScalarField::root_of_unity`. The Lagrange coefficients of other
+    /// implementations are only interchangeable with ours if they use the
+    /// same root.
+    pub fn from_coeffs_with_root(mut coeffs: Vec<G>, root: G::Scalar) -> Option<Self>
+    {
+        // Compute the size of our evaluation domain
+        let mut m = 1;
+        let mut exp = 0;
+        while m < coeffs.len() {
+            m *= 2;
+            exp += 1;
+
+            if exp > G::Scalar::TWO_ADICITY {
+                return None;
+            }
+        }
+
+        // Compute omega, the 2^exp primitive root of unity
+        let mut omega = root;
+        for _ in exp..G::Scalar::TWO_ADICITY {
+            omega = omega * omega;
+        }
+
+        // Extend the coeffs vector with zeroes if necessary
+        coeffs.resize(m, G::zero());
+
+        Some(EvaluationDomain {
+            coeffs: coeffs,
+            exp: exp,
+            omega: omega,
+            omegainv: omega.inverse().unwrap(),
+            minv: G::Scalar::from_u64(m as u64).inverse().unwrap(),
+        })
+    }
+
+    pub fn as_ref(&self) -> &[G] {
+        &self.coeffs
+    }
+
+    pub fn into_coeffs(self) -> Vec<G> {
+        self.coeffs
+    }
+
+    /// Evaluates the domain at the m-th roots of unity.
+    pub fn fft(&mut self)
+    {
+        best_fft(&mut self.coeffs, &self.omega, self.exp);
+    }
+
+    /// Inverse of `fft`. Applied to (g, g^τ, ..., g^{τ^{m-1}}) this yields the
+    /// Lagrange coefficients (g^{L_0(τ)}, ..., g^{L_{m-1}(τ)}).
+    pub fn ifft(&mut self)
+    {
+        best_fft(&mut self.coeffs, &self.omegainv, self.exp);
+
+        let minv = self.minv;
+        let chunk_size = (self.coeffs.len() / num_cpus::get()) + 1;
+
+        crossbeam::scope(|scope| {
+            for v in self.coeffs.chunks_mut(chunk_size) {
+                scope.spawn(move || {
+                    for v in v {
+                        *v = v.mul(&minv);
+                    }
+                });
+            }
+        });
+    }
+}
+
+fn log2_floor(num: usize) -> u32
+{
+    assert!(num > 0);
+
+    let mut pow = 0;
+    while (1 << (pow + 1)) <= num {
+        pow += 1;
+    }
+
+    pow
+}
+
+fn best_fft<G: CurvePoint>(a: &mut [G], omega: &G::Scalar, log_n: u32)
+{
+    let log_cpus = log2_floor(num_cpus::get());
+
+    if log_n <= log_cpus {
+        serial_fft(a, omega, log_n);
+    } else {
+        parallel_fft(a, omega, log_n, log_cpus);
+    }
+}
+
+fn serial_fft<G: CurvePoint>(a: &mut [G], omega: &G::Scalar, log_n: u32)
+{
+    fn bitreverse(mut n: u32, l: u32) -> u32 {
+        let mut r = 0;
+        for _ in 0..l {
+            r = (r << 1) | (n & 1);
+            n >>= 1;
+        }
+        r
+    }
+
+    let n = a.len() as u32;
+    assert_eq!(n, 1 << log_n);
+
+    for k in 0..n {
+        let rk = bitreverse(k, log_n);
+        if k < rk {
+            a.swap(rk as usize, k as usize);
+        }
+    }
+
+    let mut m = 1;
+    for _ in 0..log_n {
+        let w_m = omega.pow((n / (2 * m)) as u64);
+
+        let mut k = 0;
+        while k < n {
+            let mut w = G::Scalar::one();
+            for j in 0..m {
+                let t = a[(k + j + m) as usize].mul(&w);
+                a[(k + j + m) as usize] = a[(k + j) as usize].sub(t);
+                a[(k + j) as usize] = a[(k + j) as usize].add(t);
+                w = w * w_m;
+            }
+
+            k += 2 * m;
+        }
+
+        m *= 2;
+    }
+}
+
+fn parallel_fft<G: CurvePoint>(a: &mut [G], omega: &G::Scalar, log_n: u32, log_cpus: u32)
+{
+    assert!(log_n >= log_cpus);
+
+    let num_cpus = 1 << log_cpus;
+    let log_new_n = log_n - log_cpus;
+    let mut tmp = vec![vec![G::zero(); 1 << log_new_n]; num_cpus];
+    let new_omega = omega.pow(num_cpus as u64);
+
+    crossbeam::scope(|scope| {
+        let a = &*a;
+
+        for (j, tmp) in tmp.iter_mut().enumerate() {
+            scope.spawn(move || {
+                // Shuffle into a sub-FFT
+                let omega_j = omega.pow(j as u64);
+                let omega_step = omega.pow((j as u64) << log_new_n);
+
+                let mut elt = G::Scalar::one();
+                for i in 0..(1 << log_new_n) {
+                    for s in 0..num_cpus {
+                        let idx = (i + (s << log_new_n)) % (1 << log_n);
+                        let t = a[idx].mul(&elt);
+                        tmp[i] = tmp[i].add(t);
+                        elt = elt * omega_step;
+                    }
+                    elt = elt * omega_j;
+                }
+
+                // Perform sub-FFT
+                serial_fft(tmp, &new_omega, log_new_n);
+            });
+        }
+    });
+
+    crossbeam::scope(|scope| {
+        let tmp = &tmp;
+
+        for (idx, a) in a.chunks_mut(1 << log_new_n).enumerate() {
+            scope.spawn(move || {
+                let mut idx = idx * (1 << log_new_n);
+                let mask = (1 << log_cpus) - 1;
+                for a in a {
+                    *a = tmp[idx & mask][idx >> log_cpus];
+                    idx += 1;
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn test_root_of_unity() {
+    use bls12_381::Scalar;
+    use bn::Fr;
+
+    fn test_field<F: ScalarField>() {
+        let mut omega = F::root_of_unity();
+        for _ in 0..(F::TWO_ADICITY - 1) {
+            omega = omega * omega;
+        }
+
+        // omega^(2^(s-1)) = -1, so it squares to one.
+        assert!(omega != F::one());
+        assert!(omega * omega == F::one());
+    }
+
+    test_field::<Fr>();
+    test_field::<Scalar>();
+}
+
+#[test]
+fn test_fft_ifft_roundtrip() {
+    use rand::thread_rng;
+    use bls12::Bls12;
+    use bn256::Bn256;
+    use engine::Engine;
+
+    fn test_engine<E: Engine>() {
+        let rng = &mut thread_rng();
+
+        for log_m in 0..7 {
+            let v = (0..(1 << log_m)).map(|_| E::G1::random(rng)).collect::<Vec<_>>();
+
+            let mut domain = EvaluationDomain::from_coeffs(v.clone()).unwrap();
+            domain.fft();
+            domain.ifft();
+            assert!(domain.into_coeffs() == v);
+
+            let mut v_serial = v.clone();
+            let mut v_parallel = v.clone();
+            let omega = EvaluationDomain::from_coeffs(v.clone()).unwrap().omega;
+            serial_fft(&mut v_serial, &omega, log_m);
+            if log_m >= 1 {
+                parallel_fft(&mut v_parallel, &omega, log_m, 1);
+                assert!(v_serial == v_parallel);
+            }
+        }
+    }
+
+    test_engine::<Bn256>();
+    test_engine::<Bls12>();
+}
@@ -0,0 +1,146 @@
+//! The pairing-friendly curves a ceremony can be run over.
+//!
+//! `Accumulator`, `PublicKey` and the tools built on them are generic over an
+//! `Engine`, which provides the scalar field, the groups G1 and G2 with their
+//! encodings, and the pairing. BN256 (`bn256::Bn256`) is the default, and
+//! BLS12-381 (`bls12::Bls12`) is also supported.
+
+use rand::Rng;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::ops::{Add, Sub, Mul, Neg};
+use std::str::FromStr;
+
+use wnaf::WnafGroup;
+use super::{DeserializationError, PointError};
+
+/// The pairing-friendly curve the points in a file belong to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CurveId {
+    Bn256 = 1,
+    Bls12_381 = 2,
+}
+
+impl CurveId {
+    pub fn from_u8(v: u8) -> Option<CurveId> {
+        match v {
+            1 => Some(CurveId::Bn256),
+            2 => Some(CurveId::Bls12_381),
+            _ => None
+        }
+    }
+}
+
+impl FromStr for CurveId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CurveId, String> {
+        match s {
+            "bn256" => Ok(CurveId::Bn256),
+            "bls12_381" => Ok(CurveId::Bls12_381),
+            _ => Err(format!("unknown curve `{}` (expected bn256 or bls12_381)", s))
+        }
+    }
+}
+
+impl fmt::Display for CurveId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CurveId::Bn256 => write!(f, "bn256"),
+            CurveId::Bls12_381 => write!(f, "bls12_381"),
+        }
+    }
+}
+
+/// The scalar field of an engine.
+pub trait ScalarField:
+    Sized + Copy + Send + Sync + PartialEq + 'static +
+    Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self>
+{
+    /// The largest power of two dividing r - 1.
+    const TWO_ADICITY: u32;
+
+    fn zero() -> Self;
+
+    fn one() -> Self;
+
+    fn random<R: Rng>(rng: &mut R) -> Self;
+
+    fn from_u64(v: u64) -> Self;
+
+    fn pow(&self, exp: u64) -> Self;
+
+    /// Returns `None` for zero.
+    fn inverse(&self) -> Option<Self>;
+
+    /// A primitive 2<sup>`TWO_ADICITY`</sup>-th root of unity.
+    fn root_of_unity() -> Self;
+
+    /// The canonical integer representation, as little-endian limbs.
+    fn to_limbs(&self) -> [u64; 4];
+}
+
+/// A group of points of an engine, G1 or G2, with the encodings used on disk.
+pub trait CurvePoint:
+    Sized + Copy + Send + Sync + PartialEq + Eq + 'static +
+    Add<Output = Self> + Sub<Output = Self> + Neg<Output = Self>
+{
+    type Scalar: ScalarField;
+
+    const UNCOMPRESSED_SIZE: usize;
+    const COMPRESSED_SIZE: usize;
+
+    fn zero() -> Self;
+
+    /// The fixed generator.
+    fn one() -> Self;
+
+    fn is_zero(&self) -> bool;
+
+    /// A point derived from `rng`. The same `rng` state always yields the same
+    /// point, which `hash_to_g2` relies on.
+    fn random<R: Rng>(rng: &mut R) -> Self;
+
+    fn mul(&self, scalar: &Self::Scalar) -> Self;
+
+    fn write_uncompressed<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    fn write_compressed<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    /// Decode an uncompressed point without checking it is on the curve.
+    fn read_uncompressed_unchecked<R: Read>(reader: &mut R) -> Result<Self, DeserializationError>;
+
+    /// Decode a compressed point. Decompression solves the curve equation, so
+    /// the result is always on the curve.
+    fn read_compressed<R: Read>(reader: &mut R) -> Result<Self, DeserializationError>;
+
+    fn is_on_curve(&self) -> bool;
+
+    fn is_in_subgroup(&self) -> bool;
+
+    /// Run every check, returning the first one to fail.
+    fn check(&self) -> Result<(), PointError> {
+        if self.is_zero() {
+            Err(PointError::PointAtInfinity)
+        } else if !self.is_on_curve() {
+            Err(PointError::NotOnCurve)
+        } else if !self.is_in_subgroup() {
+            Err(PointError::NotInSubgroup)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A pairing-friendly curve.
+pub trait Engine: Sized + Copy + PartialEq + Eq + fmt::Debug + Send + Sync + 'static {
+    type Fr: ScalarField;
+    type G1: WnafGroup<Scalar = Self::Fr>;
+    type G2: WnafGroup<Scalar = Self::Fr>;
+    type Gt: PartialEq;
+
+    /// The id written to file headers.
+    const CURVE: CurveId;
+
+    fn pairing(p: &Self::G1, q: &Self::G2) -> Self::Gt;
+}
@@ -0,0 +1,176 @@
+//! Export of the powers of tau as a KZG structured reference string for
+//! gnark, in the layout of `kzg.SRS` for BN254 in gnark-crypto v0.11, as
+//! written by `WriteTo` (compressed) or `WriteRawTo` (uncompressed):
+//!
+//! * the powers τ<sup>i</sup> in G1, after their number as a big-endian u32
+//! * the generator of G2 and τ in G2
+//! * the generator of G1
+//!
+//! Coordinates are big-endian integers, with the imaginary part first in G2.
+//! Compressed points hold x only. The top two bits of the first byte are
+//! 0b00 for an uncompressed point, 0b10 or 0b11 for a compressed point whose
+//! y is the smaller or larger of ±y (in G2, by the real part unless it is
+//! zero), and 0b01 for the compressed point at infinity. The uncompressed
+//! point at infinity is all zeros.
+
+use bn::{AffineG1, AffineG2, Fq, Fq2, G1, G2};
+use byteorder::{BigEndian, WriteBytesExt};
+use std::io::{self, Write};
+
+use bn256::Bn256;
+use super::{Accumulator, UseCompression};
+
+const FQ_SIZE: usize = 32;
+
+const UNCOMPRESSED: u8 = 0b00 << 6;
+const COMPRESSED_SMALLEST: u8 = 0b10 << 6;
+const COMPRESSED_LARGEST: u8 = 0b11 << 6;
+const INFINITY: u8 = 0b01 << 6;
+
+/// A gnark KZG structured reference string.
+#[derive(PartialEq, Eq, Clone)]
+pub struct GnarkSrs {
+    /// tau^i in G1
+    pub g1: Vec<G1>,
+    /// The generator of G2 and tau in G2
+    pub g2: [G2; 2],
+}
+
+impl GnarkSrs {
+    /// Takes every power of tau in G1 from `acc`.
+    pub fn new(acc: &Accumulator<Bn256>) -> Self
+    {
+        GnarkSrs {
+            g1: acc.tau_powers_g1.clone(),
+            g2: [acc.tau_powers_g2[0], acc.tau_powers_g2[1]],
+        }
+    }
+
+    pub fn serialize<W: Write>(&self, writer: &mut W, compression: UseCompression) -> io::Result<()>
+    {
+        writer.write_u32::<BigEndian>(self.g1.len() as u32)?;
+        for p in &self.g1 {
+            write_g1(writer, p, compression)?;
+        }
+        write_g2(writer, &self.g2[0], compression)?;
+        write_g2(writer, &self.g2[1], compression)?;
+        write_g1(writer, &self.g1[0], compression)?;
+
+        Ok(())
+    }
+}
+
+fn fq_to_bytes(x: Fq) -> [u8; FQ_SIZE]
+{
+    let mut bytes = [0u8; FQ_SIZE];
+    x.into_u256().to_big_endian(&mut bytes).expect("32 bytes is enough for Fq");
+    bytes
+}
+
+/// Whether y is the larger of ±y as integers.
+fn is_largest(y: Fq) -> bool
+{
+    fq_to_bytes(y) > fq_to_bytes(-y)
+}
+
+/// As `is_largest`, deciding by the real part unless it is zero, as
+/// `E2.LexicographicallyLargest` does.
+fn is_largest_fq2(y: Fq2) -> bool
+{
+    if y.real() == Fq::zero() {
+        is_largest(y.imaginary())
+    } else {
+        is_largest(y.real())
+    }
+}
+
+fn write_g1<W: Write>(writer: &mut W, p: &G1, compression: UseCompression) -> io::Result<()>
+{
+    let mut bytes = [0u8; 2 * FQ_SIZE];
+    let size = match compression {
+        UseCompression::Yes => FQ_SIZE,
+        UseCompression::No => 2 * FQ_SIZE,
+    };
+
+    match AffineG1::from_jacobian(*p) {
+        Some(p) => {
+            bytes[0..FQ_SIZE].copy_from_slice(&fq_to_bytes(p.x()));
+            bytes[FQ_SIZE..].copy_from_slice(&fq_to_bytes(p.y()));
+            bytes[0] |= match compression {
+                UseCompression::Yes if is_largest(p.y()) => COMPRESSED_LARGEST,
+                UseCompression::Yes => COMPRESSED_SMALLEST,
+                UseCompression::No => UNCOMPRESSED,
+            };
+        }
+        None => if let UseCompression::Yes = compression {
+            bytes[0] = INFINITY;
+        }
+    }
+    writer.write_all(&bytes[0..size])
+}
+
+fn write_g2<W: Write>(writer: &mut W, p: &G2, compression: UseCompression) -> io::Result<()>
+{
+    let mut bytes = [0u8; 4 * FQ_SIZE];
+    let size = match compression {
+        UseCompression::Yes => 2 * FQ_SIZE,
+        UseCompression::No => 4 * FQ_SIZE,
+    };
+
+    match AffineG2::from_jacobian(*p) {
+        Some(p) => {
+            bytes[0..FQ_SIZE].copy_from_slice(&fq_to_bytes(p.x().imaginary()));
+            bytes[FQ_SIZE..(2 * FQ_SIZE)].copy_from_slice(&fq_to_bytes(p.x().real()));
+            bytes[(2 * FQ_SIZE)..(3 * FQ_SIZE)].copy_from_slice(&fq_to_bytes(p.y().imaginary()));
+            bytes[(3 * FQ_SIZE)..].copy_from_slice(&fq_to_bytes(p.y().real()));
+            bytes[0] |= match compression {
+                UseCompression::Yes if is_largest_fq2(p.y()) => COMPRESSED_LARGEST,
+                UseCompression::Yes => COMPRESSED_SMALLEST,
+                UseCompression::No => UNCOMPRESSED,
+            };
+        }
+        None => if let UseCompression::Yes = compression {
+            bytes[0] = INFINITY;
+        }
+    }
+    writer.write_all(&bytes[0..size])
+}
+
+#[test]
+fn test_gnark_srs() {
+    use bn::Fr;
+    use configuration::*;
+    use engine::{CurvePoint, ScalarField};
+    use super::PrivateKey;
+
+    // An accumulator with tau = 7, from which the vectors were computed by
+    // test_vectors/gen_test_vectors.py.
+    let mut acc = Accumulator::<Bn256>::new(Configuration::new(16));
+    acc.transform(&PrivateKey::new(Fr::from_u64(7), Fr::from_u64(11), Fr::from_u64(13)));
+    let srs = GnarkSrs::new(&acc);
+    assert_eq!(srs.g1.len(), 31);
+
+    let mut data = vec![];
+    srs.serialize(&mut data, UseCompression::Yes).unwrap();
+    assert_eq!(&data[..], &include_bytes!("../test_vectors/gnark_srs_16_compressed.bin")[..]);
+
+    let mut data = vec![];
+    srs.serialize(&mut data, UseCompression::No).unwrap();
+    assert_eq!(&data[..], &include_bytes!("../test_vectors/gnark_srs_16_uncompressed.bin")[..]);
+
+    // The point at infinity.
+    let mut data = vec![];
+    write_g2(&mut data, &G2::zero(), UseCompression::Yes).unwrap();
+    assert_eq!(data[0], INFINITY);
+    assert!(data[1..].iter().all(|&b| b == 0));
+
+    // Uncompressed, it is all zeros.
+    let mut data = vec![];
+    write_g2(&mut data, &G2::zero(), UseCompression::No).unwrap();
+    assert_eq!(data.len(), 4 * FQ_SIZE);
+    assert!(data.iter().all(|&b| b == 0));
+    let mut data = vec![];
+    write_g1(&mut data, &G1::zero(), UseCompression::No).unwrap();
+    assert_eq!(data.len(), 2 * FQ_SIZE);
+    assert!(data.iter().all(|&b| b == 0));
+}
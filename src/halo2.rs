@@ -0,0 +1,284 @@
+//! Export of the powers of tau as the universal setup of halo2's KZG
+//! commitment scheme (`ParamsKZG<Bn256>` in halo2_proofs). Only BN256 is
+//! supported.
+//!
+//! A file for a domain of size n = 2<sup>k</sup> contains, in the
+//! `SerdeFormat::Processed` encoding, which is read with
+//! `ParamsKZG::read_custom(reader, SerdeFormat::Processed)` (`ParamsKZG::read`
+//! expects `SerdeFormat::RawBytes`):
+//!
+//! * k as a little-endian u32
+//! * the powers τ<sup>i</sup> in G1, for i in 0..n
+//! * the Lagrange coefficients L<sub>i</sub>(τ) in G1, for i in 0..n, over
+//!   the roots of unity of halo2curves
+//! * the generator of G2 and τ in G2
+//!
+//! Points are compressed as in halo2curves: the x coordinate as a
+//! little-endian integer (the real part first in G2), with the top bit of the
+//! last byte set if y (the real part of y in G2) is odd. The point at
+//! infinity is all zeros.
+
+use bn::{AffineG1, AffineG2, Fq, Fq2, Fr, G1, G2};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+use bn256::Bn256;
+use domain::EvaluationDomain;
+use engine::CurvePoint;
+use super::{Accumulator, DeserializationError, PointError};
+
+const FQ_SIZE: usize = 32;
+
+/// The primitive 2^28-th root of unity of halo2curves (`Fr::ROOT_OF_UNITY`),
+/// 7^((r - 1) / 2^28). It differs from `ScalarField::root_of_unity`, so the
+/// Lagrange coefficients must be computed over it for halo2 to use them.
+const HALO2_ROOT_OF_UNITY: &str =
+    "1748695177688661943023146337482803886740723238769601073607632802312037301404";
+
+/// Returns the name of the file holding the parameters for a domain of size
+/// 2<sup>`k`</sup>.
+pub fn params_kzg_filename(k: u32) -> String
+{
+    format!("kzg_bn254_{}.srs", k)
+}
+
+/// The contents of a halo2 KZG parameters file.
+#[derive(PartialEq, Eq, Clone)]
+pub struct ParamsKzg {
+    pub k: u32,
+    /// tau^i in G1
+    pub g: Vec<G1>,
+    /// L_i(tau) in G1
+    pub g_lagrange: Vec<G1>,
+    pub g2: G2,
+    /// tau in G2
+    pub s_g2: G2,
+}
+
+impl ParamsKzg {
+    /// Computes the parameters for the domain of size 2<sup>`k`</sup> from
+    /// the powers of tau in `acc`. Panics if the domain exceeds
+    /// `acc.config.num_powers`.
+    pub fn new(acc: &Accumulator<Bn256>, k: u32) -> Self
+    {
+        let n = 1usize << k;
+        assert!(n <= acc.config.num_powers, "domain larger than the accumulator");
+
+        let g = acc.tau_powers_g1[0..n].to_vec();
+
+        // halo2 indexes the Lagrange coefficients by the powers of its own
+        // 2^k-th root of unity.
+        let root = Fr::from_str(HALO2_ROOT_OF_UNITY).expect("root of unity is a valid field element");
+        let mut domain = EvaluationDomain::from_coeffs_with_root(g.clone(), root)
+            .expect("domain size is supported by the scalar field");
+        domain.ifft();
+
+        ParamsKzg {
+            k: k,
+            g: g,
+            g_lagrange: domain.into_coeffs(),
+            g2: acc.tau_powers_g2[0],
+            s_g2: acc.tau_powers_g2[1],
+        }
+    }
+
+    pub fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()>
+    {
+        writer.write_u32::<LittleEndian>(self.k)?;
+        for p in self.g.iter().chain(self.g_lagrange.iter()) {
+            write_g1(writer, p)?;
+        }
+        write_g2(writer, &self.g2)?;
+        write_g2(writer, &self.s_g2)?;
+
+        Ok(())
+    }
+
+    /// Reads the parameters, checking that every point is on the curve and
+    /// in the prime order subgroup. The point at infinity is allowed, as in
+    /// halo2.
+    pub fn deserialize<R: Read>(reader: &mut R) -> Result<Self, DeserializationError>
+    {
+        let k = reader.read_u32::<LittleEndian>()?;
+        if k >= 32 {
+            return Err(DeserializationError::InvalidEncoding);
+        }
+        let n = 1usize << k;
+
+        let g = (0..n).map(|_| read_g1(reader)).collect::<Result<Vec<_>, _>>()?;
+        let g_lagrange = (0..n).map(|_| read_g1(reader)).collect::<Result<Vec<_>, _>>()?;
+        let g2 = read_g2(reader)?;
+        let s_g2 = read_g2(reader)?;
+        for (i, p) in [g2, s_g2].iter().enumerate() {
+            if !p.is_in_subgroup() {
+                return Err(DeserializationError::InvalidPoint("g2", i, PointError::NotInSubgroup));
+            }
+        }
+
+        Ok(ParamsKzg {
+            k: k,
+            g: g,
+            g_lagrange: g_lagrange,
+            g2: g2,
+            s_g2: s_g2,
+        })
+    }
+}
+
+fn fq_to_bytes(x: Fq) -> [u8; FQ_SIZE]
+{
+    let mut bytes = [0u8; FQ_SIZE];
+    x.into_u256().to_big_endian(&mut bytes).expect("32 bytes is enough for Fq");
+    bytes.reverse();
+    bytes
+}
+
+/// Reads a little-endian integer less than q, clearing the flag bit first.
+/// Returns the value and the flag.
+fn fq_from_bytes(bytes: &[u8], flagged: bool) -> Result<(Fq, bool), DeserializationError>
+{
+    let mut be = [0u8; FQ_SIZE];
+    be.copy_from_slice(bytes);
+    be.reverse();
+    let flag = flagged && be[0] & 0x80 != 0;
+    if flagged {
+        be[0] &= 0x7f;
+    }
+
+    Fq::from_slice(&be)
+        .map(|x| (x, flag))
+        .map_err(|_| DeserializationError::InvalidEncoding)
+}
+
+fn is_odd(x: Fq) -> bool
+{
+    fq_to_bytes(x)[0] & 1 == 1
+}
+
+fn write_g1<W: Write>(writer: &mut W, p: &G1) -> io::Result<()>
+{
+    let mut bytes = [0u8; FQ_SIZE];
+    if let Some(p) = AffineG1::from_jacobian(*p) {
+        bytes = fq_to_bytes(p.x());
+        if is_odd(p.y()) {
+            bytes[FQ_SIZE - 1] |= 0x80;
+        }
+    }
+    writer.write_all(&bytes)
+}
+
+fn write_g2<W: Write>(writer: &mut W, p: &G2) -> io::Result<()>
+{
+    let mut bytes = [0u8; 2 * FQ_SIZE];
+    if let Some(p) = AffineG2::from_jacobian(*p) {
+        bytes[0..FQ_SIZE].copy_from_slice(&fq_to_bytes(p.x().real()));
+        bytes[FQ_SIZE..].copy_from_slice(&fq_to_bytes(p.x().imaginary()));
+        if is_odd(p.y().real()) {
+            bytes[2 * FQ_SIZE - 1] |= 0x80;
+        }
+    }
+    writer.write_all(&bytes)
+}
+
+fn read_g1<R: Read>(reader: &mut R) -> Result<G1, DeserializationError>
+{
+    let mut bytes = [0u8; FQ_SIZE];
+    reader.read_exact(&mut bytes)?;
+    let (x, odd) = fq_from_bytes(&bytes, true)?;
+    if x == Fq::zero() && !odd {
+        return Ok(G1::zero());
+    }
+
+    let y = (x * x * x + G1::b()).sqrt().ok_or(DeserializationError::InvalidEncoding)?;
+    let y = if is_odd(y) == odd { y } else { -y };
+    Ok(G1::new(x, y, Fq::one()))
+}
+
+fn read_g2<R: Read>(reader: &mut R) -> Result<G2, DeserializationError>
+{
+    let mut bytes = [0u8; 2 * FQ_SIZE];
+    reader.read_exact(&mut bytes)?;
+    let (c0, _) = fq_from_bytes(&bytes[0..FQ_SIZE], false)?;
+    let (c1, odd) = fq_from_bytes(&bytes[FQ_SIZE..], true)?;
+    let x = Fq2::new(c0, c1);
+    if x == Fq2::zero() && !odd {
+        return Ok(G2::zero());
+    }
+
+    let y = (x * x * x + G2::b()).sqrt().ok_or(DeserializationError::InvalidEncoding)?;
+    let y = if is_odd(y.real()) == odd { y } else { -y };
+    Ok(G2::new(x, y, Fq2::one()))
+}
+
+#[test]
+fn test_params_kzg() {
+    use rand::{thread_rng, Rng};
+    use configuration::*;
+    use engine::ScalarField;
+    use super::{keypair, PrivateKey};
+
+    let config = Configuration::for_engine::<Bn256>(64);
+    let rng = &mut thread_rng();
+    let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+    let mut acc = Accumulator::<Bn256>::new(config);
+    let (_, sk) = keypair::<Bn256, _>(rng, &digest);
+    acc.transform(&sk);
+
+    for &k in &[0, 3, 6] {
+        let n = 1 << k;
+        let params = ParamsKzg::new(&acc, k);
+        assert_eq!(params.g.len(), n);
+        assert_eq!(params.g_lagrange.len(), n);
+        assert!(params.s_g2 == G2::one().mul(&*sk.tau));
+
+        // L_0(tau) = (tau^n - 1) / (n * (tau - 1))
+        let expected = (ScalarField::pow(&*sk.tau, n as u64) - Fr::one()) *
+            (Fr::from_u64(n as u64) * (*sk.tau - Fr::one())).inverse().unwrap();
+        assert!(params.g_lagrange[0] == G1::one().mul(&expected));
+
+        let mut data = vec![];
+        params.serialize(&mut data).unwrap();
+        assert_eq!(data.len(), 4 + 2 * n * FQ_SIZE + 4 * FQ_SIZE);
+        assert!(ParamsKzg::deserialize(&mut &data[..]).unwrap() == params);
+    }
+
+    // With tau = 7, L_1(tau) and L_5(tau) for k = 3, computed independently
+    // over the roots of unity of halo2curves as
+    // L_i(tau) = w^i (tau^n - 1) / (n (tau - w^i)).
+    let mut acc = Accumulator::<Bn256>::new(config);
+    acc.transform(&PrivateKey::new(Fr::from_u64(7), Fr::from_u64(11), Fr::from_u64(13)));
+    let params = ParamsKzg::new(&acc, 3);
+    let expected = [
+        (1, "3788658943884668231861721280327998653770329027470857963788775855569209694488"),
+        (5, "18099583927954477397508157755360305720147261867350300629253705829494328179529"),
+    ];
+    for &(i, l) in &expected {
+        assert!(params.g_lagrange[i] == G1::one().mul(&Fr::from_str(l).unwrap()));
+    }
+
+    // The generator of G1 is (1, 2).
+    let mut data = vec![];
+    write_g1(&mut data, &G1::one()).unwrap();
+    let mut expected = [0u8; FQ_SIZE];
+    expected[0] = 1;
+    assert_eq!(&data[..], &expected[..]);
+
+    // -G1 has odd y.
+    let mut data = vec![];
+    write_g1(&mut data, &-G1::one()).unwrap();
+    expected[FQ_SIZE - 1] = 0x80;
+    assert_eq!(&data[..], &expected[..]);
+    assert!(read_g1(&mut &data[..]).unwrap() == -G1::one());
+
+    // The point at infinity.
+    let mut data = vec![];
+    write_g2(&mut data, &G2::zero()).unwrap();
+    assert!(data.iter().all(|&b| b == 0));
+    assert!(read_g2(&mut &data[..]).unwrap().is_zero());
+
+    // An x with no point on the curve.
+    let mut data = [0u8; FQ_SIZE];
+    data[0] = 4;
+    assert!(read_g1(&mut &data[..]).is_err());
+}
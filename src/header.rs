@@ -0,0 +1,262 @@
+//! Self-describing header at the start of challenge, response and transcript
+//! files.
+//!
+//! The header is not covered by the BLAKE2b hash chain, so a file has the same
+//! hash with or without it. Files written without a header (the legacy layout)
+//! can still be read by passing `--legacy` to the tools.
+//!
+//! Layout (integers big-endian, `HEADER_SIZE` bytes in total):
+//!
+//! * magic (8 bytes)
+//! * format version (u32)
+//! * curve id (u8)
+//! * num_powers (u64, a power of two no more than `MAX_NUM_POWERS`)
+//! * compression flag of each accumulator section (u8 each, 0 or 1)
+//! * reserved, which must be zero
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use configuration::{Configuration, MAX_NUM_POWERS};
+use engine::CurveId;
+use super::UseCompression;
+
+/// Identifies files written by this crate.
+pub const MAGIC: [u8; 8] = *b"POWERTAU";
+
+/// The current format version.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Size of the header on disk.
+pub const HEADER_SIZE: usize = 32;
+
+/// Number of sections in an accumulator: tau powers in G1 and G2, alpha and
+/// beta tau powers in G1, and beta in G2.
+pub const NUM_SECTIONS: usize = 5;
+
+/// Errors that might occur when reading or checking a header.
+#[derive(Debug)]
+pub enum HeaderError {
+    IoError(io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    UnsupportedCurve(u8),
+    InvalidNumPowers(u64),
+    InvalidCompressionFlag(u8),
+    /// The reserved bytes are not all zero.
+    NonzeroReserved,
+    /// The number of powers in the file differs from the one expected.
+    NumPowersMismatch(usize, usize),
+    /// The file is for a different curve than the one expected.
+    CurveMismatch(CurveId, CurveId),
+    /// A section of the file is not in the expected form.
+    CompressionMismatch(usize),
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HeaderError::IoError(ref e) => write!(f, "Disk IO error: {}", e),
+            HeaderError::BadMagic => write!(f, "Not a powers of tau file (use --legacy for files without a header)"),
+            HeaderError::UnsupportedVersion(v) => write!(f, "Unsupported format version {}", v),
+            HeaderError::UnsupportedCurve(c) => write!(f, "Unsupported curve id {}", c),
+            HeaderError::InvalidNumPowers(n) => write!(f, "Invalid number of powers {}", n),
+            HeaderError::InvalidCompressionFlag(c) => write!(f, "Invalid compression flag {}", c),
+            HeaderError::NonzeroReserved => write!(f, "Reserved bytes of the header are not zero"),
+            HeaderError::NumPowersMismatch(expected, found) =>
+                write!(f, "Expected {} powers but the file contains {}", expected, found),
+            HeaderError::CurveMismatch(expected, found) =>
+                write!(f, "Expected a {} file but the file is for {}", expected, found),
+            HeaderError::CompressionMismatch(section) =>
+                write!(f, "Section {} is not in the expected (un)compressed form", section),
+        }
+    }
+}
+
+impl From<io::Error> for HeaderError {
+    fn from(err: io::Error) -> HeaderError {
+        HeaderError::IoError(err)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FileHeader {
+    pub version: u32,
+    pub curve: CurveId,
+    pub num_powers: usize,
+    /// Compression of each section of the accumulator, in file order.
+    pub compression: [UseCompression; NUM_SECTIONS],
+}
+
+impl FileHeader {
+    /// Describes a file holding an accumulator for `config` with every
+    /// section in the same form.
+    pub fn new(config: &Configuration, compression: UseCompression) -> Self {
+        FileHeader {
+            version: FORMAT_VERSION,
+            curve: config.curve,
+            num_powers: config.num_powers,
+            compression: [compression; NUM_SECTIONS],
+        }
+    }
+
+    /// The configuration described by this header.
+    pub fn config(&self) -> Configuration {
+        Configuration::for_curve(self.curve, self.num_powers)
+    }
+
+    /// Checks that every section uses `compression` and, if given, that the
+    /// file holds `num_powers` powers of `curve`.
+    pub fn check(
+        &self,
+        compression: UseCompression,
+        num_powers: Option<usize>,
+        curve: Option<CurveId>
+    ) -> Result<(), HeaderError>
+    {
+        if let Some(curve) = curve {
+            if curve != self.curve {
+                return Err(HeaderError::CurveMismatch(curve, self.curve));
+            }
+        }
+
+        if let Some(num_powers) = num_powers {
+            if num_powers != self.num_powers {
+                return Err(HeaderError::NumPowersMismatch(num_powers, self.num_powers));
+            }
+        }
+
+        for (i, c) in self.compression.iter().enumerate() {
+            if *c != compression {
+                return Err(HeaderError::CompressionMismatch(i));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()>
+    {
+        writer.write_all(&MAGIC)?;
+        writer.write_u32::<BigEndian>(self.version)?;
+        writer.write_u8(self.curve as u8)?;
+        writer.write_u64::<BigEndian>(self.num_powers as u64)?;
+        for c in &self.compression {
+            writer.write_u8(match *c {
+                UseCompression::No => 0,
+                UseCompression::Yes => 1
+            })?;
+        }
+        writer.write_all(&[0; HEADER_SIZE - 8 - 4 - 1 - 8 - NUM_SECTIONS])?;
+
+        Ok(())
+    }
+
+    pub fn deserialize<R: Read>(reader: &mut R) -> Result<Self, HeaderError>
+    {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(HeaderError::BadMagic);
+        }
+
+        let version = reader.read_u32::<BigEndian>()?;
+        if version != FORMAT_VERSION {
+            return Err(HeaderError::UnsupportedVersion(version));
+        }
+
+        let curve = reader.read_u8()?;
+        let curve = CurveId::from_u8(curve).ok_or(HeaderError::UnsupportedCurve(curve))?;
+
+        let num_powers = reader.read_u64::<BigEndian>()?;
+        if num_powers == 0 || (num_powers & (num_powers - 1)) != 0 || num_powers > MAX_NUM_POWERS as u64 {
+            return Err(HeaderError::InvalidNumPowers(num_powers));
+        }
+
+        let mut compression = [UseCompression::No; NUM_SECTIONS];
+        for c in &mut compression {
+            *c = match reader.read_u8()? {
+                0 => UseCompression::No,
+                1 => UseCompression::Yes,
+                flag => return Err(HeaderError::InvalidCompressionFlag(flag))
+            };
+        }
+
+        let mut reserved = [0; HEADER_SIZE - 8 - 4 - 1 - 8 - NUM_SECTIONS];
+        reader.read_exact(&mut reserved)?;
+        if reserved.iter().any(|&b| b != 0) {
+            return Err(HeaderError::NonzeroReserved);
+        }
+
+        Ok(FileHeader {
+            version: version,
+            curve: curve,
+            num_powers: num_powers as usize,
+            compression: compression,
+        })
+    }
+}
+
+#[test]
+fn test_header_serialization() {
+    let config = Configuration::new(16);
+    let header = FileHeader::new(&config, UseCompression::Yes);
+
+    let mut v = vec![];
+    header.serialize(&mut v).unwrap();
+    assert_eq!(v.len(), HEADER_SIZE);
+
+    let deserialized = FileHeader::deserialize(&mut &v[..]).unwrap();
+    assert_eq!(header, deserialized);
+    assert!(deserialized.config() == config);
+
+    assert!(deserialized.check(UseCompression::Yes, None, None).is_ok());
+    assert!(deserialized.check(UseCompression::Yes, Some(16), Some(CurveId::Bn256)).is_ok());
+    match deserialized.check(UseCompression::No, None, None) {
+        Err(HeaderError::CompressionMismatch(0)) => {}
+        _ => panic!("expected a compression mismatch")
+    }
+    match deserialized.check(UseCompression::Yes, Some(32), None) {
+        Err(HeaderError::NumPowersMismatch(32, 16)) => {}
+        _ => panic!("expected a num_powers mismatch")
+    }
+    match deserialized.check(UseCompression::Yes, None, Some(CurveId::Bls12_381)) {
+        Err(HeaderError::CurveMismatch(CurveId::Bls12_381, CurveId::Bn256)) => {}
+        _ => panic!("expected a curve mismatch")
+    }
+
+    let config = Configuration::for_curve(CurveId::Bls12_381, 16);
+    let mut v = vec![];
+    FileHeader::new(&config, UseCompression::No).serialize(&mut v).unwrap();
+    assert!(FileHeader::deserialize(&mut &v[..]).unwrap().config() == config);
+
+    let mut bad = v.clone();
+    bad[0] = b'X';
+    match FileHeader::deserialize(&mut &bad[..]) {
+        Err(HeaderError::BadMagic) => {}
+        _ => panic!("expected bad magic")
+    }
+
+    let mut bad = v.clone();
+    bad[20] = 0x13; // num_powers = 19
+    match FileHeader::deserialize(&mut &bad[..]) {
+        Err(HeaderError::InvalidNumPowers(19)) => {}
+        _ => panic!("expected invalid num_powers")
+    }
+
+    let mut bad = v.clone();
+    bad[15] = 0x01;
+    bad[20] = 0x00; // num_powers = 2^40
+    match FileHeader::deserialize(&mut &bad[..]) {
+        Err(HeaderError::InvalidNumPowers(n)) => assert_eq!(n, 1 << 40),
+        _ => panic!("expected invalid num_powers")
+    }
+
+    let mut bad = v.clone();
+    bad[HEADER_SIZE - 1] = 1;
+    match FileHeader::deserialize(&mut &bad[..]) {
+        Err(HeaderError::NonzeroReserved) => {}
+        _ => panic!("expected nonzero reserved bytes")
+    }
+}
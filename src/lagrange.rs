@@ -0,0 +1,148 @@
+//! Lagrange-basis form of the powers of tau, as required by the Groth16
+//! phase-2 tooling.
+//!
+//! For every radix-2 domain of size m ≤ `num_powers` the verified
+//! `Accumulator` is converted into a `phase1radix2m{log2(m)}` file containing
+//! (all points uncompressed):
+//!
+//! * α, β in G1 and β in G2
+//! * the Lagrange coefficients L<sub>i</sub>(τ) in G1 and G2
+//! * the Lagrange coefficients αL<sub>i</sub>(τ) and βL<sub>i</sub>(τ) in G1
+//! * the H query terms τ<sup>i</sup>(τ<sup>m</sup> - 1) in G1, for i in 0..m-1
+
+use std::io::{self, Write};
+use std::ops::*;
+
+use super::{Accumulator, UseCompression, write_point};
+use domain::EvaluationDomain;
+use engine::{CurvePoint, Engine};
+
+/// Returns the name of the file holding the Lagrange coefficients for a
+/// domain of size 2<sup>`log_m`</sup>.
+pub fn lagrange_filename(log_m: usize) -> String
+{
+    format!("phase1radix2m{}", log_m)
+}
+
+/// The contents of a `phase1radix2m` file.
+#[derive(PartialEq, Eq, Clone)]
+pub struct LagrangeCoefficients<E: Engine> {
+    pub alpha_g1: E::G1,
+    pub beta_g1: E::G1,
+    pub beta_g2: E::G2,
+    /// L_i(tau) in G1
+    pub coeffs_g1: Vec<E::G1>,
+    /// L_i(tau) in G2
+    pub coeffs_g2: Vec<E::G2>,
+    /// alpha * L_i(tau) in G1
+    pub alpha_coeffs_g1: Vec<E::G1>,
+    /// beta * L_i(tau) in G1
+    pub beta_coeffs_g1: Vec<E::G1>,
+    /// tau^i * (tau^m - 1) in G1, for i in 0..m-1
+    pub h: Vec<E::G1>,
+}
+
+impl<E: Engine> LagrangeCoefficients<E> {
+    /// Computes the Lagrange coefficients over the domain of size `m` from
+    /// the powers of tau in `acc`. Panics if `m` is not a power of two or
+    /// exceeds `acc.config.num_powers`.
+    pub fn new(acc: &Accumulator<E>, m: usize) -> Self
+    {
+        assert!(m > 0 && (m & (m - 1)) == 0, "domain size must be a power of two");
+        assert!(m <= acc.config.num_powers, "domain larger than the accumulator");
+
+        fn ifft<G: CurvePoint>(v: &[G]) -> Vec<G> {
+            let mut domain = EvaluationDomain::from_coeffs(v.to_vec())
+                .expect("domain size is supported by the scalar field");
+            domain.ifft();
+            domain.into_coeffs()
+        }
+
+        // The H query includes terms of the form tau^i * (tau^m - 1)
+        let h = (0..(m - 1))
+            .map(|i| acc.tau_powers_g1[i + m].sub(acc.tau_powers_g1[i]))
+            .collect::<Vec<_>>();
+
+        LagrangeCoefficients {
+            alpha_g1: acc.alpha_tau_powers_g1[0],
+            beta_g1: acc.beta_tau_powers_g1[0],
+            beta_g2: acc.beta_g2,
+            coeffs_g1: ifft(&acc.tau_powers_g1[0..m]),
+            coeffs_g2: ifft(&acc.tau_powers_g2[0..m]),
+            alpha_coeffs_g1: ifft(&acc.alpha_tau_powers_g1[0..m]),
+            beta_coeffs_g1: ifft(&acc.beta_tau_powers_g1[0..m]),
+            h: h,
+        }
+    }
+
+    /// Write the coefficients in the `phase1radix2m` layout. Points are always
+    /// in uncompressed form.
+    pub fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()>
+    {
+        write_point(writer, &self.alpha_g1, UseCompression::No)?;
+        write_point(writer, &self.beta_g1, UseCompression::No)?;
+        write_point(writer, &self.beta_g2, UseCompression::No)?;
+
+        for p in &self.coeffs_g1 {
+            write_point(writer, p, UseCompression::No)?;
+        }
+        for p in &self.coeffs_g2 {
+            write_point(writer, p, UseCompression::No)?;
+        }
+        for p in &self.alpha_coeffs_g1 {
+            write_point(writer, p, UseCompression::No)?;
+        }
+        for p in &self.beta_coeffs_g1 {
+            write_point(writer, p, UseCompression::No)?;
+        }
+        for p in &self.h {
+            write_point(writer, p, UseCompression::No)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_lagrange_coefficients() {
+    use rand::{thread_rng, Rng};
+    use bls12::Bls12;
+    use bn256::Bn256;
+    use configuration::*;
+    use engine::ScalarField;
+    use super::keypair;
+
+    fn test_engine<E: Engine>() {
+        let config = Configuration::for_engine::<E>(256);
+        let rng = &mut thread_rng();
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+        let mut acc = Accumulator::<E>::new(config);
+        let (_, sk) = keypair::<E, _>(rng, &digest);
+        acc.transform(&sk);
+
+        for &m in &[1, 16, 256] {
+            let lagrange = LagrangeCoefficients::new(&acc, m);
+            assert_eq!(lagrange.coeffs_g1.len(), m);
+            assert_eq!(lagrange.h.len(), m - 1);
+
+            // The Lagrange polynomials sum to one.
+            fn sum<G: CurvePoint>(v: &[G]) -> G {
+                v.iter().fold(G::zero(), |acc, p| acc.add(*p))
+            }
+            assert!(sum(&lagrange.coeffs_g1) == E::G1::one());
+            assert!(sum(&lagrange.coeffs_g2) == E::G2::one());
+            assert!(sum(&lagrange.alpha_coeffs_g1) == lagrange.alpha_g1);
+            assert!(sum(&lagrange.beta_coeffs_g1) == lagrange.beta_g1);
+
+            // L_0(tau) = (tau^m - 1) / (m * (tau - 1))
+            let m_fr = E::Fr::from_u64(m as u64);
+            let expected = (sk.tau.pow(m as u64) - E::Fr::one()) *
+                (m_fr * (*sk.tau - E::Fr::one())).inverse().unwrap();
+            assert!(lagrange.coeffs_g1[0] == E::G1::one().mul(&expected));
+        }
+    }
+
+    test_engine::<Bn256>();
+    test_engine::<Bls12>();
+}
@@ -1,5 +1,6 @@
 //! This ceremony constructs the "powers of tau" for Jens Groth's 2016 zk-SNARK proving
-//! system using the BN256 pairing-friendly elliptic curve construction.
+//! system using the BN256 pairing-friendly elliptic curve construction. The
+//! BLS12-381 curve is also supported (see `engine`).
 //!
 //! # Overview
 //!
@@ -27,6 +28,7 @@
 //! public parameters for all circuits within a bounded size.
 
 extern crate bn;
+extern crate bls12_381;
 extern crate rand;
 extern crate crossbeam;
 extern crate num_cpus;
@@ -36,17 +38,17 @@ extern crate typenum;
 extern crate byteorder;
 extern crate bincode;
 extern crate rustc_serialize;
+extern crate crypto;
+extern crate hex;
+#[cfg(unix)]
+extern crate libc;
 
-use arith::{U256};
 use byteorder::{ReadBytesExt, BigEndian};
 use rand::{SeedableRng, Rng};
 use rand::chacha::ChaChaRng;
-use bn::*;
+use bn::CurveError;
 use std::ops::*;
-use bincode::{DecodingError, EncodingError};
-use rustc_serialize::{Decodable, Encodable};
-
-const INF : bincode::SizeLimit = bincode::SizeLimit::Infinite;
+use bincode::DecodingError;
 
 use std::io::{self, Read, Write};
 use generic_array::GenericArray;
@@ -54,13 +56,36 @@ use typenum::consts::U64;
 use blake2::{Blake2b, Digest};
 use std::fmt;
 
+pub mod arkworks;
+pub mod beacon;
+pub mod bellman;
+pub mod bls12;
+pub mod bn256;
 pub mod configuration;
 pub mod cmd_utils;
+pub mod constant_time;
+pub mod domain;
+pub mod engine;
+pub mod gnark;
+pub mod halo2;
+pub mod header;
+pub mod lagrange;
+pub mod libff;
+pub mod multiexp;
+pub mod ptau;
+pub mod sandbox;
+pub mod secret;
+pub mod streaming;
+pub mod wnaf;
 use configuration::*;
+use engine::*;
+use multiexp::multiexp;
+use secret::{SecretBox, SecretVec};
+use wnaf::WnafGroup;
 
 /// Hashes to G2 using the first 32 bytes of `digest`. Panics if `digest` is less
 /// than 32 bytes.
-fn hash_to_g2(mut digest: &[u8]) -> G2
+fn hash_to_g2<E: Engine>(mut digest: &[u8]) -> E::G2
 {
     assert!(digest.len() >= 32);
 
@@ -70,22 +95,27 @@ fn hash_to_g2(mut digest: &[u8]) -> G2
         seed[(4 * i) .. ((4 * i) + 4)].copy_from_slice(&bytes);
     }
 
-    G2::random(&mut ChaChaRng::from_seed(seed))
+    E::G2::random(&mut ChaChaRng::from_seed(seed))
 }
 
 #[test]
 fn test_hash_to_g2() {
-    assert!(
-        hash_to_g2(&[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32,33])
-        ==
-        hash_to_g2(&[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32,34])
-    );
+    fn test_engine<E: Engine>() {
+        assert!(
+            hash_to_g2::<E>(&[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32,33])
+            ==
+            hash_to_g2::<E>(&[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32,34])
+        );
+
+        assert!(
+            hash_to_g2::<E>(&[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32])
+            !=
+            hash_to_g2::<E>(&[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,33])
+        );
+    }
 
-    assert!(
-        hash_to_g2(&[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,32])
-        !=
-        hash_to_g2(&[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,29,30,31,33])
-    );
+    test_engine::<bn256::Bn256>();
+    test_engine::<bls12::Bls12>();
 }
 
 /// Contains terms of the form (s<sub>1</sub>, s<sub>1</sub><sup>x</sup>, H(s<sub>1</sub><sup>x</sup>)<sub>2</sub>, H(s<sub>1</sub><sup>x</sup>)<sub>2</sub><sup>x</sup>)
@@ -96,31 +126,52 @@ fn test_hash_to_g2() {
 ///
 /// It is necessary to verify `same_ratio`((s<sub>1</sub>, s<sub>1</sub><sup>x</sup>), (H(s<sub>1</sub><sup>x</sup>)<sub>2</sub>, H(s<sub>1</sub><sup>x</sup>)<sub>2</sub><sup>x</sup>)).
 #[derive(PartialEq, Eq)]
-pub struct PublicKey {
-    tau_g1: (G1, G1),
-    alpha_g1: (G1, G1),
-    beta_g1: (G1, G1),
-    tau_g2: G2,
-    alpha_g2: G2,
-    beta_g2: G2
+pub struct PublicKey<E: Engine> {
+    tau_g1: (E::G1, E::G1),
+    alpha_g1: (E::G1, E::G1),
+    beta_g1: (E::G1, E::G1),
+    tau_g2: E::G2,
+    alpha_g2: E::G2,
+    beta_g2: E::G2
 }
 
 /// Contains the secrets τ, α and β that the participant of the ceremony must destroy.
-pub struct PrivateKey {
-    tau: Fr,
-    alpha: Fr,
-    beta: Fr
+/// They are held in locked memory and zeroed when the key is dropped (see `secret`).
+pub struct PrivateKey<E: Engine> {
+    tau: SecretBox<E::Fr>,
+    alpha: SecretBox<E::Fr>,
+    beta: SecretBox<E::Fr>
+}
+
+impl<E: Engine> PrivateKey<E> {
+    fn new(tau: E::Fr, alpha: E::Fr, beta: E::Fr) -> Self
+    {
+        PrivateKey {
+            tau: SecretBox::new(tau),
+            alpha: SecretBox::new(alpha),
+            beta: SecretBox::new(beta)
+        }
+    }
+
+    /// Whether the secrets could be locked into memory. If not, they might
+    /// be written to swap.
+    pub fn is_locked(&self) -> bool
+    {
+        self.tau.is_locked() && self.alpha.is_locked() && self.beta.is_locked()
+    }
 }
 
-fn compute_g2_s(
-    g1_s: &G1,
-    g1_s_x: &G1,
+fn compute_g2_s<E: Engine>(
+    g1_s: &E::G1,
+    g1_s_x: &E::G1,
     personalization: u8,
-    transcript_digest: &[u8]) -> G2
+    transcript_digest: &[u8]) -> E::G2
 {
-    let g1_s_enc = bincode::encode(&g1_s, INF)
+    let mut g1_s_enc = vec![];
+    write_point(&mut g1_s_enc, g1_s, UseCompression::No)
         .expect("g1_s encoding");
-    let g1_s_x_enc = bincode::encode(&g1_s_x, INF)
+    let mut g1_s_x_enc = vec![];
+    write_point(&mut g1_s_x_enc, g1_s_x, UseCompression::No)
         .expect("g1_s_x encoding");
 
     // Compute BLAKE2b(personalization | transcript | g^s | g^{s*x})
@@ -131,34 +182,37 @@ fn compute_g2_s(
     h.update(&g1_s_x_enc);
 
     // Hash into G2 as g^{s'}
-    hash_to_g2(&h.finalize())
+    hash_to_g2::<E>(&h.finalize())
 }
 
 /// Constructs a keypair given an RNG and a 64-byte transcript `digest`.
-pub fn keypair<R: Rng>(rng: &mut R, digest: &[u8]) -> (PublicKey, PrivateKey)
+pub fn keypair<E: Engine, R: Rng>(rng: &mut R, digest: &[u8]) -> (PublicKey<E>, PrivateKey<E>)
 {
     assert_eq!(digest.len(), 64);
 
-    let tau = Fr::random(rng);
-    let alpha = Fr::random(rng);
-    let beta = Fr::random(rng);
+    let tau = SecretBox::new(E::Fr::random(rng));
+    let alpha = SecretBox::new(E::Fr::random(rng));
+    let beta = SecretBox::new(E::Fr::random(rng));
+
+    let g1_params = E::G1::wnaf_params();
+    let g2_params = E::G2::wnaf_params();
 
-    let mut op = |x, personalization: u8| {
+    let mut op = |x: &E::Fr, personalization: u8| {
         // Sample random g^s
-        let g1_s = G1::random(rng);
+        let g1_s = E::G1::random(rng);
         // Compute g^{s*x}
-        let g1_s_x = g1_s.mul(x);
+        let g1_s_x = secret_mul(&g1_s, x, &g1_params);
         // Compute hash in G2
-        let g2_s = compute_g2_s(&g1_s, &g1_s_x, personalization, digest);
+        let g2_s = compute_g2_s::<E>(&g1_s, &g1_s_x, personalization, digest);
         // Compute g^{s'*x}
-        let g2_s_x = g2_s.mul(x);
+        let g2_s_x = secret_mul(&g2_s, x, &g2_params);
 
         ((g1_s, g1_s_x), g2_s_x)
     };
 
-    let pk_tau = op(tau, 0);
-    let pk_alpha = op(alpha, 1);
-    let pk_beta = op(beta, 2);
+    let pk_tau = op(&*tau, 0);
+    let pk_alpha = op(&*alpha, 1);
+    let pk_beta = op(&*beta, 2);
 
     (
         PublicKey {
@@ -178,7 +232,7 @@ pub fn keypair<R: Rng>(rng: &mut R, digest: &[u8]) -> (PublicKey, PrivateKey)
 }
 
 /// Determines if point compression should be used.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum UseCompression {
     Yes,
     No
@@ -199,21 +253,29 @@ fn write_point<W, G>(
     compression: UseCompression
 ) -> io::Result<()>
     where W: Write,
-          G: Group,
-          G: Encodable,
-          G::Compressed: Encodable,
+          G: CurvePoint
 {
-    let result = match compression {
-        UseCompression::No =>
-            bincode::encode_into(p, writer, INF),
-        UseCompression::Yes =>
-            bincode::encode_into(&p.as_compressed(), writer, INF),
-    };
+    match compression {
+        UseCompression::No => p.write_uncompressed(writer),
+        UseCompression::Yes => p.write_compressed(writer),
+    }
+}
 
-    match result {
-        Err(EncodingError::IoError(io_err)) => Err(io_err),
-        Err(EncodingError::SizeLimit) => Err(io::ErrorKind::Other)?,
-        Ok(()) => Ok(()),
+/// Reasons a point can fail the checks performed with `CheckForCorrectness::Yes`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PointError {
+    NotOnCurve,
+    NotInSubgroup,
+    PointAtInfinity
+}
+
+impl fmt::Display for PointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PointError::NotOnCurve => write!(f, "not on the curve"),
+            PointError::NotInSubgroup => write!(f, "not in the prime order subgroup"),
+            PointError::PointAtInfinity => write!(f, "point at infinity")
+        }
     }
 }
 
@@ -223,7 +285,11 @@ pub enum DeserializationError {
     IoError(io::Error),
     DecodingError(DecodingError),
     CurveError(CurveError),
-    PointAtInfinity
+    /// A point whose encoding is not valid for the curve.
+    InvalidEncoding,
+    PointAtInfinity,
+    /// An invalid point, given as (section, index within section, reason).
+    InvalidPoint(&'static str, usize, PointError)
 }
 
 impl fmt::Display for DeserializationError {
@@ -232,7 +298,10 @@ impl fmt::Display for DeserializationError {
             DeserializationError::IoError(ref e) => write!(f, "Disk IO error: {}", e),
             DeserializationError::DecodingError(ref e) => write!(f, "Decoding error: {}", e),
             DeserializationError::CurveError(ref e) => write!(f, "Curve error: {:?}", e),
-            DeserializationError::PointAtInfinity => write!(f, "Point at infinity found")
+            DeserializationError::InvalidEncoding => write!(f, "Invalid point encoding"),
+            DeserializationError::PointAtInfinity => write!(f, "Point at infinity found"),
+            DeserializationError::InvalidPoint(section, index, ref e) =>
+                write!(f, "Invalid point {} in {}: {}", index, section, e)
         }
     }
 }
@@ -255,7 +324,127 @@ impl From<DecodingError> for DeserializationError {
     }
 }
 
-impl PublicKey {
+/// The check that failed when verifying a transformation of the accumulator.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum VerificationError {
+    TauProofOfKnowledge,
+    AlphaProofOfKnowledge,
+    BetaProofOfKnowledge,
+    /// The first power of tau in G1 is not the generator.
+    TauG1GeneratorNotOne,
+    /// The first power of tau in G2 is not the generator.
+    TauG2GeneratorNotOne,
+    /// The new tau is not the previous one multiplied by the key's.
+    TauRatio,
+    /// The new alpha is not the previous one multiplied by the key's.
+    AlphaRatio,
+    /// The new beta is not the previous one multiplied by the key's.
+    BetaRatio,
+    /// The new beta in G2 does not match the new beta in G1.
+    BetaG2Ratio,
+    /// The powers of tau in G1 are not consecutive.
+    TauPowersG1,
+    /// The powers of tau in G2 are not consecutive.
+    TauPowersG2,
+    /// The powers of tau multiplied by alpha are not consecutive.
+    AlphaTauPowersG1,
+    /// The powers of tau multiplied by beta are not consecutive.
+    BetaTauPowersG1,
+}
+
+impl VerificationError {
+    /// The exit code with which the command line tools report this failure.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            VerificationError::TauProofOfKnowledge => 10,
+            VerificationError::AlphaProofOfKnowledge => 11,
+            VerificationError::BetaProofOfKnowledge => 12,
+            VerificationError::TauG1GeneratorNotOne => 13,
+            VerificationError::TauG2GeneratorNotOne => 14,
+            VerificationError::TauRatio => 15,
+            VerificationError::AlphaRatio => 16,
+            VerificationError::BetaRatio => 17,
+            VerificationError::BetaG2Ratio => 18,
+            VerificationError::TauPowersG1 => 19,
+            VerificationError::TauPowersG2 => 20,
+            VerificationError::AlphaTauPowersG1 => 21,
+            VerificationError::BetaTauPowersG1 => 22,
+        }
+    }
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VerificationError::TauProofOfKnowledge =>
+                write!(f, "Invalid proof of knowledge of tau"),
+            VerificationError::AlphaProofOfKnowledge =>
+                write!(f, "Invalid proof of knowledge of alpha"),
+            VerificationError::BetaProofOfKnowledge =>
+                write!(f, "Invalid proof of knowledge of beta"),
+            VerificationError::TauG1GeneratorNotOne =>
+                write!(f, "First power of tau in G1 is not the generator"),
+            VerificationError::TauG2GeneratorNotOne =>
+                write!(f, "First power of tau in G2 is not the generator"),
+            VerificationError::TauRatio =>
+                write!(f, "Tau was not multiplied by the contributed tau"),
+            VerificationError::AlphaRatio =>
+                write!(f, "Alpha was not multiplied by the contributed alpha"),
+            VerificationError::BetaRatio =>
+                write!(f, "Beta was not multiplied by the contributed beta"),
+            VerificationError::BetaG2Ratio =>
+                write!(f, "Beta in G2 does not match beta in G1"),
+            VerificationError::TauPowersG1 =>
+                write!(f, "Inconsistent powers of tau in G1"),
+            VerificationError::TauPowersG2 =>
+                write!(f, "Inconsistent powers of tau in G2"),
+            VerificationError::AlphaTauPowersG1 =>
+                write!(f, "Inconsistent powers of tau multiplied by alpha"),
+            VerificationError::BetaTauPowersG1 =>
+                write!(f, "Inconsistent powers of tau multiplied by beta"),
+        }
+    }
+}
+
+/// Checks every point in `elements` across multiple cores, reporting the
+/// first invalid point found in `section`. `elements` starts at index `offset`
+/// within the section.
+fn check_all<C: CurvePoint>(
+    elements: &[C],
+    section: &'static str,
+    offset: usize
+) -> Result<(), DeserializationError>
+{
+    let chunk_size = (elements.len() / num_cpus::get()) + 1;
+
+    let results = crossbeam::scope(|scope| {
+        let mut handles = vec![];
+
+        for (i, elements) in elements.chunks(chunk_size).enumerate() {
+            handles.push(scope.spawn(move || {
+                for (j, element) in elements.iter().enumerate() {
+                    if let Err(e) = element.check() {
+                        return Err((offset + (i * chunk_size) + j, e));
+                    }
+                }
+
+                Ok(())
+            }));
+        }
+
+        handles.into_iter().map(|h| h.join()).collect::<Vec<_>>()
+    });
+
+    for result in results {
+        if let Err((index, e)) = result {
+            return Err(DeserializationError::InvalidPoint(section, index, e));
+        }
+    }
+
+    Ok(())
+}
+
+impl<E: Engine> PublicKey<E> {
     /// Serialize the public key. Points are always in uncompressed form.
     pub fn serialize<W: Write>(&self, writer: &mut W) -> io::Result<()>
     {
@@ -278,30 +467,34 @@ impl PublicKey {
     /// Deserialize the public key. Points are always in uncompressed form, and
     /// always checked, since there aren't very many of them. Does not allow any
     /// points at infinity.
-    pub fn deserialize<R: Read>(reader: &mut R) -> Result<PublicKey, DeserializationError>
+    pub fn deserialize<R: Read>(reader: &mut R) -> Result<PublicKey<E>, DeserializationError>
     {
-        fn read_uncompressed<C: Group + Decodable, R: Read>(reader: &mut R) -> Result<C, DeserializationError> {
-            let v : C = bincode::decode_from(reader, INF)?;
+        fn read_uncompressed<C: CurvePoint, R: Read>(
+            reader: &mut R,
+            index: usize
+        ) -> Result<C, DeserializationError>
+        {
+            let v = C::read_uncompressed_unchecked(reader)?;
 
-            if v.is_zero() {
-                Err(DeserializationError::PointAtInfinity)
-            } else {
-                Ok(v)
+            match v.check() {
+                Ok(()) => Ok(v),
+                Err(PointError::PointAtInfinity) => Err(DeserializationError::PointAtInfinity),
+                Err(e) => Err(DeserializationError::InvalidPoint("public_key", index, e))
             }
         }
 
-        let tau_g1_s = read_uncompressed(reader)?;
-        let tau_g1_s_tau = read_uncompressed(reader)?;
+        let tau_g1_s = read_uncompressed(reader, 0)?;
+        let tau_g1_s_tau = read_uncompressed(reader, 1)?;
 
-        let alpha_g1_s = read_uncompressed(reader)?;
-        let alpha_g1_s_alpha = read_uncompressed(reader)?;
+        let alpha_g1_s = read_uncompressed(reader, 2)?;
+        let alpha_g1_s_alpha = read_uncompressed(reader, 3)?;
 
-        let beta_g1_s = read_uncompressed(reader)?;
-        let beta_g1_s_beta = read_uncompressed(reader)?;
+        let beta_g1_s = read_uncompressed(reader, 4)?;
+        let beta_g1_s_beta = read_uncompressed(reader, 5)?;
 
-        let tau_g2 = read_uncompressed(reader)?;
-        let alpha_g2 = read_uncompressed(reader)?;
-        let beta_g2 = read_uncompressed(reader)?;
+        let tau_g2 = read_uncompressed(reader, 6)?;
+        let alpha_g2 = read_uncompressed(reader, 7)?;
+        let beta_g2 = read_uncompressed(reader, 8)?;
 
         Ok(PublicKey {
             tau_g1: (tau_g1_s, tau_g1_s_tau),
@@ -318,14 +511,19 @@ impl PublicKey {
 fn test_pubkey_serialization() {
     use rand::thread_rng;
 
-    let rng = &mut thread_rng();
-    let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
-    let (pk, _) = keypair(rng, &digest);
-    let mut v = vec![];
-    pk.serialize(&mut v).unwrap();
-    assert_eq!(v.len(), PUBLIC_KEY_SIZE);
-    let deserialized = PublicKey::deserialize(&mut &v[..]).unwrap();
-    assert!(pk == deserialized);
+    fn test_engine<E: Engine>() {
+        let rng = &mut thread_rng();
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+        let (pk, _) = keypair::<E, _>(rng, &digest);
+        let mut v = vec![];
+        pk.serialize(&mut v).unwrap();
+        assert_eq!(v.len(), public_key_size::<E>());
+        let deserialized = PublicKey::deserialize(&mut &v[..]).unwrap();
+        assert!(pk == deserialized);
+    }
+
+    test_engine::<bn256::Bn256>();
+    test_engine::<bls12::Bls12>();
 }
 
 /// The `Accumulator` is an object that participants of the ceremony contribute
@@ -336,29 +534,31 @@ fn test_pubkey_serialization() {
 /// * (τ, τ<sup>2</sup>, ..., τ<sup>2<sup>22</sup> - 2</sup>, α, ατ, ατ<sup>2</sup>, ..., ατ<sup>2<sup>21</sup> - 1</sup>, β, βτ, βτ<sup>2</sup>, ..., βτ<sup>2<sup>21</sup> - 1</sup>)<sub>1</sub>
 /// * (β, τ, τ<sup>2</sup>, ..., τ<sup>2<sup>21</sup> - 1</sup>)<sub>2</sub>
 #[derive(PartialEq, Eq, Clone)]
-pub struct Accumulator {
+pub struct Accumulator<E: Engine> {
     /// tau^0, tau^1, tau^2, ..., tau^{TAU_POWERS_G1_LENGTH - 1}
-    pub tau_powers_g1: Vec<G1>,
+    pub tau_powers_g1: Vec<E::G1>,
     /// tau^0, tau^1, tau^2, ..., tau^{TAU_POWERS_LENGTH - 1}
-    pub tau_powers_g2: Vec<G2>,
+    pub tau_powers_g2: Vec<E::G2>,
     /// alpha * tau^0, alpha * tau^1, alpha * tau^2, ..., alpha * tau^{TAU_POWERS_LENGTH - 1}
-    pub alpha_tau_powers_g1: Vec<G1>,
+    pub alpha_tau_powers_g1: Vec<E::G1>,
     /// beta * tau^0, beta * tau^1, beta * tau^2, ..., beta * tau^{TAU_POWERS_LENGTH - 1}
-    pub beta_tau_powers_g1: Vec<G1>,
+    pub beta_tau_powers_g1: Vec<E::G1>,
     /// beta
-    pub beta_g2: G2,
+    pub beta_g2: E::G2,
     pub config: Configuration,
 }
 
-impl Accumulator {
+impl<E: Engine> Accumulator<E> {
     /// Constructs an "initial" accumulator with τ = 1, α = 1, β = 1.
     pub fn new(config: Configuration) -> Self {
+        assert_eq!(config.curve, E::CURVE, "configuration is for a different curve");
+
         Accumulator {
-            tau_powers_g1: vec![G1::one(); config.num_powers_g1],
-            tau_powers_g2: vec![G2::one(); config.num_powers],
-            alpha_tau_powers_g1: vec![G1::one(); config.num_powers],
-            beta_tau_powers_g1: vec![G1::one(); config.num_powers],
-            beta_g2: G2::one(),
+            tau_powers_g1: vec![E::G1::one(); config.num_powers_g1],
+            tau_powers_g2: vec![E::G2::one(); config.num_powers],
+            alpha_tau_powers_g1: vec![E::G1::one(); config.num_powers],
+            beta_tau_powers_g1: vec![E::G1::one(); config.num_powers],
+            beta_g2: E::G2::one(),
             config: config,
         }
     }
@@ -370,32 +570,18 @@ impl Accumulator {
         compression: UseCompression
     ) -> io::Result<()>
     {
-        fn write_all<W: Write, C: Group + Encodable>(
-            writer: &mut W,
-            c: &[C],
-            compression: UseCompression)
-            -> io::Result<()>
-            where C::Compressed: Encodable
-        {
-            for c in c {
-                write_point(writer, c, compression)?;
-            }
-
-            Ok(())
-        }
-
-        write_all(writer, &self.tau_powers_g1, compression)?;
-        write_all(writer, &self.tau_powers_g2, compression)?;
-        write_all(writer, &self.alpha_tau_powers_g1, compression)?;
-        write_all(writer, &self.beta_tau_powers_g1, compression)?;
-        write_all(writer, &[self.beta_g2], compression)?;
+        write_points(writer, &self.tau_powers_g1, compression)?;
+        write_points(writer, &self.tau_powers_g2, compression)?;
+        write_points(writer, &self.alpha_tau_powers_g1, compression)?;
+        write_points(writer, &self.beta_tau_powers_g1, compression)?;
+        write_points(writer, &[self.beta_g2], compression)?;
 
         Ok(())
     }
 
     /// Read the accumulator from disk with some compression behavior. `checked`
-    /// indicates whether we should check it's a valid element of the group and
-    /// not the point at infinity.
+    /// indicates whether we should check each point is on the curve, in the
+    /// prime order subgroup and not the point at infinity.
     pub fn deserialize<R: Read>(
         config: Configuration,
         reader: &mut R,
@@ -403,56 +589,17 @@ impl Accumulator {
         checked: CheckForCorrectness
     ) -> Result<Self, DeserializationError>
     {
-        fn read_all<R: Read, C: Group + Decodable>(
-            reader: &mut R,
-            size: usize,
-            compression: UseCompression,
-            checked: CheckForCorrectness
-        ) -> Result<Vec<C>, DeserializationError>
-        where C::Compressed : Decodable
-        {
-            fn decompress_all<R: Read, C: Group + Decodable>(
-                reader: &mut R,
-                size: usize,
-                compression: UseCompression,
-                _checked: CheckForCorrectness
-            ) -> Result<Vec<C>, DeserializationError>
-                where C::Compressed : Decodable
-            {
-                // Read the encoded elements
-                let mut elements = vec![C::zero(); size];
-
-                match compression {
-                    UseCompression::No => {
-                        for element in &mut elements {
-                            *element = bincode::decode_from(reader, INF)?;
-                        }
-                    }
-                    UseCompression::Yes => {
-                        for element in &mut elements {
-                            let comp : C::Compressed = bincode::decode_from(reader, INF)?;
-                            *element = C::from_compressed(&comp)?
-                        }
-                    }
-                }
-
-                // TODO: Support skipping correctness checking
-
-                Ok(elements)
-            }
-
-            decompress_all::<_, C>(reader, size, compression, checked)
-        }
-
-        let tau_powers_g1 = read_all(
-            reader, config.num_powers_g1, compression, checked)?;
-        let tau_powers_g2 = read_all(
-            reader, config.num_powers, compression, checked)?;
-        let alpha_tau_powers_g1 = read_all(
-            reader, config.num_powers, compression, checked)?;
-        let beta_tau_powers_g1 = read_all(
-            reader, config.num_powers, compression, checked)?;
-        let beta_g2 = read_all(reader, 1, compression, checked)?[0];
+        assert_eq!(config.curve, E::CURVE, "configuration is for a different curve");
+
+        let tau_powers_g1 = read_points(
+            reader, config.num_powers_g1, compression, checked, "tau_powers_g1", 0)?;
+        let tau_powers_g2 = read_points(
+            reader, config.num_powers, compression, checked, "tau_powers_g2", 0)?;
+        let alpha_tau_powers_g1 = read_points(
+            reader, config.num_powers, compression, checked, "alpha_tau_powers_g1", 0)?;
+        let beta_tau_powers_g1 = read_points(
+            reader, config.num_powers, compression, checked, "beta_tau_powers_g1", 0)?;
+        let beta_g2 = read_points(reader, 1, compression, checked, "beta_g2", 0)?[0];
 
         Ok(Accumulator {
             tau_powers_g1: tau_powers_g1,
@@ -464,123 +611,314 @@ impl Accumulator {
         })
     }
 
+    /// Checks the parts of `verify_transform` that concern this accumulator
+    /// alone: the first powers of tau are the generators, beta in G1 and G2
+    /// match, and the powers are consecutive. Used for accumulators that do
+    /// not come from this ceremony, whose points should already have been
+    /// checked.
+    pub fn verify_consistency(&self) -> Result<(), VerificationError>
+    {
+        TransformSummary::new(self, self).verify_consistency()
+    }
+
     /// Transforms the accumulator with a private key.
-    pub fn transform(&mut self, key: &PrivateKey)
+    pub fn transform(&mut self, key: &PrivateKey<E>)
     {
         // Construct the powers of tau
-        let mut taupowers = vec![Fr::zero(); self.config.num_powers_g1];
-        let chunk_size = self.config.num_powers_g1 / num_cpus::get();
-
-        crossbeam::scope(|scope| {
-            for (i, taupowers) in taupowers.chunks_mut(chunk_size).enumerate() {
-                scope.spawn(move || {
-                    let exp : U256 = U256::from((i * chunk_size) as u64);
-                    let exp = Fr::new(exp).unwrap();
-                    let mut acc = key.tau.pow(exp);
-                    for t in taupowers {
-                        *t = acc;
-                        acc = acc * key.tau;
-                    }
-                });
+        let taupowers = tau_powers(&*key.tau, 0, self.config.num_powers_g1);
+
+        let num_powers = self.config.num_powers;
+        batch_exp(&mut self.tau_powers_g1, &taupowers[0..], None);
+        batch_exp(&mut self.tau_powers_g2, &taupowers[0..num_powers], None);
+        batch_exp(&mut self.alpha_tau_powers_g1, &taupowers[0..num_powers], Some(&*key.alpha));
+        batch_exp(&mut self.beta_tau_powers_g1, &taupowers[0..num_powers], Some(&*key.beta));
+        self.beta_g2 = secret_mul(&self.beta_g2, &*key.beta, &E::G2::wnaf_params());
+    }
+}
+
+/// Write a sequence of points with some compression behavior.
+fn write_points<W: Write, C: CurvePoint>(
+    writer: &mut W,
+    c: &[C],
+    compression: UseCompression)
+    -> io::Result<()>
+{
+    for c in c {
+        write_point(writer, c, compression)?;
+    }
+
+    Ok(())
+}
+
+/// Read `size` points of one section of an accumulator. `offset` is the index
+/// of the first point within its section, used when reporting invalid points.
+fn read_points<R: Read, C: CurvePoint>(
+    reader: &mut R,
+    size: usize,
+    compression: UseCompression,
+    checked: CheckForCorrectness,
+    section: &'static str,
+    offset: usize
+) -> Result<Vec<C>, DeserializationError>
+{
+    // Read the encoded elements
+    let mut elements = vec![C::zero(); size];
+
+    match compression {
+        UseCompression::No => {
+            for element in &mut elements {
+                *element = C::read_uncompressed_unchecked(reader)?;
             }
-        });
+        }
+        UseCompression::Yes => {
+            // Decompression always solves the curve equation,
+            // so that check cannot be skipped here.
+            for element in &mut elements {
+                *element = C::read_compressed(reader)?;
+            }
+        }
+    }
 
-        fn batch_exp<C: Group>(bases: &mut [C], exp: &[Fr], coeff: Option<&Fr>)
-        {
-            assert_eq!(bases.len(), exp.len());
-            let chunk_size = bases.len() / num_cpus::get();
+    match checked {
+        CheckForCorrectness::Yes => check_all(&elements, section, offset)?,
+        CheckForCorrectness::No => {}
+    }
 
-            // Perform exponentiation over multiple cores.
-            crossbeam::scope(|scope| {
-                for (bases, exp) in bases.chunks_mut(chunk_size)
-                    .zip(exp.chunks(chunk_size))
-                {
-                    scope.spawn(move || {
-                        for (base, exp) in bases.iter_mut().zip(exp.iter())
-                        {
-                            let final_exp = {
-                                if let Some(coeff) = coeff { exp.mul(*coeff) }
-                                else { *exp }
-                            };
-                            *base = base.mul(final_exp);
-                        }
-                    });
+    Ok(elements)
+}
+
+/// Computes tau^start, tau^(start + 1), ..., tau^(start + len - 1) over
+/// multiple cores, in secret memory as they reveal tau.
+fn tau_powers<F: ScalarField>(tau: &F, start: usize, len: usize) -> SecretVec<F>
+{
+    let mut taupowers = SecretVec::new(len, F::zero());
+    let chunk_size = (len / num_cpus::get()) + 1;
+
+    crossbeam::scope(|scope| {
+        for (i, taupowers) in taupowers.chunks_mut(chunk_size).enumerate() {
+            scope.spawn(move || {
+                let mut acc = tau.pow((start + (i * chunk_size)) as u64);
+                for t in taupowers {
+                    *t = acc;
+                    acc = acc * *tau;
                 }
             });
         }
+    });
 
-        let num_powers = self.config.num_powers;
-        batch_exp(&mut self.tau_powers_g1, &taupowers[0..], None);
-        batch_exp(&mut self.tau_powers_g2, &taupowers[0..num_powers], None);
-        batch_exp(&mut self.alpha_tau_powers_g1, &taupowers[0..num_powers], Some(&key.alpha));
-        batch_exp(&mut self.beta_tau_powers_g1, &taupowers[0..num_powers], Some(&key.beta));
-        self.beta_g2 = self.beta_g2.mul(key.beta);
-    }
+    taupowers
+}
+
+/// Multiplies `base` by the secret `scalar`, in constant time if the
+/// `constant-time` feature is enabled (see `constant_time`).
+#[cfg(feature = "constant-time")]
+fn secret_mul<C: WnafGroup>(base: &C, scalar: &C::Scalar, _params: &C::Params) -> C
+{
+    constant_time::mul(base, scalar)
+}
+
+#[cfg(not(feature = "constant-time"))]
+fn secret_mul<C: WnafGroup>(base: &C, scalar: &C::Scalar, params: &C::Params) -> C
+{
+    base.wnaf_mul(scalar, params)
+}
+
+/// Raises each of `bases` to the matching power in `exp`, multiplied by
+/// `coeff` if given.
+fn batch_exp<C: WnafGroup>(bases: &mut [C], exp: &[C::Scalar], coeff: Option<&C::Scalar>)
+{
+    assert_eq!(bases.len(), exp.len());
+    let chunk_size = (bases.len() / num_cpus::get()) + 1;
+    let params = C::wnaf_params();
+    let params = &params;
+
+    // Perform exponentiation over multiple cores.
+    crossbeam::scope(|scope| {
+        for (bases, exp) in bases.chunks_mut(chunk_size)
+            .zip(exp.chunks(chunk_size))
+        {
+            scope.spawn(move || {
+                for (base, exp) in bases.iter_mut().zip(exp.iter())
+                {
+                    let final_exp = {
+                        if let Some(coeff) = coeff { *exp * *coeff }
+                        else { *exp }
+                    };
+                    *base = secret_mul(base, &final_exp, params);
+                }
+            });
+        }
+    });
 }
 
 /// Verifies a transformation of the `Accumulator` with the `PublicKey`, given a 64-byte transcript `digest`.
-pub fn verify_transform(before: &Accumulator, after: &Accumulator, key: &PublicKey, digest: &[u8]) -> bool
+pub fn verify_transform<E: Engine>(
+    before: &Accumulator<E>,
+    after: &Accumulator<E>,
+    key: &PublicKey<E>,
+    digest: &[u8]
+) -> Result<(), VerificationError>
 {
-    assert_eq!(digest.len(), 64);
+    TransformSummary::new(before, after).verify(key, digest)
+}
 
-    let tau_g2_s = compute_g2_s(&key.tau_g1.0, &key.tau_g1.1, 0, digest);
-    let alpha_g2_s = compute_g2_s(&key.alpha_g1.0, &key.alpha_g1.1, 1, digest);
-    let beta_g2_s = compute_g2_s(&key.beta_g1.0, &key.beta_g1.1, 2, digest);
+/// The elements of a pair of accumulators that `verify_transform` depends on,
+/// with each vector of powers of the new accumulator reduced to a single pair
+/// by `power_pairs`. This allows the checks to be run on accumulators that
+/// are never held in memory in full (see `streaming`).
+pub struct TransformSummary<E: Engine> {
+    /// before.tau_powers_g1[1]
+    before_tau_g1: E::G1,
+    /// before.alpha_tau_powers_g1[0]
+    before_alpha_g1: E::G1,
+    /// before.beta_tau_powers_g1[0]
+    before_beta_g1: E::G1,
+    before_beta_g2: E::G2,
+    /// after.tau_powers_g1[0..2]
+    after_tau_g1: (E::G1, E::G1),
+    /// after.tau_powers_g2[0..2]
+    after_tau_g2: (E::G2, E::G2),
+    /// after.alpha_tau_powers_g1[0]
+    after_alpha_g1: E::G1,
+    /// after.beta_tau_powers_g1[0]
+    after_beta_g1: E::G1,
+    after_beta_g2: E::G2,
+    /// power_pairs(after.tau_powers_g1)
+    tau_powers_g1_pairs: (E::G1, E::G1),
+    /// power_pairs(after.tau_powers_g2)
+    tau_powers_g2_pairs: (E::G2, E::G2),
+    /// power_pairs(after.alpha_tau_powers_g1)
+    alpha_tau_powers_g1_pairs: (E::G1, E::G1),
+    /// power_pairs(after.beta_tau_powers_g1)
+    beta_tau_powers_g1_pairs: (E::G1, E::G1),
+}
 
-    // Check the proofs-of-knowledge for tau/alpha/beta
-    if !same_ratio(key.tau_g1, (tau_g2_s, key.tau_g2)) {
-        return false;
-    }
-    if !same_ratio(key.alpha_g1, (alpha_g2_s, key.alpha_g2)) {
-        return false;
-    }
-    if !same_ratio(key.beta_g1, (beta_g2_s, key.beta_g2)) {
-        return false;
+impl<E: Engine> TransformSummary<E> {
+    pub fn new(before: &Accumulator<E>, after: &Accumulator<E>) -> Self {
+        TransformSummary {
+            before_tau_g1: before.tau_powers_g1[1],
+            before_alpha_g1: before.alpha_tau_powers_g1[0],
+            before_beta_g1: before.beta_tau_powers_g1[0],
+            before_beta_g2: before.beta_g2,
+            after_tau_g1: (after.tau_powers_g1[0], after.tau_powers_g1[1]),
+            after_tau_g2: (after.tau_powers_g2[0], after.tau_powers_g2[1]),
+            after_alpha_g1: after.alpha_tau_powers_g1[0],
+            after_beta_g1: after.beta_tau_powers_g1[0],
+            after_beta_g2: after.beta_g2,
+            tau_powers_g1_pairs: power_pairs(&after.tau_powers_g1),
+            tau_powers_g2_pairs: power_pairs(&after.tau_powers_g2),
+            alpha_tau_powers_g1_pairs: power_pairs(&after.alpha_tau_powers_g1),
+            beta_tau_powers_g1_pairs: power_pairs(&after.beta_tau_powers_g1),
+        }
     }
 
-    // Check the correctness of the generators for tau powers
-    if after.tau_powers_g1[0] != G1::one() {
-        return false;
-    }
-    if after.tau_powers_g2[0] != G2::one() {
-        return false;
+    /// Joins the summaries of two accumulators, each of the transformation
+    /// from the accumulator to itself, into that of the transformation from
+    /// `before` to `after`.
+    pub fn chain(before: &Self, after: &Self) -> Self {
+        TransformSummary {
+            before_tau_g1: before.after_tau_g1.1,
+            before_alpha_g1: before.after_alpha_g1,
+            before_beta_g1: before.after_beta_g1,
+            before_beta_g2: before.after_beta_g2,
+            after_tau_g1: after.after_tau_g1,
+            after_tau_g2: after.after_tau_g2,
+            after_alpha_g1: after.after_alpha_g1,
+            after_beta_g1: after.after_beta_g1,
+            after_beta_g2: after.after_beta_g2,
+            tau_powers_g1_pairs: after.tau_powers_g1_pairs,
+            tau_powers_g2_pairs: after.tau_powers_g2_pairs,
+            alpha_tau_powers_g1_pairs: after.alpha_tau_powers_g1_pairs,
+            beta_tau_powers_g1_pairs: after.beta_tau_powers_g1_pairs,
+        }
     }
 
-    // Did the participant multiply the previous tau by the new one?
-    if !same_ratio((before.tau_powers_g1[1], after.tau_powers_g1[1]), (tau_g2_s, key.tau_g2)) {
-        return false;
-    }
+    /// Checks the parts of `verify_transform` that concern the new
+    /// accumulator alone, as `Accumulator::verify_consistency` does.
+    pub fn verify_consistency(&self) -> Result<(), VerificationError>
+    {
+        if self.after_tau_g1.0 != E::G1::one() {
+            return Err(VerificationError::TauG1GeneratorNotOne);
+        }
+        if self.after_tau_g2.0 != E::G2::one() {
+            return Err(VerificationError::TauG2GeneratorNotOne);
+        }
+        if !same_ratio::<E>((E::G1::one(), self.after_beta_g1), (E::G2::one(), self.after_beta_g2)) {
+            return Err(VerificationError::BetaG2Ratio);
+        }
 
-    // Did the participant multiply the previous alpha by the new one?
-    if !same_ratio((before.alpha_tau_powers_g1[0], after.alpha_tau_powers_g1[0]), (alpha_g2_s, key.alpha_g2)) {
-        return false;
+        self.verify_powers()
     }
 
-    // Did the participant multiply the previous beta by the new one?
-    if !same_ratio((before.beta_tau_powers_g1[0], after.beta_tau_powers_g1[0]), (beta_g2_s, key.beta_g2)) {
-        return false;
-    }
-    if !same_ratio((before.beta_tau_powers_g1[0], after.beta_tau_powers_g1[0]), (before.beta_g2, after.beta_g2)) {
-        return false;
-    }
+    /// Verifies the transformation with the `PublicKey`, given a 64-byte transcript `digest`.
+    pub fn verify(&self, key: &PublicKey<E>, digest: &[u8]) -> Result<(), VerificationError>
+    {
+        assert_eq!(digest.len(), 64);
 
-    // Are the powers of tau correct?
-    if !same_ratio(power_pairs(&after.tau_powers_g1), (after.tau_powers_g2[0], after.tau_powers_g2[1])) {
-        return false;
-    }
-    if !same_ratio(
-        (after.tau_powers_g1[0], after.tau_powers_g1[1]),
-        power_pairs(&after.tau_powers_g2)) {
-        return false;
-    }
-    if !same_ratio(power_pairs(&after.alpha_tau_powers_g1), (after.tau_powers_g2[0], after.tau_powers_g2[1])) {
-        return false;
-    }
-    if !same_ratio(power_pairs(&after.beta_tau_powers_g1), (after.tau_powers_g2[0], after.tau_powers_g2[1])) {
-        return false;
+        let tau_g2_s = compute_g2_s::<E>(&key.tau_g1.0, &key.tau_g1.1, 0, digest);
+        let alpha_g2_s = compute_g2_s::<E>(&key.alpha_g1.0, &key.alpha_g1.1, 1, digest);
+        let beta_g2_s = compute_g2_s::<E>(&key.beta_g1.0, &key.beta_g1.1, 2, digest);
+
+        // Check the proofs-of-knowledge for tau/alpha/beta
+        if !same_ratio::<E>(key.tau_g1, (tau_g2_s, key.tau_g2)) {
+            return Err(VerificationError::TauProofOfKnowledge);
+        }
+        if !same_ratio::<E>(key.alpha_g1, (alpha_g2_s, key.alpha_g2)) {
+            return Err(VerificationError::AlphaProofOfKnowledge);
+        }
+        if !same_ratio::<E>(key.beta_g1, (beta_g2_s, key.beta_g2)) {
+            return Err(VerificationError::BetaProofOfKnowledge);
+        }
+
+        // Check the correctness of the generators for tau powers
+        if self.after_tau_g1.0 != E::G1::one() {
+            return Err(VerificationError::TauG1GeneratorNotOne);
+        }
+        if self.after_tau_g2.0 != E::G2::one() {
+            return Err(VerificationError::TauG2GeneratorNotOne);
+        }
+
+        // Did the participant multiply the previous tau by the new one?
+        if !same_ratio::<E>((self.before_tau_g1, self.after_tau_g1.1), (tau_g2_s, key.tau_g2)) {
+            return Err(VerificationError::TauRatio);
+        }
+
+        // Did the participant multiply the previous alpha by the new one?
+        if !same_ratio::<E>((self.before_alpha_g1, self.after_alpha_g1), (alpha_g2_s, key.alpha_g2)) {
+            return Err(VerificationError::AlphaRatio);
+        }
+
+        // Did the participant multiply the previous beta by the new one?
+        if !same_ratio::<E>((self.before_beta_g1, self.after_beta_g1), (beta_g2_s, key.beta_g2)) {
+            return Err(VerificationError::BetaRatio);
+        }
+        if !same_ratio::<E>((self.before_beta_g1, self.after_beta_g1), (self.before_beta_g2, self.after_beta_g2)) {
+            return Err(VerificationError::BetaG2Ratio);
+        }
+
+        self.verify_powers()
     }
 
-    true
+    /// Checks that each vector of the new accumulator holds consecutive powers
+    /// of its tau.
+    fn verify_powers(&self) -> Result<(), VerificationError>
+    {
+        // Are the powers of tau correct?
+        if !same_ratio::<E>(self.tau_powers_g1_pairs, self.after_tau_g2) {
+            return Err(VerificationError::TauPowersG1);
+        }
+        if !same_ratio::<E>(self.after_tau_g1, self.tau_powers_g2_pairs) {
+            return Err(VerificationError::TauPowersG2);
+        }
+        if !same_ratio::<E>(self.alpha_tau_powers_g1_pairs, self.after_tau_g2) {
+            return Err(VerificationError::AlphaTauPowersG1);
+        }
+        if !same_ratio::<E>(self.beta_tau_powers_g1_pairs, self.after_tau_g2) {
+            return Err(VerificationError::BetaTauPowersG1);
+        }
+
+        Ok(())
+    }
 }
 
 /// Computes a random linear combination over v1/v2.
@@ -596,58 +934,24 @@ pub fn verify_transform(before: &Accumulator, after: &Accumulator, key: &PublicK
 /// e(g, (as)*r1 + (bs)*r2 + (cs)*r3) = e(g^s, a*r1 + b*r2 + c*r3)
 ///
 /// ... with high probability.
-fn merge_pairs<G: Group>(v1: &[G], v2: &[G]) -> (G, G)
+fn merge_pairs<G: CurvePoint>(v1: &[G], v2: &[G]) -> (G, G)
 {
-    use rand::{thread_rng};
-    use std::sync::{Arc, Mutex};
+    use rand::{thread_rng, Rng};
 
     assert_eq!(v1.len(), v2.len());
 
-    // TODO: Multi-thread this
-
-    // TODO: Use wNAF and multi-threading
-
-    let chunk = (v1.len() / num_cpus::get()) + 1;
-    let s = Arc::new(Mutex::new(G::zero()));
-    let sx = Arc::new(Mutex::new(G::zero()));
-
-    crossbeam::scope(|scope| {
-        for (v1, v2) in v1.chunks(chunk).zip(v2.chunks(chunk)) {
-            let s = s.clone();
-            let sx = sx.clone();
-
-            scope.spawn(move || {
-                // We do not need to be overly cautious of the RNG
-                // used for this check.
-                let rng = &mut thread_rng();
-
-                let mut local_s = G::zero();
-                let mut local_sx = G::zero();
-
-                for (v1, v2) in v1.iter().zip(v2.iter()) {
-                    let rho = Fr::random(rng);
-                    local_s = local_s.add(v1.mul(rho));
-                    local_sx = local_sx.add(v2.mul(rho));
-
-                }
-
-                let mut s_ref = s.lock().unwrap();
-                let mut sx_ref = sx.lock().unwrap();
-                *s_ref = (*s_ref).add(local_s);
-                *sx_ref = (*sx_ref).add(local_sx);
-            });
-        }
-    });
-
-    let s = s.lock().unwrap();
-    let sx = sx.lock().unwrap();
+    // We do not need to be overly cautious of the RNG
+    // used for this check, and 128-bit coefficients are
+    // sufficient for soundness.
+    let rng = &mut thread_rng();
+    let rho = (0..v1.len()).map(|_| rng.gen::<u128>()).collect::<Vec<_>>();
 
-    (*s, *sx)
+    (multiexp(v1, &rho), multiexp(v2, &rho))
 }
 
 /// Construct a single pair (s, s^x) for a vector of
 /// the form [1, x, x^2, x^3, ...].
-fn power_pairs<G: Group>(v: &[G]) -> (G, G)
+fn power_pairs<G: CurvePoint>(v: &[G]) -> (G, G)
 {
     merge_pairs(&v[0..(v.len()-1)], &v[1..])
 }
@@ -656,90 +960,241 @@ fn power_pairs<G: Group>(v: &[G]) -> (G, G)
 fn test_power_pairs() {
     use rand::thread_rng;
 
-    let rng = &mut thread_rng();
+    fn test_engine<E: Engine>() {
+        let rng = &mut thread_rng();
 
-    let mut v = vec![];
-    let x = Fr::random(rng);
-    let mut acc = Fr::one();
-    for _ in 0..100 {
-        v.push(G1::one().mul(acc));
-        acc = acc.mul(x);
-    }
+        let mut v = vec![];
+        let x = E::Fr::random(rng);
+        let mut acc = E::Fr::one();
+        for _ in 0..100 {
+            v.push(E::G1::one().mul(&acc));
+            acc = acc * x;
+        }
 
-    let gx = G2::one().mul(x);
+        let gx = E::G2::one().mul(&x);
 
-    assert!(same_ratio(power_pairs(&v), (G2::one(), gx)));
+        assert!(same_ratio::<E>(power_pairs(&v), (E::G2::one(), gx)));
 
-    v[1] = v[1].mul(Fr::random(rng));
+        v[1] = v[1].mul(&E::Fr::random(rng));
 
-    assert!(!same_ratio(power_pairs(&v), (G2::one(), gx)));
+        assert!(!same_ratio::<E>(power_pairs(&v), (E::G2::one(), gx)));
+    }
+
+    test_engine::<bn256::Bn256>();
+    test_engine::<bls12::Bls12>();
 }
 
 /// Checks if pairs have the same ratio.
-fn same_ratio(
-    g1: (G1, G1),
-    g2: (G2, G2)
+fn same_ratio<E: Engine>(
+    g1: (E::G1, E::G1),
+    g2: (E::G2, E::G2)
 ) -> bool
 {
-    bn::pairing(g1.0, g2.1) == bn::pairing(g1.1, g2.0)
+    E::pairing(&g1.0, &g2.1) == E::pairing(&g1.1, &g2.0)
 }
 
 #[test]
 fn test_same_ratio() {
     use rand::thread_rng;
 
-    let rng = &mut thread_rng();
+    fn test_engine<E: Engine>() {
+        let rng = &mut thread_rng();
+
+        let s = E::Fr::random(rng);
+        let g1 = E::G1::one();
+        let g2 = E::G2::one();
+        let g1_s = g1.mul(&s);
+        let g2_s = g2.mul(&s);
 
-    let s = Fr::random(rng);
-    let g1 = G1::one();
-    let g2 = G2::one();
-    let g1_s = g1.mul(s);
-    let g2_s = g2.mul(s);
+        assert!(same_ratio::<E>((g1, g1_s), (g2, g2_s)));
+        assert!(!same_ratio::<E>((g1_s, g1), (g2, g2_s)));
+    }
 
-    assert!(same_ratio((g1, g1_s), (g2, g2_s)));
-    assert!(!same_ratio((g1_s, g1), (g2, g2_s)));
+    test_engine::<bn256::Bn256>();
+    test_engine::<bls12::Bls12>();
 }
 
 #[test]
 fn test_accumulator_serialization() {
     use rand::thread_rng;
 
-    let config = Configuration::new(256);
-    let rng = &mut thread_rng();
-    let mut digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+    fn test_engine<E: Engine>() {
+        let config = Configuration::for_engine::<E>(256);
+        let rng = &mut thread_rng();
+        let mut digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+        let mut acc = Accumulator::<E>::new(config);
+        let before = acc.clone();
+        let (pk, sk) = keypair(rng, &digest);
+        acc.transform(&sk);
+        assert!(verify_transform(&before, &acc, &pk, &digest).is_ok());
+        digest[0] = !digest[0];
+        assert_eq!(
+            verify_transform(&before, &acc, &pk, &digest),
+            Err(VerificationError::TauProofOfKnowledge));
 
-    let mut acc = Accumulator::new(config);
-    let before = acc.clone();
-    let (pk, sk) = keypair(rng, &digest);
-    acc.transform(&sk);
-    assert!(verify_transform(&before, &acc, &pk, &digest));
-    digest[0] = !digest[0];
-    assert!(!verify_transform(&before, &acc, &pk, &digest));
+        {
+            let mut v = Vec::with_capacity(config.accumulator_size_bytes - 64);
+            acc.serialize(&mut v, UseCompression::No).unwrap();
+            assert_eq!(v.len(), config.accumulator_size_bytes - 64);
+            let deserialized = Accumulator::deserialize(
+                config,
+                &mut &v[..],
+                UseCompression::No,
+                CheckForCorrectness::No).unwrap();
+            assert!(acc == deserialized);
+        }
 
-    {
-        let mut v = Vec::with_capacity(config.accumulator_size_bytes - 64);
-        acc.serialize(&mut v, UseCompression::No).unwrap();
-        assert_eq!(v.len(), config.accumulator_size_bytes - 64);
-        let deserialized = Accumulator::deserialize(
-            config,
-            &mut &v[..],
-            UseCompression::No,
-            CheckForCorrectness::No).unwrap();
-        assert!(acc == deserialized);
+        {
+            let expect_size = config.contribution_size_bytes - 64 - public_key_size::<E>();
+            let mut v = Vec::with_capacity(expect_size);
+            acc.serialize(&mut v, UseCompression::Yes).unwrap();
+            assert_eq!(expect_size, v.len());
+            let deserialized = Accumulator::deserialize(
+                config,
+                &mut &v[..],
+                UseCompression::Yes,
+                CheckForCorrectness::No).unwrap();
+            assert!(acc == deserialized);
+        }
     }
 
-    {
-        let expect_size = config.contribution_size_bytes - 64 - PUBLIC_KEY_SIZE;
-        let mut v = Vec::with_capacity(expect_size);
-        acc.serialize(&mut v, UseCompression::Yes).unwrap();
-        assert_eq!(expect_size, v.len());
-        let deserialized = Accumulator::deserialize(
-            config,
-            &mut &v[..],
-            UseCompression::Yes,
-            CheckForCorrectness::No).unwrap();
-        assert!(acc == deserialized);
+    test_engine::<bn256::Bn256>();
+    test_engine::<bls12::Bls12>();
+}
+
+#[test]
+fn test_verification_errors() {
+    use rand::thread_rng;
+
+    fn test_engine<E: Engine>() {
+        let config = Configuration::for_engine::<E>(16);
+        let rng = &mut thread_rng();
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+        let before = Accumulator::<E>::new(config);
+        let mut after = before.clone();
+        let (pk, sk) = keypair(rng, &digest);
+        after.transform(&sk);
+        assert!(verify_transform(&before, &after, &pk, &digest).is_ok());
+
+        let two = E::Fr::from_u64(2);
+
+        let mut acc = after.clone();
+        acc.alpha_tau_powers_g1[0] = acc.alpha_tau_powers_g1[0].mul(&two);
+        assert_eq!(verify_transform(&before, &acc, &pk, &digest), Err(VerificationError::AlphaRatio));
+
+        let mut acc = after.clone();
+        acc.beta_g2 = acc.beta_g2.mul(&two);
+        assert_eq!(verify_transform(&before, &acc, &pk, &digest), Err(VerificationError::BetaG2Ratio));
+
+        let mut acc = after.clone();
+        acc.tau_powers_g1[5] = acc.tau_powers_g1[5].mul(&two);
+        assert_eq!(verify_transform(&before, &acc, &pk, &digest), Err(VerificationError::TauPowersG1));
+
+        let mut acc = after.clone();
+        acc.tau_powers_g2[0] = acc.tau_powers_g2[0].mul(&two);
+        assert_eq!(verify_transform(&before, &acc, &pk, &digest), Err(VerificationError::TauG2GeneratorNotOne));
     }
+
+    test_engine::<bn256::Bn256>();
+    test_engine::<bls12::Bls12>();
+}
+
+#[test]
+fn test_verify_consistency() {
+    use rand::thread_rng;
+
+    fn test_engine<E: Engine>() {
+        let config = Configuration::for_engine::<E>(16);
+        let rng = &mut thread_rng();
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+        let mut acc = Accumulator::<E>::new(config);
+        assert!(acc.verify_consistency().is_ok());
+        let (_, sk) = keypair(rng, &digest);
+        acc.transform(&sk);
+        assert!(acc.verify_consistency().is_ok());
+
+        let two = E::Fr::from_u64(2);
+
+        let mut bad = acc.clone();
+        bad.beta_g2 = bad.beta_g2.mul(&two);
+        assert_eq!(bad.verify_consistency(), Err(VerificationError::BetaG2Ratio));
+
+        let mut bad = acc.clone();
+        bad.alpha_tau_powers_g1[7] = bad.alpha_tau_powers_g1[7].mul(&two);
+        assert_eq!(bad.verify_consistency(), Err(VerificationError::AlphaTauPowersG1));
+
+        let mut bad = acc.clone();
+        bad.tau_powers_g1[0] = bad.tau_powers_g1[1];
+        assert_eq!(bad.verify_consistency(), Err(VerificationError::TauG1GeneratorNotOne));
+    }
+
+    test_engine::<bn256::Bn256>();
+    test_engine::<bls12::Bls12>();
+}
+
+#[test]
+fn test_accumulator_point_checks() {
+    use rand::thread_rng;
+
+    fn test_engine<E: Engine>() {
+        let config = Configuration::for_engine::<E>(16);
+        let rng = &mut thread_rng();
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+        let mut acc = Accumulator::<E>::new(config);
+        let (_, sk) = keypair(rng, &digest);
+        acc.transform(&sk);
+
+        let deserialize = |v: &[u8], compression, checked| {
+            Accumulator::<E>::deserialize(config, &mut &v[..], compression, checked)
+        };
+
+        // Valid accumulators pass the checks in both encodings.
+        for &compression in &[UseCompression::No, UseCompression::Yes] {
+            let mut v = vec![];
+            acc.serialize(&mut v, compression).unwrap();
+            assert!(acc == deserialize(&v, compression, CheckForCorrectness::Yes).unwrap());
+        }
+
+        // The point at infinity is only accepted when unchecked.
+        {
+            let mut bad = acc.clone();
+            bad.alpha_tau_powers_g1[3] = E::G1::zero();
+            let mut v = vec![];
+            bad.serialize(&mut v, UseCompression::No).unwrap();
+            assert!(bad == deserialize(&v, UseCompression::No, CheckForCorrectness::No).unwrap());
+            match deserialize(&v, UseCompression::No, CheckForCorrectness::Yes) {
+                Err(DeserializationError::InvalidPoint(section, index, e)) => {
+                    assert_eq!(section, "alpha_tau_powers_g1");
+                    assert_eq!(index, 3);
+                    assert_eq!(e, PointError::PointAtInfinity);
+                }
+                _ => panic!("expected the point at infinity to be rejected")
+            }
+        }
+
+        // Flip the low bit of the y coordinate of tau_powers_g1[2].
+        {
+            let mut v = vec![];
+            acc.serialize(&mut v, UseCompression::No).unwrap();
+            v[(3 * E::G1::UNCOMPRESSED_SIZE) - 1] ^= 1;
+            assert!(deserialize(&v, UseCompression::No, CheckForCorrectness::No).is_ok());
+            match deserialize(&v, UseCompression::No, CheckForCorrectness::Yes) {
+                Err(DeserializationError::InvalidPoint(section, index, e)) => {
+                    assert_eq!(section, "tau_powers_g1");
+                    assert_eq!(index, 2);
+                    assert_eq!(e, PointError::NotOnCurve);
+                }
+                _ => panic!("expected a point off the curve to be rejected")
+            }
+        }
+    }
+
+    test_engine::<bn256::Bn256>();
+    test_engine::<bls12::Bls12>();
 }
 
 /// Compute BLAKE2b("")
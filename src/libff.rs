@@ -0,0 +1,405 @@
+//! Export of the BN256 accumulator, and optionally its Lagrange
+//! coefficients, in the serialization of libff's `alt_bn128` points, for
+//! provers built on libsnark.
+//!
+//! libff is built with or without `BINARY_OUTPUT`, `MONTGOMERY_OUTPUT` and
+//! point compression, and reads only what it writes, so `LibffFormat` must
+//! match the build of the reader. Each point is written as
+//!
+//! * `1` if it is the point at infinity, else `0`, as a character
+//! * x in affine coordinates (the real part first in G2)
+//! * y, or with compression the least significant bit of y (of its real part
+//!   in G2) as a character
+//!
+//! where the point at infinity has x = 0 and y = 1. Coordinates are decimal
+//! integers, or with `BINARY_OUTPUT` 32-byte little-endian integers, and with
+//! `MONTGOMERY_OUTPUT` are multiplied by 2<sup>256</sup> mod q. Without
+//! `BINARY_OUTPUT`, the parts of a point are separated by a space and each
+//! point is followed by a newline. A vector of points is preceded by its
+//! length in decimal and a newline.
+//!
+//! The accumulator is written as the vectors `tau_powers_g1`,
+//! `tau_powers_g2`, `alpha_tau_powers_g1` and `beta_tau_powers_g1`, then
+//! `beta_g2`. The Lagrange coefficients are written as `alpha_g1`, `beta_g1`
+//! and `beta_g2`, then the vectors `coeffs_g1`, `coeffs_g2`,
+//! `alpha_coeffs_g1`, `beta_coeffs_g1` and `h`.
+
+use bn::{AffineG1, AffineG2, Fq, Fq2, G1, G2};
+use std::io::{self, Write};
+
+use bn256::{montgomery_r, Bn256};
+use lagrange::LagrangeCoefficients;
+use super::Accumulator;
+
+const FQ_SIZE: usize = 32;
+
+/// The libff build options that affect serialization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LibffFormat {
+    /// `BINARY_OUTPUT`
+    pub binary: bool,
+    /// `MONTGOMERY_OUTPUT`
+    pub montgomery: bool,
+    /// `USE_PT_COMPRESSION`
+    pub compressed: bool,
+}
+
+impl Default for LibffFormat {
+    /// The defaults of libff's build.
+    fn default() -> Self
+    {
+        LibffFormat {
+            binary: true,
+            montgomery: true,
+            compressed: true,
+        }
+    }
+}
+
+struct LibffWriter<'a, W: Write + 'a> {
+    writer: &'a mut W,
+    format: LibffFormat,
+    r: Fq,
+}
+
+impl<'a, W: Write> LibffWriter<'a, W> {
+    fn new(writer: &'a mut W, format: LibffFormat) -> Self
+    {
+        LibffWriter {
+            writer: writer,
+            format: format,
+            r: montgomery_r(),
+        }
+    }
+
+    fn separator(&mut self) -> io::Result<()>
+    {
+        if self.format.binary { Ok(()) } else { self.writer.write_all(b" ") }
+    }
+
+    fn newline(&mut self) -> io::Result<()>
+    {
+        if self.format.binary { Ok(()) } else { self.writer.write_all(b"\n") }
+    }
+
+    fn flag(&mut self, flag: bool) -> io::Result<()>
+    {
+        self.writer.write_all(if flag { b"1" } else { b"0" })
+    }
+
+    fn fq(&mut self, x: Fq) -> io::Result<()>
+    {
+        let x = if self.format.montgomery { x * self.r } else { x };
+        let mut bytes = fq_to_big_endian(x);
+        if self.format.binary {
+            bytes.reverse();
+            self.writer.write_all(&bytes)
+        } else {
+            self.writer.write_all(to_decimal(&bytes).as_bytes())
+        }
+    }
+
+    fn fq2(&mut self, x: Fq2) -> io::Result<()>
+    {
+        self.fq(x.real())?;
+        self.separator()?;
+        self.fq(x.imaginary())
+    }
+
+    fn g1(&mut self, p: &G1) -> io::Result<()>
+    {
+        let (is_zero, x, y) = match AffineG1::from_jacobian(*p) {
+            Some(p) => (false, p.x(), p.y()),
+            None => (true, Fq::zero(), Fq::one()),
+        };
+
+        self.flag(is_zero)?;
+        self.separator()?;
+        self.fq(x)?;
+        self.separator()?;
+        if self.format.compressed {
+            self.flag(is_odd(y))?;
+        } else {
+            self.fq(y)?;
+        }
+        self.newline()
+    }
+
+    fn g2(&mut self, p: &G2) -> io::Result<()>
+    {
+        let (is_zero, x, y) = match AffineG2::from_jacobian(*p) {
+            Some(p) => (false, p.x(), p.y()),
+            None => (true, Fq2::zero(), Fq2::one()),
+        };
+
+        self.flag(is_zero)?;
+        self.separator()?;
+        self.fq2(x)?;
+        self.separator()?;
+        if self.format.compressed {
+            self.flag(is_odd(y.real()))?;
+        } else {
+            self.fq2(y)?;
+        }
+        self.newline()
+    }
+
+    fn g1_vec(&mut self, v: &[G1]) -> io::Result<()>
+    {
+        writeln!(self.writer, "{}", v.len())?;
+        for p in v {
+            self.g1(p)?;
+        }
+        Ok(())
+    }
+
+    fn g2_vec(&mut self, v: &[G2]) -> io::Result<()>
+    {
+        writeln!(self.writer, "{}", v.len())?;
+        for p in v {
+            self.g2(p)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write `acc`, which should have been verified, in libff's serialization.
+pub fn write_accumulator<W: Write>(
+    writer: &mut W,
+    acc: &Accumulator<Bn256>,
+    format: LibffFormat
+) -> io::Result<()>
+{
+    let mut w = LibffWriter::new(writer, format);
+    w.g1_vec(&acc.tau_powers_g1)?;
+    w.g2_vec(&acc.tau_powers_g2)?;
+    w.g1_vec(&acc.alpha_tau_powers_g1)?;
+    w.g1_vec(&acc.beta_tau_powers_g1)?;
+    w.g2(&acc.beta_g2)
+}
+
+/// Write the Lagrange coefficients in libff's serialization.
+pub fn write_lagrange<W: Write>(
+    writer: &mut W,
+    lagrange: &LagrangeCoefficients<Bn256>,
+    format: LibffFormat
+) -> io::Result<()>
+{
+    let mut w = LibffWriter::new(writer, format);
+    w.g1(&lagrange.alpha_g1)?;
+    w.g1(&lagrange.beta_g1)?;
+    w.g2(&lagrange.beta_g2)?;
+    w.g1_vec(&lagrange.coeffs_g1)?;
+    w.g2_vec(&lagrange.coeffs_g2)?;
+    w.g1_vec(&lagrange.alpha_coeffs_g1)?;
+    w.g1_vec(&lagrange.beta_coeffs_g1)?;
+    w.g1_vec(&lagrange.h)
+}
+
+fn fq_to_big_endian(x: Fq) -> [u8; FQ_SIZE]
+{
+    let mut bytes = [0u8; FQ_SIZE];
+    x.into_u256().to_big_endian(&mut bytes).expect("32 bytes is enough for Fq");
+    bytes
+}
+
+fn is_odd(x: Fq) -> bool
+{
+    fq_to_big_endian(x)[FQ_SIZE - 1] & 1 == 1
+}
+
+/// Formats a big-endian integer in decimal.
+fn to_decimal(bytes: &[u8]) -> String
+{
+    let mut n = bytes.to_vec();
+    let mut digits = vec![];
+
+    while n.iter().any(|&b| b != 0) {
+        // Divide by 10 in place, keeping the remainder.
+        let mut rem = 0u32;
+        for b in n.iter_mut() {
+            let v = (rem << 8) | (*b as u32);
+            *b = (v / 10) as u8;
+            rem = v % 10;
+        }
+        digits.push(b'0' + rem as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+
+    digits.reverse();
+    String::from_utf8(digits).expect("digits are ASCII")
+}
+
+#[test]
+fn test_to_decimal() {
+    assert_eq!(to_decimal(&[0; 32]), "0");
+    assert_eq!(to_decimal(&[1, 0]), "256");
+    assert_eq!(to_decimal(&fq_to_big_endian(montgomery_r())),
+        "6350874878119819312338956282401532409788428879151445726012394534686998597021");
+}
+
+#[test]
+fn test_libff_accumulator() {
+    use rand::{thread_rng, Rng};
+    use configuration::*;
+    use engine::CurvePoint;
+    use super::keypair;
+
+    /// Reads points back following the rules in the module documentation,
+    /// independently of `LibffWriter`.
+    struct Reader<'a> {
+        data: &'a [u8],
+        format: LibffFormat,
+    }
+
+    impl<'a> Reader<'a> {
+        fn take(&mut self, n: usize) -> &'a [u8] {
+            let (head, tail) = self.data.split_at(n);
+            self.data = tail;
+            head
+        }
+
+        /// A whitespace-delimited token in text mode.
+        fn token(&mut self) -> &'a str {
+            while self.data[0] == b' ' || self.data[0] == b'\n' {
+                self.take(1);
+            }
+            let len = self.data.iter().position(|&b| b == b' ' || b == b'\n').unwrap_or(self.data.len());
+            ::std::str::from_utf8(self.take(len)).unwrap()
+        }
+
+        fn flag(&mut self) -> bool {
+            let flag = if self.format.binary { self.take(1) } else { self.token().as_bytes() };
+            assert_eq!(flag.len(), 1);
+            match flag[0] {
+                b'0' => false,
+                b'1' => true,
+                _ => panic!("invalid flag")
+            }
+        }
+
+        fn fq(&mut self) -> Fq {
+            let x = if self.format.binary {
+                let mut bytes = self.take(FQ_SIZE).to_vec();
+                bytes.reverse();
+                Fq::from_slice(&bytes).unwrap()
+            } else {
+                Fq::from_str(self.token()).unwrap()
+            };
+            if self.format.montgomery { x * montgomery_r().inverse().unwrap() } else { x }
+        }
+
+        fn g1(&mut self) -> G1 {
+            let is_zero = self.flag();
+            let x = self.fq();
+            let y = if self.format.compressed {
+                let odd = self.flag();
+                let y = (x * x * x + G1::b()).sqrt().unwrap_or(Fq::one());
+                if is_odd(y) == odd { y } else { -y }
+            } else {
+                self.fq()
+            };
+
+            if is_zero {
+                assert!(x == Fq::zero() && y == Fq::one());
+                G1::zero()
+            } else {
+                G1::new(x, y, Fq::one())
+            }
+        }
+
+        fn g2(&mut self) -> G2 {
+            let is_zero = self.flag();
+            let x = Fq2::new(self.fq(), self.fq());
+            let y = if self.format.compressed {
+                let odd = self.flag();
+                let y = (x * x * x + G2::b()).sqrt().unwrap_or(Fq2::one());
+                if is_odd(y.real()) == odd { y } else { -y }
+            } else {
+                Fq2::new(self.fq(), self.fq())
+            };
+
+            if is_zero {
+                assert!(x == Fq2::zero() && y == Fq2::one());
+                G2::zero()
+            } else {
+                G2::new(x, y, Fq2::one())
+            }
+        }
+
+        fn len(&mut self) -> usize {
+            while !self.format.binary && self.data[0] == b'\n' {
+                self.take(1);
+            }
+            let len = self.data.iter().position(|&b| b == b'\n').unwrap();
+            let len = ::std::str::from_utf8(self.take(len)).unwrap().parse().unwrap();
+            self.take(1);
+            len
+        }
+
+        fn g1_vec(&mut self) -> Vec<G1> {
+            let len = self.len();
+            (0..len).map(|_| self.g1()).collect()
+        }
+
+        fn g2_vec(&mut self) -> Vec<G2> {
+            let len = self.len();
+            (0..len).map(|_| self.g2()).collect()
+        }
+    }
+
+    let config = Configuration::for_engine::<Bn256>(16);
+    let rng = &mut thread_rng();
+    let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+    let mut acc = Accumulator::<Bn256>::new(config);
+    let (_, sk) = keypair::<Bn256, _>(rng, &digest);
+    acc.transform(&sk);
+    let lagrange = LagrangeCoefficients::new(&acc, 4);
+
+    for i in 0..8 {
+        let format = LibffFormat {
+            binary: i & 1 != 0,
+            montgomery: i & 2 != 0,
+            compressed: i & 4 != 0,
+        };
+
+        let mut data = vec![];
+        write_accumulator(&mut data, &acc, format).unwrap();
+        write_lagrange(&mut data, &lagrange, format).unwrap();
+
+        let mut reader = Reader { data: &data, format: format };
+        assert!(reader.g1_vec() == acc.tau_powers_g1);
+        assert!(reader.g2_vec() == acc.tau_powers_g2);
+        assert!(reader.g1_vec() == acc.alpha_tau_powers_g1);
+        assert!(reader.g1_vec() == acc.beta_tau_powers_g1);
+        assert!(reader.g2() == acc.beta_g2);
+
+        assert!(reader.g1() == lagrange.alpha_g1);
+        assert!(reader.g1() == lagrange.beta_g1);
+        assert!(reader.g2() == lagrange.beta_g2);
+        assert!(reader.g1_vec() == lagrange.coeffs_g1);
+        assert!(reader.g2_vec() == lagrange.coeffs_g2);
+        assert!(reader.g1_vec() == lagrange.alpha_coeffs_g1);
+        assert!(reader.g1_vec() == lagrange.beta_coeffs_g1);
+        assert!(reader.g1_vec() == lagrange.h);
+        assert!(reader.data.iter().all(|&b| b == b'\n'));
+    }
+
+    // The generator of G1 is (1, 2), and the point at infinity is written
+    // as (0, 1).
+    let text = LibffFormat { binary: false, montgomery: false, compressed: false };
+    let mut data = vec![];
+    {
+        let mut w = LibffWriter::new(&mut data, text);
+        w.g1(&G1::one()).unwrap();
+        w.g1(&G1::zero()).unwrap();
+    }
+    assert_eq!(&data[..], &b"0 1 2\n1 0 1\n"[..]);
+
+    let mut data = vec![];
+    LibffWriter::new(&mut data, LibffFormat { compressed: true, ..text }).g1(&-G1::one()).unwrap();
+    assert_eq!(&data[..], &b"0 1 1\n"[..]);
+}
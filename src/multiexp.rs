@@ -0,0 +1,119 @@
+//! Multi-scalar multiplication using the bucket method of Pippenger, for
+//! computing random linear combinations of many group elements at once.
+
+use std::ops::*;
+use crossbeam;
+use num_cpus;
+
+use engine::CurvePoint;
+
+/// Number of bits in the scalars accepted by `multiexp`.
+pub const SCALAR_BITS: usize = 128;
+
+/// Computes Σ scalars<sub>i</sub> · bases<sub>i</sub> over multiple cores.
+pub fn multiexp<G: CurvePoint>(bases: &[G], scalars: &[u128]) -> G
+{
+    assert_eq!(bases.len(), scalars.len());
+
+    let chunk_size = (bases.len() / num_cpus::get()) + 1;
+
+    crossbeam::scope(|scope| {
+        let mut handles = vec![];
+
+        for (bases, scalars) in bases.chunks(chunk_size).zip(scalars.chunks(chunk_size)) {
+            handles.push(scope.spawn(move || serial_multiexp(bases, scalars)));
+        }
+
+        handles.into_iter().fold(G::zero(), |acc, h| acc.add(h.join()))
+    })
+}
+
+/// Window size (in bits) minimising the number of group additions for `n`
+/// terms.
+fn window_size(n: usize) -> usize
+{
+    if n < 32 {
+        3
+    } else {
+        (n as f64).ln().ceil() as usize
+    }
+}
+
+fn serial_multiexp<G: CurvePoint>(bases: &[G], scalars: &[u128]) -> G
+{
+    let c = window_size(bases.len());
+    let mask = (1u128 << c) - 1;
+    let num_windows = (SCALAR_BITS + c - 1) / c;
+
+    let mut acc = G::zero();
+    let mut buckets = vec![G::zero(); (1 << c) - 1];
+
+    // Process the windows from the most significant down, doubling the
+    // accumulator c times between each of them.
+    for w in (0..num_windows).rev() {
+        for _ in 0..c {
+            acc = acc.add(acc);
+        }
+
+        for bucket in &mut buckets {
+            *bucket = G::zero();
+        }
+
+        // Sort the bases into buckets by their digit in this window
+        for (base, scalar) in bases.iter().zip(scalars.iter()) {
+            let digit = ((scalar >> (w * c)) & mask) as usize;
+            if digit != 0 {
+                buckets[digit - 1] = buckets[digit - 1].add(*base);
+            }
+        }
+
+        // Summing the running sum adds bucket i exactly (i + 1) times
+        let mut running_sum = G::zero();
+        for bucket in buckets.iter().rev() {
+            running_sum = running_sum.add(*bucket);
+            acc = acc.add(running_sum);
+        }
+    }
+
+    acc
+}
+
+#[test]
+fn test_multiexp() {
+    use rand::{thread_rng, Rng};
+    use bls12::Bls12;
+    use bn256::Bn256;
+    use engine::{Engine, ScalarField};
+
+    fn scalar_from_u128<F: ScalarField>(v: u128) -> F {
+        let shift = F::from_u64(1u64 << 32);
+        let hi = F::from_u64((v >> 64) as u64);
+        let lo = F::from_u64(v as u64);
+        hi * shift * shift + lo
+    }
+
+    fn naive<G: CurvePoint>(bases: &[G], scalars: &[u128]) -> G {
+        bases.iter().zip(scalars.iter())
+            .fold(G::zero(), |acc, (b, s)| acc.add(b.mul(&scalar_from_u128(*s))))
+    }
+
+    fn test_engine<E: Engine>() {
+        let rng = &mut thread_rng();
+
+        for &n in &[0, 1, 5, 31, 100] {
+            let g1 = (0..n).map(|_| E::G1::random(rng)).collect::<Vec<_>>();
+            let g2 = (0..n).map(|_| E::G2::random(rng)).collect::<Vec<_>>();
+            let mut scalars = (0..n).map(|_| rng.gen::<u128>()).collect::<Vec<_>>();
+            if n > 1 {
+                scalars[0] = 0;
+                scalars[1] = !0;
+            }
+
+            assert!(multiexp(&g1, &scalars) == naive(&g1, &scalars));
+            assert!(multiexp(&g2, &scalars) == naive(&g2, &scalars));
+        }
+    }
+
+    test_engine::<Bn256>();
+    test_engine::<Bls12>();
+}
@@ -0,0 +1,1099 @@
+//! Export of the accumulator in the layout of a snarkjs `.ptau` file, and
+//! import and verification of the accumulator of another ceremony from such
+//! a file. Only BN256 (`bn128` in snarkjs) is supported.
+//!
+//! A `.ptau` file begins with the magic `ptau`, a version and the number of
+//! sections. Each section is a type and a size, followed by its contents:
+//!
+//! 1. header: the size in bytes of a base field element, the base field
+//!    modulus, the log2 of the number of powers and the log2 of the number of
+//!    powers of the ceremony
+//! 2. the powers of tau in G1
+//! 3. the powers of tau in G2
+//! 4. the powers of tau multiplied by alpha in G1
+//! 5. the powers of tau multiplied by beta in G1
+//! 6. beta in G2
+//! 7. one record for each contribution
+//!
+//! All integers are little-endian. Points are uncompressed, with each
+//! coordinate a little-endian integer in Montgomery form (multiplied by
+//! 2<sup>256</sup> mod q), and the point at infinity all zeros.
+//!
+//! A contribution record also holds the partial BLAKE2b state of the hash
+//! of the response, from which snarkjs finishes the hash over its encoding
+//! of the public key. This ceremony hashes responses in its own encoding, so
+//! `write_ptau` leaves the state as zeros and records only the hash of the
+//! next challenge, computed as in `PtauContribution::new`.
+//!
+//! The contributions of a ceremony run with snarkjs are verified as in
+//! `verify_transform`, but the hash of each challenge and the points in G2
+//! that the proofs of knowledge are checked against are computed as snarkjs
+//! computes them.
+
+use blake2::{Blake2b, Digest};
+use bn::{AffineG1, AffineG2, Fq, Fq2, G1, G2};
+use bn::arith::U256;
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use rand::{RngCore, SeedableRng};
+use rand::chacha::ChaChaRng;
+use std::cmp;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use bn256::{montgomery_r, Bn256};
+use configuration::{Configuration, ConfigurationError, MAX_NUM_POWERS};
+use engine::CurvePoint;
+use super::{
+    blank_hash, check_all, same_ratio, Accumulator, DeserializationError, HashWriter, PublicKey,
+    UseCompression, VerificationError
+};
+
+pub const PTAU_MAGIC: &[u8; 4] = b"ptau";
+pub const PTAU_VERSION: u32 = 1;
+
+pub const SECTION_HEADER: u32 = 1;
+pub const SECTION_TAU_G1: u32 = 2;
+pub const SECTION_TAU_G2: u32 = 3;
+pub const SECTION_ALPHA_TAU_G1: u32 = 4;
+pub const SECTION_BETA_TAU_G1: u32 = 5;
+pub const SECTION_BETA_G2: u32 = 6;
+pub const SECTION_CONTRIBUTIONS: u32 = 7;
+
+/// Size in bytes of an element of the base field.
+const FQ_SIZE: usize = 32;
+const G1_SIZE: usize = 2 * FQ_SIZE;
+const G2_SIZE: usize = 4 * FQ_SIZE;
+
+/// Size of the state of a BLAKE2b hash in a contribution record.
+const PARTIAL_HASH_SIZE: usize = 216;
+
+/// Size of a contribution record: the accumulator's points, the public key,
+/// the partial hash, the next challenge hash, the type and the (empty)
+/// parameters.
+const CONTRIBUTION_SIZE: usize =
+    (3 * G1_SIZE + 2 * G2_SIZE) + (6 * G1_SIZE + 3 * G2_SIZE) + PARTIAL_HASH_SIZE + 64 + 4 + 4;
+
+/// The cofactor of G2, big-endian.
+const G2_COFACTOR: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5e,
+    0x06, 0xce, 0xec, 0xda, 0x57, 0x2a, 0x24, 0x89, 0x34, 0x5f, 0x22, 0x99, 0xc0, 0xf9, 0xfa, 0x8d,
+];
+
+/// Errors that might occur when reading a `.ptau` file.
+#[derive(Debug)]
+pub enum PtauError {
+    IoError(io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    /// The base field of the file is not the one of BN256.
+    UnsupportedCurve,
+    InvalidPower(u32),
+    /// A section required for the accumulator is missing.
+    MissingSection(u32),
+    /// A section is smaller than the number of powers requires.
+    InvalidSectionSize(u32, u64),
+    /// The file holds fewer powers than requested, as (available, requested).
+    TooFewPowers(usize, usize),
+    ConfigurationError(ConfigurationError),
+    DeserializationError(DeserializationError),
+}
+
+impl fmt::Display for PtauError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PtauError::IoError(ref e) => write!(f, "Disk IO error: {}", e),
+            PtauError::BadMagic => write!(f, "Not a .ptau file"),
+            PtauError::UnsupportedVersion(v) => write!(f, "Unsupported .ptau version {}", v),
+            PtauError::UnsupportedCurve => write!(f, "Only bn128 .ptau files are supported"),
+            PtauError::InvalidPower(p) => write!(f, "Invalid power {}", p),
+            PtauError::MissingSection(id) => write!(f, "Section {} is missing", id),
+            PtauError::InvalidSectionSize(id, size) =>
+                write!(f, "Section {} is too small ({} bytes)", id, size),
+            PtauError::TooFewPowers(available, requested) =>
+                write!(f, "Requested {} powers but the file contains {}", requested, available),
+            PtauError::ConfigurationError(ref e) => write!(f, "{}", e),
+            PtauError::DeserializationError(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<io::Error> for PtauError {
+    fn from(err: io::Error) -> PtauError {
+        PtauError::IoError(err)
+    }
+}
+
+impl From<ConfigurationError> for PtauError {
+    fn from(err: ConfigurationError) -> PtauError {
+        PtauError::ConfigurationError(err)
+    }
+}
+
+impl From<DeserializationError> for PtauError {
+    fn from(err: DeserializationError) -> PtauError {
+        PtauError::DeserializationError(err)
+    }
+}
+
+/// The record of one contribution to the ceremony.
+pub struct PtauContribution {
+    /// tau in G1 and G2, alpha and beta in G1 and beta in G2, from the
+    /// accumulator after the contribution.
+    pub tau_g1: G1,
+    pub tau_g2: G2,
+    pub alpha_g1: G1,
+    pub beta_g1: G1,
+    pub beta_g2: G2,
+    pub public_key: PublicKey<Bn256>,
+    /// Hash of the challenge file that follows the contribution.
+    pub next_challenge_hash: [u8; 64],
+}
+
+impl PtauContribution {
+    /// Records the contribution that produced `acc`, with the given public
+    /// key and the hash of its response file.
+    ///
+    /// snarkjs recomputes the response hash from the partial BLAKE2b state
+    /// in the record, over its own encoding of the points. The hashes of
+    /// this ceremony cover a different encoding, so the partial state is
+    /// left empty, and the response hash is only reflected in the hash of
+    /// the next challenge.
+    pub fn new(acc: &Accumulator<Bn256>, public_key: PublicKey<Bn256>, response_hash: &[u8]) -> Self
+    {
+        let mut sink = HashWriter::new(io::sink());
+        sink.write_all(response_hash).unwrap();
+        acc.serialize(&mut sink, UseCompression::No).unwrap();
+
+        let mut next_challenge_hash = [0; 64];
+        next_challenge_hash.copy_from_slice(sink.into_hash().as_slice());
+
+        PtauContribution {
+            tau_g1: acc.tau_powers_g1[1],
+            tau_g2: acc.tau_powers_g2[1],
+            alpha_g1: acc.alpha_tau_powers_g1[0],
+            beta_g1: acc.beta_tau_powers_g1[0],
+            beta_g2: acc.beta_g2,
+            public_key: public_key,
+            next_challenge_hash: next_challenge_hash,
+        }
+    }
+}
+
+/// Writes coordinates in the Montgomery form that snarkjs expects.
+struct PtauWriter<'a, W: Write + 'a> {
+    writer: &'a mut W,
+    r: Fq,
+}
+
+impl<'a, W: Write> PtauWriter<'a, W> {
+    fn fq(&mut self, x: Fq) -> io::Result<()>
+    {
+        let mut bytes = [0u8; FQ_SIZE];
+        (x * self.r).into_u256().to_big_endian(&mut bytes).expect("32 bytes is enough for Fq");
+        bytes.reverse();
+        self.writer.write_all(&bytes)
+    }
+
+    fn g1(&mut self, p: &G1) -> io::Result<()>
+    {
+        match AffineG1::from_jacobian(*p) {
+            Some(p) => {
+                self.fq(p.x())?;
+                self.fq(p.y())
+            }
+            None => self.writer.write_all(&[0u8; G1_SIZE])
+        }
+    }
+
+    fn g2(&mut self, p: &G2) -> io::Result<()>
+    {
+        match AffineG2::from_jacobian(*p) {
+            Some(p) => {
+                self.fq(p.x().real())?;
+                self.fq(p.x().imaginary())?;
+                self.fq(p.y().real())?;
+                self.fq(p.y().imaginary())
+            }
+            None => self.writer.write_all(&[0u8; G2_SIZE])
+        }
+    }
+
+    fn section(&mut self, id: u32, size: usize) -> io::Result<()>
+    {
+        self.writer.write_u32::<LittleEndian>(id)?;
+        self.writer.write_u64::<LittleEndian>(size as u64)
+    }
+
+    fn contribution(&mut self, c: &PtauContribution) -> io::Result<()>
+    {
+        self.g1(&c.tau_g1)?;
+        self.g2(&c.tau_g2)?;
+        self.g1(&c.alpha_g1)?;
+        self.g1(&c.beta_g1)?;
+        self.g2(&c.beta_g2)?;
+
+        let key = &c.public_key;
+        self.g1(&key.tau_g1.0)?;
+        self.g1(&key.tau_g1.1)?;
+        self.g1(&key.alpha_g1.0)?;
+        self.g1(&key.alpha_g1.1)?;
+        self.g1(&key.beta_g1.0)?;
+        self.g1(&key.beta_g1.1)?;
+        self.g2(&key.tau_g2)?;
+        self.g2(&key.alpha_g2)?;
+        self.g2(&key.beta_g2)?;
+
+        // The response was not hashed as snarkjs hashes it, so there is no
+        // partial state to record.
+        self.writer.write_all(&[0u8; PARTIAL_HASH_SIZE])?;
+        self.writer.write_all(&c.next_challenge_hash)?;
+
+        // A regular contribution (rather than a beacon), without parameters.
+        self.writer.write_u32::<LittleEndian>(0)?;
+        self.writer.write_u32::<LittleEndian>(0)
+    }
+}
+
+/// Write `acc`, which should have been verified, as a `.ptau` file with a
+/// record for each of `contributions`.
+pub fn write_ptau<W: Write>(
+    writer: &mut W,
+    acc: &Accumulator<Bn256>,
+    contributions: &[PtauContribution]
+) -> io::Result<()>
+{
+    let config = &acc.config;
+    let mut w = PtauWriter {
+        writer: writer,
+        r: montgomery_r(),
+    };
+
+    w.writer.write_all(PTAU_MAGIC)?;
+    w.writer.write_u32::<LittleEndian>(PTAU_VERSION)?;
+    w.writer.write_u32::<LittleEndian>(SECTION_CONTRIBUTIONS)?;
+
+    w.section(SECTION_HEADER, 4 + FQ_SIZE + 4 + 4)?;
+    w.writer.write_u32::<LittleEndian>(FQ_SIZE as u32)?;
+    let mut modulus = [0u8; FQ_SIZE];
+    Fq::modulus().to_big_endian(&mut modulus).expect("32 bytes is enough for q");
+    modulus.reverse();
+    w.writer.write_all(&modulus)?;
+    w.writer.write_u32::<LittleEndian>(config.num_powers_log2 as u32)?;
+    w.writer.write_u32::<LittleEndian>(config.num_powers_log2 as u32)?;
+
+    w.section(SECTION_TAU_G1, acc.tau_powers_g1.len() * G1_SIZE)?;
+    for p in &acc.tau_powers_g1 {
+        w.g1(p)?;
+    }
+
+    w.section(SECTION_TAU_G2, acc.tau_powers_g2.len() * G2_SIZE)?;
+    for p in &acc.tau_powers_g2 {
+        w.g2(p)?;
+    }
+
+    w.section(SECTION_ALPHA_TAU_G1, acc.alpha_tau_powers_g1.len() * G1_SIZE)?;
+    for p in &acc.alpha_tau_powers_g1 {
+        w.g1(p)?;
+    }
+
+    w.section(SECTION_BETA_TAU_G1, acc.beta_tau_powers_g1.len() * G1_SIZE)?;
+    for p in &acc.beta_tau_powers_g1 {
+        w.g1(p)?;
+    }
+
+    w.section(SECTION_BETA_G2, G2_SIZE)?;
+    w.g2(&acc.beta_g2)?;
+
+    w.section(SECTION_CONTRIBUTIONS, 4 + contributions.len() * CONTRIBUTION_SIZE)?;
+    w.writer.write_u32::<LittleEndian>(contributions.len() as u32)?;
+    for c in contributions {
+        w.contribution(c)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a coordinate in Montgomery form, given the inverse of R.
+fn read_fq<R: Read>(reader: &mut R, r_inv: &Fq) -> Result<Fq, PtauError>
+{
+    let mut bytes = [0u8; FQ_SIZE];
+    reader.read_exact(&mut bytes)?;
+    bytes.reverse();
+    let x = Fq::from_slice(&bytes).map_err(|_| DeserializationError::InvalidEncoding)?;
+    Ok(x * *r_inv)
+}
+
+fn read_g1<R: Read>(reader: &mut R, r_inv: &Fq) -> Result<G1, PtauError>
+{
+    let x = read_fq(reader, r_inv)?;
+    let y = read_fq(reader, r_inv)?;
+    if x == Fq::zero() && y == Fq::zero() {
+        return Ok(G1::zero());
+    }
+    Ok(G1::new(x, y, Fq::one()))
+}
+
+fn read_g2<R: Read>(reader: &mut R, r_inv: &Fq) -> Result<G2, PtauError>
+{
+    let x = Fq2::new(read_fq(reader, r_inv)?, read_fq(reader, r_inv)?);
+    let y = Fq2::new(read_fq(reader, r_inv)?, read_fq(reader, r_inv)?);
+    if x == Fq2::zero() && y == Fq2::zero() {
+        return Ok(G2::zero());
+    }
+    Ok(G2::new(x, y, Fq2::one()))
+}
+
+/// Reads `count` points with `read` and checks each of them.
+fn read_points<R: Read, C: CurvePoint, F>(
+    reader: &mut R,
+    count: usize,
+    section: &'static str,
+    mut read: F
+) -> Result<Vec<C>, PtauError>
+    where F: FnMut(&mut R) -> Result<C, PtauError>
+{
+    // The count comes from the file, so the vector only grows as the points
+    // are actually read.
+    let mut points = Vec::with_capacity(cmp::min(count, 1 << 16));
+    for _ in 0..count {
+        points.push(read(reader)?);
+    }
+    check_all(&points, section, 0)?;
+    Ok(points)
+}
+
+/// Reads the header section, returning the configuration for `num_powers`
+/// powers, or for every power in the file if `None`, and the log2 of the
+/// number of powers of the ceremony.
+fn read_header<R: Read>(
+    reader: &mut R,
+    num_powers: Option<usize>
+) -> Result<(Configuration, u32), PtauError>
+{
+    if reader.read_u32::<LittleEndian>()? != FQ_SIZE as u32 {
+        return Err(PtauError::UnsupportedCurve);
+    }
+    let mut modulus = [0u8; FQ_SIZE];
+    reader.read_exact(&mut modulus)?;
+    modulus.reverse();
+    if U256::from_slice(&modulus).ok() != Some(Fq::modulus()) {
+        return Err(PtauError::UnsupportedCurve);
+    }
+
+    let power = reader.read_u32::<LittleEndian>()?;
+    let ceremony_power = reader.read_u32::<LittleEndian>()?;
+    // Larger ceremonies are not supported, and their sizes would overflow.
+    for &p in &[power, ceremony_power] {
+        if p > MAX_NUM_POWERS.trailing_zeros() {
+            return Err(PtauError::InvalidPower(p));
+        }
+    }
+
+    let available = 1usize << power;
+    let num_powers = num_powers.unwrap_or(available);
+    if num_powers > available {
+        return Err(PtauError::TooFewPowers(available, num_powers));
+    }
+
+    Ok((Configuration::try_for_engine::<Bn256>(num_powers)?, ceremony_power))
+}
+
+/// Reads one record of the contributions section, checking its points.
+fn read_contribution<R: Read>(
+    reader: &mut R,
+    r_inv: &Fq,
+    index: usize
+) -> Result<PtauContribution, PtauError>
+{
+    let tau_g1 = read_g1(reader, r_inv)?;
+    let tau_g2 = read_g2(reader, r_inv)?;
+    let alpha_g1 = read_g1(reader, r_inv)?;
+    let beta_g1 = read_g1(reader, r_inv)?;
+    let beta_g2 = read_g2(reader, r_inv)?;
+    let public_key = PublicKey {
+        tau_g1: (read_g1(reader, r_inv)?, read_g1(reader, r_inv)?),
+        alpha_g1: (read_g1(reader, r_inv)?, read_g1(reader, r_inv)?),
+        beta_g1: (read_g1(reader, r_inv)?, read_g1(reader, r_inv)?),
+        tau_g2: read_g2(reader, r_inv)?,
+        alpha_g2: read_g2(reader, r_inv)?,
+        beta_g2: read_g2(reader, r_inv)?,
+    };
+
+    let g1s = [
+        tau_g1, alpha_g1, beta_g1, public_key.tau_g1.0, public_key.tau_g1.1,
+        public_key.alpha_g1.0, public_key.alpha_g1.1, public_key.beta_g1.0, public_key.beta_g1.1
+    ];
+    let g2s = [tau_g2, beta_g2, public_key.tau_g2, public_key.alpha_g2, public_key.beta_g2];
+    for e in g1s.iter().map(|p| p.check()).chain(g2s.iter().map(|p| p.check())) {
+        e.map_err(|e| DeserializationError::InvalidPoint("contributions", index, e))?;
+    }
+
+    // The partial hash of the response is not used.
+    let mut partial_hash = [0u8; PARTIAL_HASH_SIZE];
+    reader.read_exact(&mut partial_hash)?;
+    let mut next_challenge_hash = [0u8; 64];
+    reader.read_exact(&mut next_challenge_hash)?;
+
+    // Skip the type (beacon or not) and the parameters, such as the name.
+    let _contribution_type = reader.read_u32::<LittleEndian>()?;
+    let params_size = reader.read_u32::<LittleEndian>()?;
+    io::copy(&mut reader.by_ref().take(params_size as u64), &mut io::sink())?;
+
+    Ok(PtauContribution {
+        tau_g1: tau_g1,
+        tau_g2: tau_g2,
+        alpha_g1: alpha_g1,
+        beta_g1: beta_g1,
+        beta_g2: beta_g2,
+        public_key: public_key,
+        next_challenge_hash: next_challenge_hash,
+    })
+}
+
+/// The contents of a `.ptau` file.
+pub struct Ptau {
+    pub accumulator: Accumulator<Bn256>,
+    /// The log2 of the number of powers of the ceremony, which may be more
+    /// than the file holds.
+    pub ceremony_power: u32,
+    pub contributions: Vec<PtauContribution>,
+}
+
+/// Read the accumulator and contributions of another ceremony from a `.ptau`
+/// file, keeping the first `num_powers` powers, or all of them if `None`.
+/// Every point is checked to be in the prime order subgroup and not the
+/// point at infinity, but the powers themselves should be checked with
+/// `Accumulator::verify_consistency`.
+pub fn read_ptau<R: Read>(
+    reader: &mut R,
+    num_powers: Option<usize>
+) -> Result<Ptau, PtauError>
+{
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != PTAU_MAGIC {
+        return Err(PtauError::BadMagic);
+    }
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version != PTAU_VERSION {
+        return Err(PtauError::UnsupportedVersion(version));
+    }
+    let num_sections = reader.read_u32::<LittleEndian>()?;
+
+    let r_inv = montgomery_r().inverse().expect("R is invertible");
+    let g1 = |r: &mut _| read_g1(r, &r_inv);
+    let g2 = |r: &mut _| read_g2(r, &r_inv);
+
+    let mut header = None;
+    let mut contributions = vec![];
+    let mut tau_powers_g1 = None;
+    let mut tau_powers_g2 = None;
+    let mut alpha_tau_powers_g1 = None;
+    let mut beta_tau_powers_g1 = None;
+    let mut beta_g2 = None;
+
+    for _ in 0..num_sections {
+        let id = reader.read_u32::<LittleEndian>()?;
+        let size = reader.read_u64::<LittleEndian>()?;
+        let mut section = reader.by_ref().take(size);
+
+        if id == SECTION_HEADER {
+            header = Some(read_header(&mut section, num_powers)?);
+        } else if id == SECTION_CONTRIBUTIONS {
+            let count = section.read_u32::<LittleEndian>()? as usize;
+            for i in 0..count {
+                contributions.push(read_contribution(&mut section, &r_inv, i)?);
+            }
+        } else if id >= SECTION_TAU_G1 && id <= SECTION_BETA_G2 {
+            // The header comes first in every file written by snarkjs.
+            let config = header.ok_or(PtauError::MissingSection(SECTION_HEADER))?.0;
+            let needed = match id {
+                SECTION_TAU_G1 => config.num_powers_g1 * G1_SIZE,
+                SECTION_TAU_G2 => config.num_powers * G2_SIZE,
+                SECTION_BETA_G2 => G2_SIZE,
+                _ => config.num_powers * G1_SIZE,
+            };
+            if size < needed as u64 {
+                return Err(PtauError::InvalidSectionSize(id, size));
+            }
+
+            match id {
+                SECTION_TAU_G1 => tau_powers_g1 = Some(read_points(
+                    &mut section, config.num_powers_g1, "tau_powers_g1", &g1)?),
+                SECTION_TAU_G2 => tau_powers_g2 = Some(read_points(
+                    &mut section, config.num_powers, "tau_powers_g2", &g2)?),
+                SECTION_ALPHA_TAU_G1 => alpha_tau_powers_g1 = Some(read_points(
+                    &mut section, config.num_powers, "alpha_tau_powers_g1", &g1)?),
+                SECTION_BETA_TAU_G1 => beta_tau_powers_g1 = Some(read_points(
+                    &mut section, config.num_powers, "beta_tau_powers_g1", &g1)?),
+                _ => beta_g2 = Some(read_points(&mut section, 1, "beta_g2", &g2)?[0]),
+            }
+        }
+
+        // Skip the rest of the section, including any powers not kept.
+        io::copy(&mut section, &mut io::sink())?;
+    }
+
+    let (config, ceremony_power) = header.ok_or(PtauError::MissingSection(SECTION_HEADER))?;
+
+    Ok(Ptau {
+        accumulator: Accumulator {
+            tau_powers_g1: tau_powers_g1.ok_or(PtauError::MissingSection(SECTION_TAU_G1))?,
+            tau_powers_g2: tau_powers_g2.ok_or(PtauError::MissingSection(SECTION_TAU_G2))?,
+            alpha_tau_powers_g1: alpha_tau_powers_g1.ok_or(PtauError::MissingSection(SECTION_ALPHA_TAU_G1))?,
+            beta_tau_powers_g1: beta_tau_powers_g1.ok_or(PtauError::MissingSection(SECTION_BETA_TAU_G1))?,
+            beta_g2: beta_g2.ok_or(PtauError::MissingSection(SECTION_BETA_G2))?,
+            config: config,
+        },
+        ceremony_power: ceremony_power,
+        contributions: contributions,
+    })
+}
+
+fn fq_to_big_endian(x: Fq) -> [u8; FQ_SIZE]
+{
+    let mut bytes = [0u8; FQ_SIZE];
+    x.into_u256().to_big_endian(&mut bytes).expect("32 bytes is enough for Fq");
+    bytes
+}
+
+/// Encodes a point in G1 as snarkjs does when hashing: big-endian x and y,
+/// with a flag for the point at infinity.
+fn uncompressed_g1(p: &G1) -> [u8; G1_SIZE]
+{
+    let mut bytes = [0u8; G1_SIZE];
+    match AffineG1::from_jacobian(*p) {
+        Some(p) => {
+            bytes[0..32].copy_from_slice(&fq_to_big_endian(p.x()));
+            bytes[32..64].copy_from_slice(&fq_to_big_endian(p.y()));
+        }
+        None => bytes[0] = 0x40
+    }
+    bytes
+}
+
+/// Encodes a point in G2 as snarkjs does when hashing, like G1 but with the
+/// imaginary part of each coordinate first.
+fn uncompressed_g2(p: &G2) -> [u8; G2_SIZE]
+{
+    let mut bytes = [0u8; G2_SIZE];
+    match AffineG2::from_jacobian(*p) {
+        Some(p) => {
+            bytes[0..32].copy_from_slice(&fq_to_big_endian(p.x().imaginary()));
+            bytes[32..64].copy_from_slice(&fq_to_big_endian(p.x().real()));
+            bytes[64..96].copy_from_slice(&fq_to_big_endian(p.y().imaginary()));
+            bytes[96..128].copy_from_slice(&fq_to_big_endian(p.y().real()));
+        }
+        None => bytes[0] = 0x40
+    }
+    bytes
+}
+
+/// The hash of the first challenge of a snarkjs ceremony with
+/// 2<sup>`ceremony_power`</sup> powers, which holds only generators.
+pub fn first_challenge_hash(ceremony_power: u32) -> [u8; 64]
+{
+    let g1 = uncompressed_g1(&G1::one());
+    let g2 = uncompressed_g2(&G2::one());
+    let n = 1u64 << ceremony_power;
+
+    let mut h = Blake2b::default();
+    h.update(blank_hash().as_slice());
+    for _ in 0..(2 * n - 1) {
+        h.update(&g1[..]);
+    }
+    for _ in 0..n {
+        h.update(&g2[..]);
+    }
+    for _ in 0..(2 * n) {
+        h.update(&g1[..]);
+    }
+    h.update(&g2[..]);
+
+    let mut hash = [0u8; 64];
+    hash.copy_from_slice(h.finalize().as_slice());
+    hash
+}
+
+/// Samples an element of the base field as snarkjs does: 64-bit limbs, least
+/// significant first, each drawn as two 32-bit words with the most
+/// significant first, until the value is less than q. The value is taken to
+/// be in Montgomery form.
+fn snarkjs_random_fq(rng: &mut ChaChaRng, r_inv: &Fq) -> Fq
+{
+    loop {
+        let mut bytes = [0u8; FQ_SIZE];
+        for i in 0..4 {
+            let hi = rng.next_u32() as u64;
+            let lo = rng.next_u32() as u64;
+            BigEndian::write_u64(&mut bytes[(24 - 8 * i)..(32 - 8 * i)], (hi << 32) | lo);
+        }
+        // q has 254 bits.
+        bytes[0] &= 0x3f;
+
+        if let Ok(x) = Fq::from_slice(&bytes) {
+            return x * *r_inv;
+        }
+    }
+}
+
+/// Orders elements of Fq2 as snarkjs does, by the imaginary part first.
+fn fq2_order_key(x: Fq2) -> ([u8; FQ_SIZE], [u8; FQ_SIZE])
+{
+    (fq_to_big_endian(x.imaginary()), fq_to_big_endian(x.real()))
+}
+
+fn mul_by_cofactor(p: G2) -> G2
+{
+    let mut acc = G2::zero();
+    for byte in G2_COFACTOR.iter() {
+        for i in (0..8).rev() {
+            acc = acc + acc;
+            if (byte >> i) & 1 == 1 {
+                acc = acc + p;
+            }
+        }
+    }
+    acc
+}
+
+/// Hashes to G2 as snarkjs does (after the original ceremony), using ChaCha20
+/// keyed with the first 32 bytes of `digest` to sample x coordinates until
+/// one is on the curve, then clearing the cofactor.
+fn snarkjs_hash_to_g2(digest: &[u8]) -> G2
+{
+    assert!(digest.len() >= 32);
+
+    // The key is read as big-endian words.
+    let mut seed = [0u8; 32];
+    for i in 0..8 {
+        LittleEndian::write_u32(
+            &mut seed[(4 * i)..(4 * i + 4)],
+            BigEndian::read_u32(&digest[(4 * i)..(4 * i + 4)]));
+    }
+    let mut rng = ChaChaRng::from_seed(seed);
+    let r_inv = montgomery_r().inverse().expect("R is invertible");
+
+    loop {
+        let x = Fq2::new(snarkjs_random_fq(&mut rng, &r_inv), snarkjs_random_fq(&mut rng, &r_inv));
+        let greatest = rng.next_u32() & 1 == 1;
+
+        if let Some(y) = (x * x * x + G2::b()).sqrt() {
+            let neg_y = -y;
+            let y = if (fq2_order_key(y) < fq2_order_key(neg_y)) ^ greatest { y } else { neg_y };
+
+            let p = mul_by_cofactor(G2::new(x, y, Fq2::one()));
+            if !p.is_zero() {
+                return p;
+            }
+        }
+    }
+}
+
+/// Computes the point in G2 that the proof of knowledge of a snarkjs public
+/// key is checked against, like `compute_g2_s`.
+fn snarkjs_g2_s(g1_s: &G1, g1_s_x: &G1, personalization: u8, challenge_hash: &[u8]) -> G2
+{
+    let mut h = Blake2b::default();
+    h.update(&[personalization]);
+    h.update(challenge_hash);
+    h.update(&uncompressed_g1(g1_s)[..]);
+    h.update(&uncompressed_g1(g1_s_x)[..]);
+
+    snarkjs_hash_to_g2(&h.finalize())
+}
+
+/// Verifies a contribution to a snarkjs ceremony, given the contribution
+/// before it (`None` for the first) and the hash of the challenge it
+/// responded to: the `next_challenge_hash` of the previous contribution, or
+/// `first_challenge_hash` for the first.
+pub fn verify_ptau_contribution(
+    previous: Option<&PtauContribution>,
+    contribution: &PtauContribution,
+    challenge_hash: &[u8]
+) -> Result<(), VerificationError>
+{
+    let (before_tau_g1, before_alpha_g1, before_beta_g1) = match previous {
+        Some(p) => (p.tau_g1, p.alpha_g1, p.beta_g1),
+        None => (G1::one(), G1::one(), G1::one()),
+    };
+    let key = &contribution.public_key;
+
+    let tau_g2_s = snarkjs_g2_s(&key.tau_g1.0, &key.tau_g1.1, 0, challenge_hash);
+    let alpha_g2_s = snarkjs_g2_s(&key.alpha_g1.0, &key.alpha_g1.1, 1, challenge_hash);
+    let beta_g2_s = snarkjs_g2_s(&key.beta_g1.0, &key.beta_g1.1, 2, challenge_hash);
+
+    // Check the proofs-of-knowledge for tau/alpha/beta
+    if !same_ratio::<Bn256>(key.tau_g1, (tau_g2_s, key.tau_g2)) {
+        return Err(VerificationError::TauProofOfKnowledge);
+    }
+    if !same_ratio::<Bn256>(key.alpha_g1, (alpha_g2_s, key.alpha_g2)) {
+        return Err(VerificationError::AlphaProofOfKnowledge);
+    }
+    if !same_ratio::<Bn256>(key.beta_g1, (beta_g2_s, key.beta_g2)) {
+        return Err(VerificationError::BetaProofOfKnowledge);
+    }
+
+    // Did the participant multiply the previous tau by the new one, and
+    // does tau in G2 match?
+    if !same_ratio::<Bn256>((before_tau_g1, contribution.tau_g1), (tau_g2_s, key.tau_g2)) {
+        return Err(VerificationError::TauRatio);
+    }
+    if !same_ratio::<Bn256>((G1::one(), contribution.tau_g1), (G2::one(), contribution.tau_g2)) {
+        return Err(VerificationError::TauPowersG2);
+    }
+
+    // Did the participant multiply the previous alpha by the new one?
+    if !same_ratio::<Bn256>((before_alpha_g1, contribution.alpha_g1), (alpha_g2_s, key.alpha_g2)) {
+        return Err(VerificationError::AlphaRatio);
+    }
+
+    // Did the participant multiply the previous beta by the new one?
+    if !same_ratio::<Bn256>((before_beta_g1, contribution.beta_g1), (beta_g2_s, key.beta_g2)) {
+        return Err(VerificationError::BetaRatio);
+    }
+    if !same_ratio::<Bn256>((G1::one(), contribution.beta_g1), (G2::one(), contribution.beta_g2)) {
+        return Err(VerificationError::BetaG2Ratio);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_write_ptau() {
+    use configuration::*;
+    use hex;
+    use rand::{thread_rng, Rng};
+    use super::keypair;
+
+    // Parses the section table as snarkjs does, returning the contents of
+    // each section.
+    fn read_sections(mut data: &[u8]) -> Vec<(u32, Vec<u8>)> {
+        let mut magic = [0u8; 4];
+        data.read_exact(&mut magic).unwrap();
+        assert_eq!(&magic, PTAU_MAGIC);
+        assert_eq!(data.read_u32::<LittleEndian>().unwrap(), PTAU_VERSION);
+
+        let num_sections = data.read_u32::<LittleEndian>().unwrap();
+        let mut sections = vec![];
+        for _ in 0..num_sections {
+            let id = data.read_u32::<LittleEndian>().unwrap();
+            let size = data.read_u64::<LittleEndian>().unwrap() as usize;
+            sections.push((id, data[..size].to_vec()));
+            data = &data[size..];
+        }
+        assert!(data.is_empty());
+        sections
+    }
+
+    let config = Configuration::for_engine::<Bn256>(16);
+    let rng = &mut thread_rng();
+
+    let mut acc = Accumulator::<Bn256>::new(config);
+    let mut contributions = vec![];
+    for _ in 0..2 {
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+        let (pk, sk) = keypair::<Bn256, _>(rng, &digest);
+        acc.transform(&sk);
+        contributions.push(PtauContribution::new(&acc, pk, &digest));
+    }
+
+    let mut data = vec![];
+    write_ptau(&mut data, &acc, &contributions).unwrap();
+    let sections = read_sections(&data);
+
+    // The magic, version 1 and 7 sections, then the header section of 44
+    // bytes: 32-byte elements, the modulus and a power of 4, twice.
+    let expected_header = hex::decode(
+        "70746175010000000700000001000000\
+         2c000000000000002000000047fd7cd8\
+         168c203c8dca7168916a81975d588181\
+         b64550b829a031e1724e643004000000\
+         04000000").unwrap();
+    assert_eq!(&data[..expected_header.len()], &expected_header[..]);
+
+    // The sections snarkjs writes for 16 powers and two contribution
+    // records of 1504 bytes each.
+    assert_eq!(
+        sections.iter().map(|s| (s.0, s.1.len())).collect::<Vec<_>>(),
+        vec![(1, 44), (2, 31 * 64), (3, 16 * 128), (4, 16 * 64), (5, 16 * 64), (6, 128), (7, 4 + 2 * 1504)]);
+
+    let mut header = &sections[0].1[..];
+    assert!(read_header(&mut header, None).unwrap() == (config, 4));
+    assert!(header.is_empty());
+
+    let r_inv = montgomery_r().inverse().unwrap();
+    let g1 = |r: &mut &[u8]| read_g1(r, &r_inv).unwrap();
+    let g2 = |r: &mut &[u8]| read_g2(r, &r_inv).unwrap();
+
+    let mut tau_g1 = &sections[1].1[..];
+    for p in &acc.tau_powers_g1 {
+        assert!(g1(&mut tau_g1) == *p);
+    }
+    let mut tau_g2 = &sections[2].1[..];
+    for p in &acc.tau_powers_g2 {
+        assert!(g2(&mut tau_g2) == *p);
+    }
+    let mut alpha_tau_g1 = &sections[3].1[..];
+    for p in &acc.alpha_tau_powers_g1 {
+        assert!(g1(&mut alpha_tau_g1) == *p);
+    }
+    let mut beta_tau_g1 = &sections[4].1[..];
+    for p in &acc.beta_tau_powers_g1 {
+        assert!(g1(&mut beta_tau_g1) == *p);
+    }
+    assert!(g2(&mut &sections[5].1[..]) == acc.beta_g2);
+
+    let mut records = &sections[6].1[..];
+    assert_eq!(records.read_u32::<LittleEndian>().unwrap(), 2);
+    for c in &contributions {
+        assert!(g1(&mut records) == c.tau_g1);
+        assert!(g2(&mut records) == c.tau_g2);
+        assert!(g1(&mut records) == c.alpha_g1);
+        assert!(g1(&mut records) == c.beta_g1);
+        assert!(g2(&mut records) == c.beta_g2);
+        assert!(g1(&mut records) == c.public_key.tau_g1.0);
+        assert!(g1(&mut records) == c.public_key.tau_g1.1);
+        for _ in 0..4 {
+            g1(&mut records);
+        }
+        assert!(g2(&mut records) == c.public_key.tau_g2);
+        g2(&mut records);
+        assert!(g2(&mut records) == c.public_key.beta_g2);
+        records = &records[PARTIAL_HASH_SIZE..];
+        assert_eq!(&records[..64], &c.next_challenge_hash[..]);
+        records = &records[64..];
+        assert_eq!(records.read_u32::<LittleEndian>().unwrap(), 0);
+        assert_eq!(records.read_u32::<LittleEndian>().unwrap(), 0);
+    }
+    assert!(records.is_empty());
+
+    // Rewriting the file from test_vectors/gen_ptau.py, whose one
+    // contribution has no parameters, gives it back, but for the partial
+    // hash, which is written as zeros.
+    let snarkjs = &include_bytes!("../test_vectors/snarkjs_2.ptau")[..];
+    let ptau = read_ptau(&mut &snarkjs[..], None).unwrap();
+    let mut data = vec![];
+    write_ptau(&mut data, &ptau.accumulator, &ptau.contributions).unwrap();
+    let mut expected = snarkjs.to_vec();
+    let partial_hash = expected.len() - (PARTIAL_HASH_SIZE + 64 + 4 + 4);
+    for b in &mut expected[partial_hash..(partial_hash + PARTIAL_HASH_SIZE)] {
+        *b = 0;
+    }
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn test_read_ptau() {
+    use bn::Fr;
+    use configuration::*;
+    use engine::ScalarField;
+    use rand::{thread_rng, Rng};
+    use super::{keypair, VerificationError};
+
+    let config = Configuration::for_engine::<Bn256>(16);
+    let rng = &mut thread_rng();
+    let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+    let mut acc = Accumulator::<Bn256>::new(config);
+    let (_, sk) = keypair::<Bn256, _>(rng, &digest);
+    acc.transform(&sk);
+
+    let mut data = vec![];
+    write_ptau(&mut data, &acc, &[]).unwrap();
+
+    // All of the powers.
+    let imported = read_ptau(&mut &data[..], None).unwrap();
+    assert_eq!(imported.ceremony_power, 4);
+    assert!(imported.contributions.is_empty());
+    let imported = imported.accumulator;
+    assert!(imported == acc);
+    assert!(imported.verify_consistency().is_ok());
+
+    // Fewer powers.
+    let imported = read_ptau(&mut &data[..], Some(4)).unwrap().accumulator;
+    assert_eq!(imported.config.num_powers, 4);
+    assert!(imported.tau_powers_g1[..] == acc.tau_powers_g1[..7]);
+    assert!(imported.tau_powers_g2[..] == acc.tau_powers_g2[..4]);
+    assert!(imported.alpha_tau_powers_g1[..] == acc.alpha_tau_powers_g1[..4]);
+    assert!(imported.beta_tau_powers_g1[..] == acc.beta_tau_powers_g1[..4]);
+    assert!(imported.beta_g2 == acc.beta_g2);
+    assert!(imported.verify_consistency().is_ok());
+
+    // More powers than the file holds.
+    match read_ptau(&mut &data[..], Some(32)) {
+        Err(PtauError::TooFewPowers(16, 32)) => {}
+        _ => panic!("expected too few powers")
+    }
+
+    // Points are checked on import.
+    let mut bad = acc.clone();
+    bad.beta_tau_powers_g1[2] = G1::zero();
+    let mut bad_data = vec![];
+    write_ptau(&mut bad_data, &bad, &[]).unwrap();
+    match read_ptau(&mut &bad_data[..], None) {
+        Err(PtauError::DeserializationError(DeserializationError::InvalidPoint(section, index, _))) => {
+            assert_eq!(section, "beta_tau_powers_g1");
+            assert_eq!(index, 2);
+        }
+        _ => panic!("expected an invalid point")
+    }
+
+    // Inconsistent powers are imported, but fail the consistency check.
+    let mut bad = acc.clone();
+    bad.tau_powers_g2[3] = bad.tau_powers_g2[3].mul(&Fr::from_u64(2));
+    let mut bad_data = vec![];
+    write_ptau(&mut bad_data, &bad, &[]).unwrap();
+    let imported = read_ptau(&mut &bad_data[..], None).unwrap().accumulator;
+    assert_eq!(imported.verify_consistency(), Err(VerificationError::TauPowersG2));
+
+    // Other files are rejected.
+    match read_ptau(&mut &data[4..], None) {
+        Err(PtauError::BadMagic) => {}
+        _ => panic!("expected bad magic")
+    }
+}
+
+#[test]
+fn test_verify_ptau_contributions() {
+    use bn::Fr;
+    use configuration::*;
+    use engine::ScalarField;
+    use rand::{thread_rng, Rng};
+    use super::PrivateKey;
+
+    let config = Configuration::for_engine::<Bn256>(4);
+    let rng = &mut thread_rng();
+
+    // Contribute as snarkjs does, with its hash to G2.
+    fn contribute<R: Rng>(
+        acc: &mut Accumulator<Bn256>,
+        rng: &mut R,
+        challenge_hash: &[u8]
+    ) -> PtauContribution
+    {
+        let sk = PrivateKey::<Bn256>::new(Fr::random(rng), Fr::random(rng), Fr::random(rng));
+        let mut key_pair = |x: &Fr, personalization: u8| {
+            let g1_s = G1::random(rng);
+            let g1_s_x = g1_s.mul(x);
+            let g2_s = snarkjs_g2_s(&g1_s, &g1_s_x, personalization, challenge_hash);
+            ((g1_s, g1_s_x), g2_s.mul(x))
+        };
+        let (tau_g1, tau_g2) = key_pair(&*sk.tau, 0);
+        let (alpha_g1, alpha_g2) = key_pair(&*sk.alpha, 1);
+        let (beta_g1, beta_g2) = key_pair(&*sk.beta, 2);
+        let pk = PublicKey {
+            tau_g1: tau_g1,
+            alpha_g1: alpha_g1,
+            beta_g1: beta_g1,
+            tau_g2: tau_g2,
+            alpha_g2: alpha_g2,
+            beta_g2: beta_g2,
+        };
+
+        acc.transform(&sk);
+        let mut contribution = PtauContribution::new(acc, pk, &[0; 64]);
+        rng.fill(&mut contribution.next_challenge_hash[..]);
+        contribution
+    }
+
+    let mut acc = Accumulator::<Bn256>::new(config);
+    let first_hash = first_challenge_hash(2);
+    let first = contribute(&mut acc, rng, &first_hash);
+    let second = contribute(&mut acc, rng, &first.next_challenge_hash);
+
+    // The contributions survive a round trip through a file.
+    let mut data = vec![];
+    write_ptau(&mut data, &acc, &[first, second]).unwrap();
+    let ptau = read_ptau(&mut &data[..], None).unwrap();
+    let (first, second) = (&ptau.contributions[0], &ptau.contributions[1]);
+
+    assert!(verify_ptau_contribution(None, first, &first_hash).is_ok());
+    assert!(verify_ptau_contribution(Some(first), second, &first.next_challenge_hash).is_ok());
+
+    // Out of order, or against the wrong challenge.
+    assert_eq!(
+        verify_ptau_contribution(None, second, &first.next_challenge_hash),
+        Err(VerificationError::TauRatio));
+    assert_eq!(
+        verify_ptau_contribution(Some(first), second, &first_hash),
+        Err(VerificationError::TauProofOfKnowledge));
+
+    // A different beta in G2.
+    let mut bad = read_ptau(&mut &data[..], None).unwrap().contributions.remove(1);
+    bad.beta_g2 = bad.beta_g2.mul(&Fr::from_u64(2));
+    assert_eq!(
+        verify_ptau_contribution(Some(first), &bad, &first.next_challenge_hash),
+        Err(VerificationError::BetaG2Ratio));
+}
+
+#[test]
+fn test_verify_snarkjs_ptau() {
+    use super::VerificationError;
+
+    // A ceremony of 4 powers with one contribution, in the layout written by
+    // snarkjs, from test_vectors/gen_ptau.py.
+    let data = &include_bytes!("../test_vectors/snarkjs_2.ptau")[..];
+
+    let ptau = read_ptau(&mut &data[..], None).unwrap();
+    assert_eq!(ptau.ceremony_power, 2);
+    assert_eq!(ptau.accumulator.config.num_powers, 4);
+    assert!(ptau.accumulator.verify_consistency().is_ok());
+    assert_eq!(ptau.contributions.len(), 1);
+    let hash = first_challenge_hash(ptau.ceremony_power);
+    assert!(verify_ptau_contribution(None, &ptau.contributions[0], &hash).is_ok());
+
+    // Offsets of the contents of the sections in the file.
+    let mut offsets = vec![0; SECTION_CONTRIBUTIONS as usize + 1];
+    let mut offset = 12;
+    while offset < data.len() {
+        let id = LittleEndian::read_u32(&data[offset..]);
+        let size = LittleEndian::read_u64(&data[(offset + 4)..]) as usize;
+        offsets[id as usize] = offset + 12;
+        offset += 12 + size;
+    }
+
+    // The last power of tau in G1 replaced by the one before it.
+    let mut bad = data.to_vec();
+    let last = offsets[SECTION_TAU_G1 as usize] + 6 * G1_SIZE;
+    let previous = bad[(last - G1_SIZE)..last].to_vec();
+    bad[last..(last + G1_SIZE)].copy_from_slice(&previous);
+    let ptau = read_ptau(&mut &bad[..], None).unwrap();
+    assert_eq!(ptau.accumulator.verify_consistency(), Err(VerificationError::TauPowersG1));
+
+    // The public keys of alpha and beta in G1 swapped.
+    let mut bad = data.to_vec();
+    let alpha = offsets[SECTION_CONTRIBUTIONS as usize] + 4 + (3 * G1_SIZE + 2 * G2_SIZE) + 2 * G1_SIZE;
+    let (before, after) = bad.split_at_mut(alpha + 2 * G1_SIZE);
+    before[alpha..].swap_with_slice(&mut after[..(2 * G1_SIZE)]);
+    let ptau = read_ptau(&mut &bad[..], None).unwrap();
+    assert_eq!(
+        verify_ptau_contribution(None, &ptau.contributions[0], &hash),
+        Err(VerificationError::AlphaProofOfKnowledge));
+
+    // A ceremony of more powers than supported.
+    let mut bad = data.to_vec();
+    LittleEndian::write_u32(&mut bad[(offsets[SECTION_HEADER as usize] + 40)..], 29);
+    match read_ptau(&mut &bad[..], None) {
+        Err(PtauError::InvalidPower(29)) => {}
+        _ => panic!("expected an invalid power")
+    }
+
+    // A short file claiming the most powers supported fails when it runs
+    // out, rather than allocating for all of them first.
+    let mut bad = data[..(offsets[SECTION_TAU_G1 as usize] + 3 * G1_SIZE)].to_vec();
+    let header = offsets[SECTION_HEADER as usize];
+    LittleEndian::write_u32(&mut bad[(header + 36)..], 28);
+    LittleEndian::write_u32(&mut bad[(header + 40)..], 28);
+    LittleEndian::write_u64(&mut bad[(offsets[SECTION_TAU_G1 as usize] - 8)..], u64::max_value());
+    match read_ptau(&mut &bad[..], None) {
+        Err(PtauError::IoError(_)) => {}
+        _ => panic!("expected the file to end")
+    }
+
+    // An invalid number of powers to keep.
+    match read_ptau(&mut &data[..], Some(3)) {
+        Err(PtauError::ConfigurationError(ConfigurationError::InvalidNumPowers(3))) => {}
+        _ => panic!("expected an invalid number of powers")
+    }
+}
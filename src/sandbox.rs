@@ -0,0 +1,189 @@
+//! Confinement of the process computing a contribution, so that its secrets
+//! cannot be sent anywhere but to the response.
+//!
+//! On Linux (x86_64 and aarch64), `enter` installs a seccomp-bpf filter
+//! allowing only the system calls needed to read the challenge from stdin,
+//! draw entropy, compute on several threads, lock memory (see `secret`) and
+//! write the response to stdout. Any other call, such as opening a file or a
+//! socket, fails with EPERM. The filter applies to the calling thread and
+//! the threads it creates afterwards, and cannot be lifted.
+
+use std::io;
+
+/// Whether `enter` can confine the process on this platform.
+pub fn is_supported() -> bool
+{
+    cfg!(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod seccomp {
+    use libc;
+    use std::io;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    // From linux/filter.h and linux/seccomp.h.
+    const BPF_LD_W_ABS: u16 = 0x20;
+    const BPF_JMP_JEQ_K: u16 = 0x15;
+    const BPF_RET_K: u16 = 0x06;
+
+    /// Offsets in `struct seccomp_data`.
+    const NR_OFFSET: u32 = 0;
+    const ARCH_OFFSET: u32 = 4;
+
+    const SECCOMP_MODE_FILTER: u32 = 2;
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+    const ALLOWED_SYSCALLS: &[libc::c_long] = &[
+        // Reading the challenge and writing the response
+        libc::SYS_read,
+        libc::SYS_write,
+        libc::SYS_close,
+        // Drawing entropy
+        libc::SYS_getrandom,
+        // Memory
+        libc::SYS_brk,
+        libc::SYS_mmap,
+        libc::SYS_munmap,
+        libc::SYS_mremap,
+        libc::SYS_mprotect,
+        libc::SYS_madvise,
+        libc::SYS_mlock,
+        libc::SYS_munlock,
+        // Threads
+        libc::SYS_clone,
+        libc::SYS_futex,
+        libc::SYS_set_robust_list,
+        libc::SYS_rseq,
+        libc::SYS_sigaltstack,
+        libc::SYS_rt_sigprocmask,
+        libc::SYS_rt_sigreturn,
+        libc::SYS_sched_getaffinity,
+        libc::SYS_sched_yield,
+        libc::SYS_clock_gettime,
+        libc::SYS_gettid,
+        libc::SYS_getpid,
+        // Exiting, or aborting on a panic
+        libc::SYS_tgkill,
+        libc::SYS_exit,
+        libc::SYS_exit_group,
+    ];
+
+    #[repr(C)]
+    struct SockFilter {
+        code: u16,
+        jt: u8,
+        jf: u8,
+        k: u32,
+    }
+
+    #[repr(C)]
+    struct SockFprog {
+        len: u16,
+        filter: *const SockFilter,
+    }
+
+    fn statement(code: u16, k: u32) -> SockFilter
+    {
+        SockFilter { code: code, jt: 0, jf: 0, k: k }
+    }
+
+    /// Returns from the filter with `action` if the loaded value is `value`.
+    fn return_if(filter: &mut Vec<SockFilter>, value: u32, action: u32)
+    {
+        filter.push(SockFilter { code: BPF_JMP_JEQ_K, jt: 0, jf: 1, k: value });
+        filter.push(statement(BPF_RET_K, action));
+    }
+
+    pub fn install() -> io::Result<()>
+    {
+        let mut filter = vec![];
+
+        // System call numbers depend on the architecture, so kill the
+        // process if it makes a call for another one.
+        filter.push(statement(BPF_LD_W_ABS, ARCH_OFFSET));
+        filter.push(SockFilter { code: BPF_JMP_JEQ_K, jt: 1, jf: 0, k: AUDIT_ARCH });
+        filter.push(statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS));
+
+        filter.push(statement(BPF_LD_W_ABS, NR_OFFSET));
+        for nr in ALLOWED_SYSCALLS {
+            return_if(&mut filter, *nr as u32, SECCOMP_RET_ALLOW);
+        }
+        // glibc creates threads with clone if clone3 is not implemented.
+        return_if(&mut filter, libc::SYS_clone3 as u32, SECCOMP_RET_ERRNO | libc::ENOSYS as u32);
+        filter.push(statement(BPF_RET_K, SECCOMP_RET_ERRNO | libc::EPERM as u32));
+
+        let prog = SockFprog {
+            len: filter.len() as u16,
+            filter: filter.as_ptr(),
+        };
+
+        // Without privileges, a filter can only be installed once the thread
+        // can no longer gain them.
+        let no_new_privs = unsafe {
+            libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong, 0 as libc::c_ulong)
+        };
+        if no_new_privs != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let seccomp = unsafe {
+            libc::prctl(libc::PR_SET_SECCOMP, SECCOMP_MODE_FILTER as libc::c_ulong, &prog as *const SockFprog)
+        };
+        if seccomp != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+/// Confine the calling thread, and the threads it creates afterwards, to the
+/// system calls needed to compute a contribution.
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub fn enter() -> io::Result<()>
+{
+    seccomp::install()
+}
+
+#[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+pub fn enter() -> io::Result<()>
+{
+    Err(io::Error::new(io::ErrorKind::Other, "sandboxing is only supported on Linux"))
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[test]
+fn test_sandbox() {
+    use std::fs::File;
+    use std::net::TcpListener;
+    use std::thread;
+    use rand::OsRng;
+    use bn256::Bn256;
+    use configuration::Configuration;
+    use {keypair, Accumulator};
+
+    // The filter only applies to the thread entering it, so the other tests
+    // are unaffected.
+    thread::spawn(|| {
+        enter().expect("unable to enter the sandbox");
+
+        let e = File::open("Cargo.toml").expect_err("opened a file in the sandbox");
+        assert_eq!(e.raw_os_error(), Some(libc::EPERM));
+        let e = TcpListener::bind("127.0.0.1:0").expect_err("opened a socket in the sandbox");
+        assert_eq!(e.raw_os_error(), Some(libc::EPERM));
+
+        // A contribution can still be computed, on several threads.
+        let mut rng = OsRng::new().unwrap();
+        let (_, privkey) = keypair::<Bn256, _>(&mut rng, &[0u8; 64]);
+        let mut acc = Accumulator::<Bn256>::new(Configuration::new(16));
+        acc.transform(&privkey);
+    }).join().expect("failed in the sandbox");
+}
@@ -0,0 +1,317 @@
+//! Memory for the secrets of a contribution: the private key, the powers of
+//! tau computed from it, and the RNG and entropy it is drawn from. Anyone
+//! who recovers them can forge proofs for circuits using the parameters.
+//!
+//! Secrets are held in whole pages of their own, which are locked into RAM
+//! with `mlock` so that they are never written to swap, and overwritten with
+//! zeros before they are freed. Locking fails beyond the limit on locked
+//! memory (`ulimit -l`), in which case the pages are still zeroed;
+//! `is_locked` tells which. Copies made in registers or on the stack while
+//! computing with the secrets are not covered.
+
+use std::alloc::{self, Layout};
+use std::cmp;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{compiler_fence, Ordering};
+#[cfg(test)]
+use std::cell::RefCell;
+
+/// Overwrite `bytes` with zeros, in a way the compiler cannot optimize away.
+pub fn zeroize(bytes: &mut [u8])
+{
+    for b in bytes.iter_mut() {
+        unsafe { ptr::write_volatile(b, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(unix)]
+fn page_size() -> usize
+{
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+#[cfg(not(unix))]
+fn page_size() -> usize
+{
+    4096
+}
+
+#[cfg(unix)]
+fn lock(ptr: *mut u8, size: usize) -> bool
+{
+    unsafe { libc::mlock(ptr as *const libc::c_void, size) == 0 }
+}
+
+#[cfg(not(unix))]
+fn lock(_ptr: *mut u8, _size: usize) -> bool
+{
+    false
+}
+
+#[cfg(unix)]
+fn unlock(ptr: *mut u8, size: usize)
+{
+    unsafe { libc::munlock(ptr as *const libc::c_void, size) };
+}
+
+#[cfg(not(unix))]
+fn unlock(_ptr: *mut u8, _size: usize)
+{
+}
+
+/// Whole pages of memory, locked if possible, and zeroed when dropped.
+struct LockedPages {
+    ptr: *mut u8,
+    layout: Layout,
+    locked: bool,
+}
+
+impl LockedPages {
+    fn new(size: usize, align: usize) -> Self
+    {
+        let page_size = page_size();
+        assert!(align <= page_size);
+
+        let size = (cmp::max(size, 1) + page_size - 1) / page_size * page_size;
+        let layout = Layout::from_size_align(size, page_size).expect("secrets are too large");
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
+        #[cfg(test)]
+        watch(ptr);
+
+        LockedPages {
+            ptr: ptr,
+            layout: layout,
+            locked: lock(ptr, size),
+        }
+    }
+}
+
+impl Drop for LockedPages {
+    fn drop(&mut self)
+    {
+        zeroize(unsafe { slice::from_raw_parts_mut(self.ptr, self.layout.size()) });
+        if self.locked {
+            unlock(self.ptr, self.layout.size());
+        }
+        unsafe { alloc::dealloc(self.ptr, self.layout) };
+    }
+}
+
+/// A secret value in locked memory, zeroed when dropped. Memory that `T`
+/// itself allocates is not covered.
+pub struct SecretBox<T> {
+    pages: LockedPages,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for SecretBox<T> {}
+unsafe impl<T: Sync> Sync for SecretBox<T> {}
+
+impl<T> SecretBox<T> {
+    pub fn new(value: T) -> Self
+    {
+        let pages = LockedPages::new(mem::size_of::<T>(), mem::align_of::<T>());
+        unsafe { ptr::write(pages.ptr as *mut T, value) };
+
+        SecretBox {
+            pages: pages,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Whether the memory could be locked.
+    pub fn is_locked(&self) -> bool
+    {
+        self.pages.locked
+    }
+}
+
+impl<T> Deref for SecretBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        unsafe { &*(self.pages.ptr as *const T) }
+    }
+}
+
+impl<T> DerefMut for SecretBox<T> {
+    fn deref_mut(&mut self) -> &mut T
+    {
+        unsafe { &mut *(self.pages.ptr as *mut T) }
+    }
+}
+
+impl<T> Drop for SecretBox<T> {
+    fn drop(&mut self)
+    {
+        // The pages are zeroed when they are dropped, after this.
+        unsafe { ptr::drop_in_place(self.pages.ptr as *mut T) };
+    }
+}
+
+/// A fixed number of secret values in locked memory, zeroed when dropped.
+pub struct SecretVec<T: Copy> {
+    pages: LockedPages,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Copy + Send> Send for SecretVec<T> {}
+unsafe impl<T: Copy + Sync> Sync for SecretVec<T> {}
+
+impl<T: Copy> SecretVec<T> {
+    /// `len` copies of `value`.
+    pub fn new(len: usize, value: T) -> Self
+    {
+        let size = len.checked_mul(mem::size_of::<T>()).expect("secrets are too large");
+        let pages = LockedPages::new(size, mem::align_of::<T>());
+        let ptr = pages.ptr as *mut T;
+        for i in 0..len {
+            unsafe { ptr::write(ptr.add(i), value) };
+        }
+
+        SecretVec {
+            pages: pages,
+            len: len,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Whether the memory could be locked.
+    pub fn is_locked(&self) -> bool
+    {
+        self.pages.locked
+    }
+}
+
+impl<T: Copy> Deref for SecretVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T]
+    {
+        unsafe { slice::from_raw_parts(self.pages.ptr as *const T, self.len) }
+    }
+}
+
+impl<T: Copy> DerefMut for SecretVec<T> {
+    fn deref_mut(&mut self) -> &mut [T]
+    {
+        unsafe { slice::from_raw_parts_mut(self.pages.ptr as *mut T, self.len) }
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// While a test watches them, the address of each secret allocated by
+    /// this thread, and once it is freed, whether it was zeroed.
+    static WATCHED_SECRETS: RefCell<Option<Vec<(usize, Option<bool>)>>> = RefCell::new(None);
+}
+
+#[cfg(test)]
+fn watch(ptr: *mut u8)
+{
+    WATCHED_SECRETS.with(|w| {
+        if let Some(ref mut w) = *w.borrow_mut() {
+            w.push((ptr as usize, None));
+        }
+    });
+}
+
+/// Wraps the system allocator in tests to inspect the memory of each watched
+/// secret as it is freed. Only secrets are allocated aligned to pages.
+#[cfg(test)]
+struct CheckSecretsZeroed;
+
+#[cfg(test)]
+unsafe impl alloc::GlobalAlloc for CheckSecretsZeroed {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8
+    {
+        alloc::GlobalAlloc::alloc(&alloc::System, layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout)
+    {
+        if layout.align() == page_size() {
+            let zeroed = slice::from_raw_parts(ptr, layout.size()).iter().all(|&b| b == 0);
+            // The list is busy, or gone, only when the thread is freeing its
+            // own memory, which is not a secret.
+            let _ = WATCHED_SECRETS.try_with(|w| {
+                if let Ok(mut w) = w.try_borrow_mut() {
+                    if let Some(ref mut w) = *w {
+                        for secret in w.iter_mut().filter(|s| s.0 == ptr as usize && s.1.is_none()) {
+                            secret.1 = Some(zeroed);
+                        }
+                    }
+                }
+            });
+        }
+        alloc::GlobalAlloc::dealloc(&alloc::System, ptr, layout)
+    }
+}
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: CheckSecretsZeroed = CheckSecretsZeroed;
+
+#[test]
+fn test_secrets_zeroed_on_drop() {
+    use bn::Fr;
+    use bn256::Bn256;
+    use configuration::Configuration;
+    use engine::ScalarField;
+    use rand::SeedableRng;
+    use rand::chacha::ChaChaRng;
+    use super::{keypair, Accumulator, PrivateKey};
+
+    // Only the secrets of this thread are watched, so the tests running in
+    // parallel do not interfere.
+    let start_watching = || WATCHED_SECRETS.with(|w| *w.borrow_mut() = Some(Vec::with_capacity(64)));
+    let stop_watching = || WATCHED_SECRETS.with(|w| w.borrow_mut().take().unwrap());
+
+    let mut bytes = [0xa5u8; 100];
+    zeroize(&mut bytes[1..]);
+    assert_eq!(bytes[0], 0xa5);
+    assert!(bytes[1..].iter().all(|&b| b == 0));
+
+    start_watching();
+    {
+        // Several pages.
+        let v = SecretVec::new(3000, 0xa5a5_a5a5_a5a5_a5a5u64);
+        assert_eq!(v.len(), 3000);
+        assert!(v.iter().all(|&x| x == 0xa5a5_a5a5_a5a5_a5a5));
+
+        let mut b = SecretBox::new([0x5au8; 32]);
+        b[1] = 1;
+        assert_eq!(&b[0..3], &[0x5a, 1, 0x5a]);
+    }
+    let secrets = stop_watching();
+    assert_eq!(secrets.len(), 2);
+    assert!(secrets.iter().all(|s| s.1 == Some(true)));
+
+    start_watching();
+    {
+        let mut rng = SecretBox::new(ChaChaRng::from_seed([7u8; 32]));
+        let (_, privkey) = keypair::<Bn256, _>(&mut *rng, &[0u8; 64]);
+        assert!(*privkey.tau != Fr::zero());
+
+        // Transforming frees the powers of tau.
+        let mut acc = Accumulator::<Bn256>::new(Configuration::new(16));
+        acc.transform(&privkey);
+        acc.transform(&PrivateKey::new(Fr::one(), Fr::one(), Fr::one()));
+    }
+    // The RNG, two keys of three secrets each, and two vectors of powers,
+    // each freed and zeroed.
+    let secrets = stop_watching();
+    assert_eq!(secrets.len(), 1 + 6 + 2);
+    assert!(secrets.iter().all(|s| s.1 == Some(true)));
+}
@@ -0,0 +1,434 @@
+//! Processing of accumulators in fixed-size batches of points, so that peak
+//! memory does not grow with the number of powers.
+//!
+//! The sections of an accumulator are laid out one after another on disk, so
+//! each section is read, processed and written `batch_size` points at a time.
+
+use std::cmp;
+use std::io::{self, Read, Write};
+use std::ops::*;
+
+use configuration::Configuration;
+use engine::{CurvePoint, Engine};
+use wnaf::WnafGroup;
+use super::{
+    CheckForCorrectness, DeserializationError, PrivateKey, TransformSummary,
+    UseCompression, batch_exp, merge_pairs, read_points, secret_mul, tau_powers, write_point,
+    write_points
+};
+
+/// Transforms the accumulator read (uncompressed) from `reader` with a private
+/// key, writing the result to `writer` with `compression`. Produces the same
+/// output as `Accumulator::transform` followed by `Accumulator::serialize`,
+/// while holding at most `batch_size` points of the accumulator in memory.
+pub fn transform_batched<E: Engine, R: Read, W: Write>(
+    config: Configuration,
+    reader: &mut R,
+    writer: &mut W,
+    compression: UseCompression,
+    key: &PrivateKey<E>,
+    batch_size: usize
+) -> Result<(), DeserializationError>
+{
+    assert!(batch_size > 0);
+
+    fn transform_section<R: Read, W: Write, C: WnafGroup>(
+        reader: &mut R,
+        writer: &mut W,
+        compression: UseCompression,
+        size: usize,
+        tau: &C::Scalar,
+        coeff: Option<&C::Scalar>,
+        batch_size: usize,
+        section: &'static str
+    ) -> Result<(), DeserializationError>
+    {
+        let mut start = 0;
+        while start < size {
+            let len = cmp::min(batch_size, size - start);
+
+            let mut points : Vec<C> = read_points(
+                reader, len, UseCompression::No, CheckForCorrectness::No, section, start)?;
+            let taupowers = tau_powers(tau, start, len);
+            batch_exp(&mut points, &taupowers[..], coeff);
+            write_points(writer, &points, compression)?;
+
+            start += len;
+        }
+
+        Ok(())
+    }
+
+    transform_section::<_, _, E::G1>(
+        reader, writer, compression, config.num_powers_g1, &*key.tau, None,
+        batch_size, "tau_powers_g1")?;
+    transform_section::<_, _, E::G2>(
+        reader, writer, compression, config.num_powers, &*key.tau, None,
+        batch_size, "tau_powers_g2")?;
+    transform_section::<_, _, E::G1>(
+        reader, writer, compression, config.num_powers, &*key.tau, Some(&*key.alpha),
+        batch_size, "alpha_tau_powers_g1")?;
+    transform_section::<_, _, E::G1>(
+        reader, writer, compression, config.num_powers, &*key.tau, Some(&*key.beta),
+        batch_size, "beta_tau_powers_g1")?;
+
+    let beta_g2 : E::G2 = read_points(
+        reader, 1, UseCompression::No, CheckForCorrectness::No, "beta_g2", 0)?[0];
+    write_points(writer, &[secret_mul(&beta_g2, &*key.beta, &E::G2::wnaf_params())], compression)?;
+
+    Ok(())
+}
+
+/// Running value of `power_pairs` over a vector supplied in consecutive
+/// chunks. The last element of each chunk is kept so that the pair spanning
+/// two chunks is included.
+struct PowerPairs<G: CurvePoint> {
+    last: Option<G>,
+    pairs: (G, G),
+}
+
+impl<G: CurvePoint> PowerPairs<G> {
+    fn new() -> Self {
+        PowerPairs {
+            last: None,
+            pairs: (G::zero(), G::zero()),
+        }
+    }
+
+    fn update(&mut self, chunk: &[G]) {
+        if chunk.is_empty() {
+            return;
+        }
+
+        let (s, sx) = match self.last {
+            Some(last) => {
+                let mut v1 = Vec::with_capacity(chunk.len());
+                v1.push(last);
+                v1.extend_from_slice(&chunk[0..(chunk.len() - 1)]);
+                merge_pairs(&v1, chunk)
+            }
+            None => merge_pairs(&chunk[0..(chunk.len() - 1)], &chunk[1..])
+        };
+
+        self.pairs = (self.pairs.0.add(s), self.pairs.1.add(sx));
+        self.last = Some(chunk[chunk.len() - 1]);
+    }
+}
+
+/// What `verify_transform` needs from one section of an accumulator.
+struct SectionSummary<G: CurvePoint> {
+    /// The first two elements of the section.
+    head: Vec<G>,
+    /// `power_pairs` over the section.
+    pairs: (G, G),
+}
+
+/// Reads one section of an accumulator `batch_size` points at a time. Each
+/// batch is also written, uncompressed, to `writer`.
+fn summarize_section<R, W, C>(
+    reader: &mut R,
+    compression: UseCompression,
+    checked: CheckForCorrectness,
+    writer: &mut W,
+    size: usize,
+    batch_size: usize,
+    section: &'static str
+) -> Result<SectionSummary<C>, DeserializationError>
+    where R: Read, W: Write, C: CurvePoint
+{
+    let mut head = Vec::with_capacity(2);
+    let mut pairs = PowerPairs::new();
+
+    let mut start = 0;
+    while start < size {
+        let len = cmp::min(batch_size, size - start);
+
+        let points : Vec<C> = read_points(reader, len, compression, checked, section, start)?;
+        write_points(writer, &points, UseCompression::No)?;
+
+        for i in 0..len {
+            if start + i >= 2 {
+                break;
+            }
+            head.push(points[i]);
+        }
+
+        pairs.update(&points);
+
+        start += len;
+    }
+
+    Ok(SectionSummary {
+        head: head,
+        pairs: pairs.pairs,
+    })
+}
+
+/// Reads an accumulator `batch_size` points at a time, and reduces it to the
+/// `TransformSummary` of the transformation from the accumulator to itself,
+/// which is what `TransformSummary::verify_consistency` checks, and which
+/// `TransformSummary::chain` joins to the summary of another accumulator.
+///
+/// The accumulator is also written, uncompressed, to `writer`, so that
+/// callers can hash or store it within the same pass.
+pub fn summarize_batched<E, R, W>(
+    config: Configuration,
+    reader: &mut R,
+    compression: UseCompression,
+    checked: CheckForCorrectness,
+    writer: &mut W,
+    batch_size: usize
+) -> Result<TransformSummary<E>, DeserializationError>
+    where E: Engine, R: Read, W: Write
+{
+    assert!(batch_size > 0);
+
+    let tau_g1 : SectionSummary<E::G1> = summarize_section(
+        reader, compression, checked, writer, config.num_powers_g1, batch_size, "tau_powers_g1")?;
+    let tau_g2 : SectionSummary<E::G2> = summarize_section(
+        reader, compression, checked, writer, config.num_powers, batch_size, "tau_powers_g2")?;
+    let alpha_g1 : SectionSummary<E::G1> = summarize_section(
+        reader, compression, checked, writer, config.num_powers, batch_size, "alpha_tau_powers_g1")?;
+    let beta_g1 : SectionSummary<E::G1> = summarize_section(
+        reader, compression, checked, writer, config.num_powers, batch_size, "beta_tau_powers_g1")?;
+    let beta_g2 : SectionSummary<E::G2> = summarize_section(
+        reader, compression, checked, writer, 1, batch_size, "beta_g2")?;
+
+    Ok(TransformSummary {
+        before_tau_g1: tau_g1.head[1],
+        before_alpha_g1: alpha_g1.head[0],
+        before_beta_g1: beta_g1.head[0],
+        before_beta_g2: beta_g2.head[0],
+        after_tau_g1: (tau_g1.head[0], tau_g1.head[1]),
+        after_tau_g2: (tau_g2.head[0], tau_g2.head[1]),
+        after_alpha_g1: alpha_g1.head[0],
+        after_beta_g1: beta_g1.head[0],
+        after_beta_g2: beta_g2.head[0],
+        tau_powers_g1_pairs: tau_g1.pairs,
+        tau_powers_g2_pairs: tau_g2.pairs,
+        alpha_tau_powers_g1_pairs: alpha_g1.pairs,
+        beta_tau_powers_g1_pairs: beta_g1.pairs,
+    })
+}
+
+/// Reads an old and a new accumulator, `batch_size` points at a time, and
+/// reduces them to the `TransformSummary` needed to verify the
+/// transformation between them. Points of the new accumulator are checked
+/// for correctness as they are read.
+///
+/// The old and new accumulators are also written, uncompressed, to
+/// `before_writer` and `after_writer`, so that callers can hash or store them
+/// within the same pass.
+pub fn summarize_transform_batched<E, R1, R2, W1, W2>(
+    config: Configuration,
+    before: &mut R1,
+    before_compression: UseCompression,
+    after: &mut R2,
+    after_compression: UseCompression,
+    before_writer: &mut W1,
+    after_writer: &mut W2,
+    batch_size: usize
+) -> Result<TransformSummary<E>, DeserializationError>
+    where E: Engine, R1: Read, R2: Read, W1: Write, W2: Write
+{
+    let before = summarize_batched::<E, _, _>(
+        config, before, before_compression, CheckForCorrectness::No, before_writer, batch_size)?;
+    let after = summarize_batched::<E, _, _>(
+        config, after, after_compression, CheckForCorrectness::Yes, after_writer, batch_size)?;
+
+    Ok(TransformSummary::chain(&before, &after))
+}
+
+/// Reads as the serialization of `Accumulator::new(config)` with some
+/// compression behavior, without holding the accumulator in memory.
+pub struct NewAccumulatorReader {
+    /// The encoding of the generator and number of copies left, per section.
+    sections: Vec<(Vec<u8>, usize)>,
+    /// Unread bytes of the current point.
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl NewAccumulatorReader {
+    pub fn new<E: Engine>(config: Configuration, compression: UseCompression) -> Self {
+        let mut g1 = vec![];
+        write_point(&mut g1, &E::G1::one(), compression).unwrap();
+        let mut g2 = vec![];
+        write_point(&mut g2, &E::G2::one(), compression).unwrap();
+
+        NewAccumulatorReader {
+            sections: vec![
+                (g2.clone(), 1),
+                (g1.clone(), config.num_powers),
+                (g1.clone(), config.num_powers),
+                (g2, config.num_powers),
+                (g1, config.num_powers_g1),
+            ],
+            buffer: vec![],
+            position: 0,
+        }
+    }
+}
+
+impl Read for NewAccumulatorReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Sections are stored in reverse order, to be popped off the end.
+        while self.position == self.buffer.len() {
+            match self.sections.pop() {
+                Some((point, count)) => {
+                    if count > 1 {
+                        self.sections.push((point.clone(), count - 1));
+                    }
+                    if count > 0 {
+                        self.buffer = point;
+                        self.position = 0;
+                    }
+                }
+                None => return Ok(0)
+            }
+        }
+
+        let len = cmp::min(buf.len(), self.buffer.len() - self.position);
+        buf[0..len].copy_from_slice(&self.buffer[self.position..(self.position + len)]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+#[test]
+fn test_transform_batched() {
+    use rand::{thread_rng, Rng};
+    use bls12::Bls12;
+    use bn256::Bn256;
+    use super::{Accumulator, keypair};
+
+    fn test_engine<E: Engine>() {
+        let config = Configuration::for_engine::<E>(16);
+        let rng = &mut thread_rng();
+        let digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+        // Start from a non-trivial accumulator
+        let mut before = Accumulator::<E>::new(config);
+        let (_, sk) = keypair(rng, &digest);
+        before.transform(&sk);
+
+        let mut input = vec![];
+        before.serialize(&mut input, UseCompression::No).unwrap();
+
+        let (_, sk) = keypair(rng, &digest);
+        let mut after = before.clone();
+        after.transform(&sk);
+
+        for &compression in &[UseCompression::No, UseCompression::Yes] {
+            let mut expected = vec![];
+            after.serialize(&mut expected, compression).unwrap();
+
+            for &batch_size in &[1, 5, 16, 1000] {
+                let mut output = vec![];
+                transform_batched(
+                    config, &mut &input[..], &mut output, compression, &sk, batch_size).unwrap();
+                assert!(output == expected);
+            }
+        }
+    }
+
+    test_engine::<Bn256>();
+    test_engine::<Bls12>();
+}
+
+#[test]
+fn test_new_accumulator_reader() {
+    use bls12::Bls12;
+    use bn256::Bn256;
+    use super::Accumulator;
+
+    fn test_engine<E: Engine>() {
+        let config = Configuration::for_engine::<E>(16);
+
+        for &compression in &[UseCompression::No, UseCompression::Yes] {
+            let mut expected = vec![];
+            Accumulator::<E>::new(config).serialize(&mut expected, compression).unwrap();
+
+            let mut actual = vec![];
+            NewAccumulatorReader::new::<E>(config, compression).read_to_end(&mut actual).unwrap();
+            assert!(actual == expected);
+        }
+    }
+
+    test_engine::<Bn256>();
+    test_engine::<Bls12>();
+}
+
+#[test]
+fn test_summarize_transform_batched() {
+    use rand::{thread_rng, Rng};
+    use bls12::Bls12;
+    use bn256::Bn256;
+    use super::{Accumulator, keypair, HashWriter, VerificationError};
+
+    fn test_engine<E: Engine>() {
+        let config = Configuration::for_engine::<E>(16);
+        let rng = &mut thread_rng();
+        let mut digest = (0..64).map(|_| rng.gen()).collect::<Vec<_>>();
+
+        let before = Accumulator::<E>::new(config);
+        let mut after = before.clone();
+        let (pk, sk) = keypair(rng, &digest);
+        after.transform(&sk);
+
+        let mut after_compressed = vec![];
+        after.serialize(&mut after_compressed, UseCompression::Yes).unwrap();
+
+        let mut after_uncompressed = vec![];
+        after.serialize(&mut after_uncompressed, UseCompression::No).unwrap();
+
+        let mut before_hash = HashWriter::new(io::sink());
+        before.serialize(&mut before_hash, UseCompression::No).unwrap();
+        let before_hash = before_hash.into_hash();
+
+        for &batch_size in &[1, 2, 7, 1000] {
+            let mut before_writer = HashWriter::new(io::sink());
+            let mut after_writer = vec![];
+            let summary = summarize_transform_batched::<E, _, _, _, _>(
+                config,
+                &mut NewAccumulatorReader::new::<E>(config, UseCompression::Yes),
+                UseCompression::Yes,
+                &mut &after_compressed[..],
+                UseCompression::Yes,
+                &mut before_writer,
+                &mut after_writer,
+                batch_size).unwrap();
+
+            assert!(summary.verify(&pk, &digest).is_ok());
+            assert!(before_writer.into_hash() == before_hash);
+            assert!(after_writer == after_uncompressed);
+
+            // The new accumulator alone is consistent
+            let summary = summarize_batched::<E, _, _>(
+                config,
+                &mut &after_uncompressed[..],
+                UseCompression::No,
+                CheckForCorrectness::Yes,
+                &mut io::sink(),
+                batch_size).unwrap();
+            assert!(summary.verify_consistency().is_ok());
+        }
+
+        // A different transcript digest fails as for `verify_transform`
+        let summary = summarize_transform_batched::<E, _, _, _, _>(
+            config,
+            &mut NewAccumulatorReader::new::<E>(config, UseCompression::Yes),
+            UseCompression::Yes,
+            &mut &after_compressed[..],
+            UseCompression::Yes,
+            &mut io::sink(),
+            &mut io::sink(),
+            5).unwrap();
+        digest[0] = !digest[0];
+        assert_eq!(summary.verify(&pk, &digest), Err(VerificationError::TauProofOfKnowledge));
+    }
+
+    test_engine::<Bn256>();
+    test_engine::<Bls12>();
+}
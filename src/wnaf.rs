@@ -0,0 +1,341 @@
+//! Exponentiation of group elements by full-width scalars using the windowed
+//! non-adjacent form (wNAF), with the GLV endomorphism on BN256 G1.
+//!
+//! These produce exactly the same group elements as `CurvePoint::mul`, and
+//! are used where many distinct bases are raised to distinct exponents.
+
+use bls12_381::{G1Projective, G2Projective};
+use bn::{Fq, Fr, G1, G2};
+use bn::arith::U256;
+use byteorder::{ByteOrder, BigEndian};
+use std::ops::{Add, Neg, Sub};
+
+use engine::{CurvePoint, ScalarField};
+
+/// Width of the NAF used for every exponentiation. Each base requires a
+/// table of 2<sup>w-2</sup> odd multiples.
+const WINDOW_SIZE: usize = 4;
+
+/// Group elements that can be exponentiated faster than with
+/// `CurvePoint::mul`.
+pub trait WnafGroup: CurvePoint {
+    /// Constants needed by `wnaf_mul`, computed once per batch.
+    type Params: Sync;
+
+    fn wnaf_params() -> Self::Params;
+
+    /// Computes `self * scalar`.
+    fn wnaf_mul(&self, scalar: &Self::Scalar, params: &Self::Params) -> Self;
+}
+
+/// Computes `base * scalar` without an endomorphism.
+fn plain_wnaf_mul<G: CurvePoint>(base: &G, scalar: &G::Scalar) -> G
+{
+    wnaf_exp(&[
+        (wnaf_table(*base), wnaf_form(scalar_to_limbs(scalar)))
+    ])
+}
+
+impl WnafGroup for G2 {
+    type Params = ();
+
+    fn wnaf_params() -> Self::Params {}
+
+    fn wnaf_mul(&self, scalar: &Fr, _params: &()) -> Self {
+        plain_wnaf_mul(self, scalar)
+    }
+}
+
+impl WnafGroup for G1Projective {
+    type Params = ();
+
+    fn wnaf_params() -> Self::Params {}
+
+    fn wnaf_mul(&self, scalar: &Self::Scalar, _params: &()) -> Self {
+        plain_wnaf_mul(self, scalar)
+    }
+}
+
+impl WnafGroup for G2Projective {
+    type Params = ();
+
+    fn wnaf_params() -> Self::Params {}
+
+    fn wnaf_mul(&self, scalar: &Self::Scalar, _params: &()) -> Self {
+        plain_wnaf_mul(self, scalar)
+    }
+}
+
+/// Constants for the GLV endomorphism φ(x, y) = (βx, y) of BN256 G1, which
+/// acts as multiplication by λ, where λ<sup>2</sup> + λ + 1 = 0 mod r.
+///
+/// The scalar decomposition uses the short lattice basis (a1, b1), (a2, b2)
+/// of {(x, y) : x + yλ = 0 mod r}, with b1 < 0.
+pub struct GlvParams {
+    beta: Fq,
+    a1: Fr,
+    minus_b1: Fr,
+    a2: Fr,
+    b2: Fr,
+}
+
+/// round(2^256 * b2 / r), as little-endian limbs.
+const GLV_G1: [u64; 2] = [0xd91d232ec7e0b3d7, 0x2];
+
+/// round(2^256 * -b1 / r), as little-endian limbs.
+const GLV_G2: [u64; 3] = [0x7a7bd9d4391eb18e, 0x4ccef014a773d2cf, 0x2];
+
+impl GlvParams {
+    pub fn new() -> Self {
+        let fr = |s| Fr::from_str(s).expect("valid GLV constant");
+
+        GlvParams {
+            beta: Fq::from_str(
+                "2203960485148121921418603742825762020974279258880205651966"
+            ).expect("valid GLV constant"),
+            a1: fr("9931322734385697763"),
+            minus_b1: fr("147946756881789319000765030803803410728"),
+            a2: fr("147946756881789319010696353538189108491"),
+            b2: fr("9931322734385697763"),
+        }
+    }
+
+    /// Decomposes `k` as k1 + k2λ mod r, where |k1| and |k2| are less than
+    /// 2<sup>127</sup>. Each half is returned as (is_negative, magnitude).
+    fn decompose(&self, k: &Fr) -> ((bool, u128), (bool, u128))
+    {
+        let limbs = k.to_limbs();
+
+        // c1 = round(k * b2 / r), c2 = round(-k * b1 / r)
+        let c1 = fr_from_u128(mul_shift_256(&limbs, &GLV_G1));
+        let c2 = fr_from_u128(mul_shift_256(&limbs, &GLV_G2));
+
+        let k1 = *k - c1 * self.a1 - c2 * self.a2;
+        let k2 = c1 * self.minus_b1 - c2 * self.b2;
+
+        (fr_to_signed_u128(k1), fr_to_signed_u128(k2))
+    }
+
+    /// φ(P) = λP
+    fn endomorphism(&self, p: &G1) -> G1 {
+        G1::new(p.x() * self.beta, p.y(), p.z())
+    }
+}
+
+impl WnafGroup for G1 {
+    type Params = GlvParams;
+
+    fn wnaf_params() -> GlvParams {
+        GlvParams::new()
+    }
+
+    fn wnaf_mul(&self, scalar: &Fr, params: &GlvParams) -> Self {
+        let ((k1_neg, k1), (k2_neg, k2)) = params.decompose(scalar);
+
+        let p1 = if k1_neg { self.neg() } else { *self };
+        let p2 = params.endomorphism(self);
+        let p2 = if k2_neg { p2.neg() } else { p2 };
+
+        wnaf_exp(&[
+            (wnaf_table(p1), wnaf_form(u128_to_limbs(k1))),
+            (wnaf_table(p2), wnaf_form(u128_to_limbs(k2)))
+        ])
+    }
+}
+
+/// Returns `f` as little-endian limbs, with an extra limb of headroom for
+/// `wnaf_form`.
+fn scalar_to_limbs<F: ScalarField>(f: &F) -> [u64; 5]
+{
+    let limbs = f.to_limbs();
+    [limbs[0], limbs[1], limbs[2], limbs[3], 0]
+}
+
+fn u128_to_limbs(v: u128) -> [u64; 5]
+{
+    [v as u64, (v >> 64) as u64, 0, 0, 0]
+}
+
+fn fr_from_u128(v: u128) -> Fr
+{
+    let mut bytes = [0u8; 32];
+    BigEndian::write_u128(&mut bytes[16..], v);
+    Fr::new(U256::from_slice(&bytes).unwrap()).expect("128-bit values are valid field elements")
+}
+
+/// Interprets a field element as a signed integer of magnitude less than
+/// 2<sup>128</sup>.
+fn fr_to_signed_u128(f: Fr) -> (bool, u128)
+{
+    fn low_u128(limbs: [u64; 4]) -> u128 {
+        (limbs[0] as u128) | ((limbs[1] as u128) << 64)
+    }
+
+    let limbs = f.to_limbs();
+    if limbs[2] == 0 && limbs[3] == 0 {
+        return (false, low_u128(limbs));
+    }
+
+    let limbs = f.neg().to_limbs();
+    assert!(limbs[2] == 0 && limbs[3] == 0, "GLV decomposition out of range");
+    (true, low_u128(limbs))
+}
+
+/// Computes (a * b) >> 256 for little-endian `a` and `b`, where the result
+/// is known to fit in 128 bits.
+fn mul_shift_256(a: &[u64], b: &[u64]) -> u128
+{
+    let mut product = [0u64; 8];
+
+    for (i, a) in a.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, b) in b.iter().enumerate() {
+            let t = (*a as u128) * (*b as u128) + (product[i + j] as u128) + carry;
+            product[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        product[i + b.len()] = carry as u64;
+    }
+
+    assert!(product[6] == 0 && product[7] == 0);
+    (product[4] as u128) | ((product[5] as u128) << 64)
+}
+
+/// Returns the width-`WINDOW_SIZE` NAF of the little-endian integer `limbs`,
+/// least significant digit first. Every non-zero digit is odd and less than
+/// 2<sup>w-1</sup> in magnitude.
+fn wnaf_form(mut limbs: [u64; 5]) -> Vec<i64>
+{
+    let mut res = Vec::with_capacity(257);
+
+    while limbs.iter().any(|l| *l != 0) {
+        let mut z = 0;
+        if limbs[0] & 1 == 1 {
+            z = (limbs[0] & ((1 << WINDOW_SIZE) - 1)) as i64;
+            if z >= 1 << (WINDOW_SIZE - 1) {
+                z -= 1 << WINDOW_SIZE;
+
+                // limbs += -z
+                let mut carry = (-z) as u64;
+                for l in limbs.iter_mut() {
+                    let (v, overflow) = l.overflowing_add(carry);
+                    *l = v;
+                    carry = overflow as u64;
+                }
+            } else {
+                // limbs -= z
+                let mut borrow = z as u64;
+                for l in limbs.iter_mut() {
+                    let (v, overflow) = l.overflowing_sub(borrow);
+                    *l = v;
+                    borrow = overflow as u64;
+                }
+            }
+        }
+
+        res.push(z);
+
+        // limbs >>= 1
+        for i in 0..4 {
+            limbs[i] = (limbs[i] >> 1) | (limbs[i + 1] << 63);
+        }
+        limbs[4] >>= 1;
+    }
+
+    res
+}
+
+/// Returns the odd multiples P, 3P, 5P, ... used with `wnaf_form` digits.
+fn wnaf_table<G: CurvePoint>(base: G) -> Vec<G>
+{
+    let double = base.add(base);
+    let mut table = Vec::with_capacity(1 << (WINDOW_SIZE - 2));
+    let mut acc = base;
+    for _ in 0..(1 << (WINDOW_SIZE - 2)) {
+        table.push(acc);
+        acc = acc.add(double);
+    }
+    table
+}
+
+/// Computes Σ (table<sub>i</sub>[0] * wnaf<sub>i</sub>) sharing the doublings
+/// between all terms.
+fn wnaf_exp<G: CurvePoint>(terms: &[(Vec<G>, Vec<i64>)]) -> G
+{
+    let len = terms.iter().map(|t| t.1.len()).max().unwrap_or(0);
+
+    let mut acc = G::zero();
+    for i in (0..len).rev() {
+        acc = acc.add(acc);
+
+        for &(ref table, ref wnaf) in terms {
+            if i < wnaf.len() {
+                let digit = wnaf[i];
+                if digit > 0 {
+                    acc = acc.add(table[(digit / 2) as usize]);
+                } else if digit < 0 {
+                    acc = acc.sub(table[(-digit / 2) as usize]);
+                }
+            }
+        }
+    }
+
+    acc
+}
+
+#[test]
+fn test_glv_decomposition() {
+    use rand::thread_rng;
+
+    let rng = &mut thread_rng();
+    let params = GlvParams::new();
+
+    // λ such that φ(P) = λP
+    let lambda = Fr::from_str(
+        "4407920970296243842393367215006156084916469457145843978461").unwrap();
+    assert!(params.endomorphism(&G1::one()) == G1::one().mul(&lambda));
+
+    let mut scalars = (0..100).map(|_| Fr::random(rng)).collect::<Vec<_>>();
+    scalars.push(Fr::zero());
+    scalars.push(Fr::one());
+    scalars.push(Fr::one().neg());
+
+    for k in scalars {
+        let ((k1_neg, k1), (k2_neg, k2)) = params.decompose(&k);
+        assert!(k1 < (1 << 127) && k2 < (1 << 127));
+
+        let k1 = if k1_neg { fr_from_u128(k1).neg() } else { fr_from_u128(k1) };
+        let k2 = if k2_neg { fr_from_u128(k2).neg() } else { fr_from_u128(k2) };
+        assert!(k1 + k2 * lambda == k);
+    }
+}
+
+#[test]
+fn test_wnaf_mul() {
+    use rand::thread_rng;
+    use bls12::Bls12;
+    use bn256::Bn256;
+    use engine::Engine;
+
+    fn test_engine<E: Engine>() {
+        let rng = &mut thread_rng();
+        let g1_params = E::G1::wnaf_params();
+        let g2_params = E::G2::wnaf_params();
+
+        let mut scalars = (0..50).map(|_| E::Fr::random(rng)).collect::<Vec<_>>();
+        scalars.push(E::Fr::zero());
+        scalars.push(E::Fr::one());
+        scalars.push(E::Fr::one().neg());
+
+        for k in scalars {
+            let g1 = E::G1::random(rng);
+            let g2 = E::G2::random(rng);
+            assert!(g1.wnaf_mul(&k, &g1_params) == g1.mul(&k));
+            assert!(g2.wnaf_mul(&k, &g2_params) == g2.mul(&k));
+            assert!(E::G1::zero().wnaf_mul(&k, &g1_params) == E::G1::zero());
+        }
+    }
+
+    test_engine::<Bn256>();
+    test_engine::<Bls12>();
+}
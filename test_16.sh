@@ -2,71 +2,120 @@
 
 set -e
 
-FLAGS="--release -- -n 16"
+# The curve may be given as the first argument (bn256 or bls12_381)
+CURVE=${1:-bn256}
+FLAGS="--release -- -n 16 --curve ${CURVE}"
 
 # Run 3 rounds and the beacon
-rm -f challenge response
+rm -f challenge.* response.* new_challenge
 
-cargo run --bin new ${FLAGS}
-# Wrote a fresh accumulator to `./challenge`
+cargo run --bin new ${FLAGS} --challenge challenge.0
+# Wrote a fresh accumulator to `challenge.0`
 
 # Round 1
 
-echo random1 | cargo run --bin compute ${FLAGS}
-# Your contribution has been written to `./response`
+echo random1 | cargo run --bin compute ${FLAGS} --challenge challenge.0 --response response.1
+# Your contribution has been written to `response.1`
 
-cargo run --bin verify_transform ${FLAGS}
-# Verification succeeded! Writing to `./new_challenge`
-
-mv challenge challenge.0
-mv response response.1
-mv new_challenge challenge
+cargo run --bin verify_transform ${FLAGS} --challenge challenge.0 --response response.1 --new-challenge challenge.1
+# Verification succeeded! Writing to `challenge.1`
 
 # Round 2
 
-echo random2 | cargo run --bin compute ${FLAGS} --digest response.2.digest
-# Your contribution has been written to `./response`
-
-cargo run --bin verify_transform ${FLAGS}
-# Verification succeeded! Writing to `./new_challenge`
+echo random2 | cargo run --bin compute ${FLAGS} --challenge challenge.1 --response response.2 --digest response.2.digest
+# Your contribution has been written to `response.2`
 
-mv challenge challenge.1
-mv response response.2
-mv new_challenge challenge
+cargo run --bin verify_transform ${FLAGS} --challenge challenge.1 --response response.2 --new-challenge challenge.2
+# Verification succeeded! Writing to `challenge.2`
 
-# Round 3
+# Round 3 (computed twice, overwriting the first response)
 
-echo random3 | cargo run --bin compute ${FLAGS}
-# Your contribution has been written to `./response`
+echo random3 | cargo run --bin compute ${FLAGS} --challenge challenge.2 --response response.3
+echo random3 | cargo run --bin compute ${FLAGS} --challenge challenge.2 --response response.3 --force
+# Your contribution has been written to `response.3`
 
-cargo run --bin verify_transform ${FLAGS}
-# Verification succeeded! Writing to `./new_challenge`
+cargo run --bin verify_transform ${FLAGS} --challenge challenge.2 --response response.3 --new-challenge challenge.3
+# Verification succeeded! Writing to `challenge.3`
 
-mv challenge challenge.2
-mv response response.3
-mv new_challenge challenge
+# The sandboxed child process computes the same response as this process
+# given the same seed, in memory or in batches
+SEED=000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f
+cargo run --features insecure-seed --bin compute ${FLAGS} --challenge challenge.2 --response response.3.unsandboxed --insecure-seed ${SEED} --no-sandbox
+cargo run --features insecure-seed --bin compute ${FLAGS} --challenge challenge.2 --response response.3.sandboxed --insecure-seed ${SEED}
+cargo run --features insecure-seed --bin compute ${FLAGS} --challenge challenge.2 --response response.3.batched --insecure-seed ${SEED} --batch-size 1000
+cmp response.3.unsandboxed response.3.sandboxed
+cmp response.3.unsandboxed response.3.batched
 
 # Round 4 (Beacon)
 
-echo random4 | cargo run --bin compute ${FLAGS}
-# Your contribution has been written to `./response`
+cargo run --bin beacon ${FLAGS} --challenge challenge.3 --response response.4.beacon \
+    --beacon-hash 00000000000000000034b33e842ac1c50456abe5fa92b60f6b3dfc5d247f7b58 --iterations-log2 10 --checkpoint-interval-log2 6
+# The beacon parameters and checkpoints have been written to `response.4.beacon.beacon`
+# Your contribution has been written to `response.4.beacon`
+
+cargo run --bin verify_beacon --release -- --beacon-descriptor response.4.beacon.beacon --threads 3
 
-cargo run --bin verify_transform ${FLAGS}
+cargo run --bin verify_transform ${FLAGS} --challenge challenge.3 --response response.4.beacon --out-dir .
 # Verification succeeded! Writing to `./new_challenge`
 
-mv challenge challenge.3
-mv response response.4.beacon
-mv new_challenge challenge
+cargo run --bin verify_beacon_key ${FLAGS} --challenge challenge.3 --response response.4.beacon --beacon-descriptor response.4.beacon.beacon
+# Verification succeeded! The public key in `response.4.beacon` was drawn from the beacon
+
+echo Resuming the beacon from a saved state ...
+head -n 10 response.4.beacon.beacon > response.4.resumed.beacon-state
+cargo run --bin beacon ${FLAGS} --challenge challenge.3 --response response.4.resumed \
+    --beacon-hash 00000000000000000034b33e842ac1c50456abe5fa92b60f6b3dfc5d247f7b58 --iterations-log2 10 --checkpoint-interval-log2 6
+cmp response.4.beacon response.4.resumed
+cmp response.4.beacon.beacon response.4.resumed.beacon
+
+echo Resuming after the descriptor and response were written ...
+head -n 10 response.4.beacon.beacon > response.4.resumed.beacon-state
+cargo run --bin beacon ${FLAGS} --challenge challenge.3 --response response.4.resumed \
+    --beacon-hash 00000000000000000034b33e842ac1c50456abe5fa92b60f6b3dfc5d247f7b58 --iterations-log2 10 --checkpoint-interval-log2 6
+cmp response.4.beacon response.4.resumed
+cmp response.4.beacon.beacon response.4.resumed.beacon
+
+echo Running a beacon combining several sources ...
+cargo run --bin beacon ${FLAGS} --challenge challenge.3 --response response.4.sources \
+    --beacon-source bitcoin-block-514200=00000000000000000034b33e842ac1c50456abe5fa92b60f6b3dfc5d247f7b58 \
+    --beacon-source lottery=3034203131203233203332203435203038 --iterations-log2 8 --checkpoint-interval-log2 4
+cargo run --bin verify_beacon --release -- --beacon-descriptor response.4.sources.beacon
+sed -e 's/^source lottery 30/source lottery 31/' response.4.sources.beacon > response.4.sources.invalid.beacon
+if (cargo run --bin verify_beacon --release -- --beacon-descriptor response.4.sources.invalid.beacon) ; then
+    echo Verification failed
+    exit 1
+fi
+
+echo Checking a source with an empty value is rejected ...
+if (cargo run --bin beacon ${FLAGS} --challenge challenge.3 --response response.4.empty \
+    --beacon-source lottery= --iterations-log2 8 --checkpoint-interval-log2 4) ; then
+    echo Verification failed
+    exit 1
+fi
+
+echo Checking a contribution not drawn from the beacon is rejected ...
+if (cargo run --bin verify_beacon_key ${FLAGS} --challenge challenge.2 --response response.3 --beacon-descriptor response.4.beacon.beacon) ; then
+    echo Verification failed
+    exit 1
+fi
 
 echo Creating transcript ...
 rm -f transcript
+# One header (32 bytes), then each response without its header and hash
+head -c 32 response.1 > transcript
 for f in response.1 response.2 response.3 response.4.beacon ; do
-    dd if=$f bs=64 skip=1 >> transcript
+    tail -c +97 $f >> transcript
 done
 
 echo Verifying transcript ...
 cargo run --bin verify ${FLAGS} -r 4 --skip-lagrange
 
+echo Verifying the last round of the transcript was drawn from the beacon ...
+cargo run --bin verify_beacon_key ${FLAGS} --challenge challenge.3 --transcript transcript --beacon-descriptor response.4.beacon.beacon
+
+echo Verifying transcript in batches ...
+cargo run --bin verify ${FLAGS} -r 4 --skip-lagrange --batch-size 1000
+
 echo Verifying transcript contains contribution ...
 cargo run --bin verify ${FLAGS} -r 4 --skip-lagrange --digest response.2.digest
 
@@ -76,3 +125,38 @@ if (cargo run --bin verify ${FLAGS} -r 4 --skip-lagrange --digest response.2.inv
     echo Verification failed
     exit 1
 fi
+
+if [ "${CURVE}" == "bn256" ] ; then
+    echo Exporting transcript to snarkjs ...
+    cargo run --bin export_ptau ${FLAGS} --ptau powersoftau.ptau --force
+
+    echo Starting a ceremony from the exported file ...
+    cargo run --bin import_ptau ${FLAGS} --ptau powersoftau.ptau --challenge imported.0 --force
+    echo random5 | cargo run --bin compute ${FLAGS} --challenge imported.0 --response imported.1 --force
+    cargo run --bin verify_transform ${FLAGS} --challenge imported.0 --response imported.1 --new-challenge imported.2 --force
+    head -c 32 imported.1 > imported_transcript
+    tail -c +97 imported.1 >> imported_transcript
+    cargo run --bin verify ${FLAGS} -r 1 --skip-lagrange --transcript imported_transcript --initial-challenge imported.0
+    cargo run --bin verify ${FLAGS} -r 1 --skip-lagrange --transcript imported_transcript --initial-challenge imported.0 --batch-size 1000
+
+    echo Exporting the ceremony that started from the imported file ...
+    cargo run --bin export_ptau ${FLAGS} --transcript imported_transcript --initial-challenge imported.0 --ptau imported.ptau --force
+    cargo run --bin import_ptau ${FLAGS} --ptau imported.ptau --challenge reimported.0 --force
+    # The accumulators match, after the header and hash
+    cmp <(tail -c +97 imported.2) <(tail -c +97 reimported.0)
+
+    echo Exporting halo2 KZG parameters ...
+    cargo run --bin export_halo2 ${FLAGS} --challenge imported.2 -k 3 --force
+
+    echo Exporting gnark and arkworks parameters ...
+    cargo run --bin export_gnark ${FLAGS} --challenge imported.2 --force
+    cargo run --bin export_arkworks ${FLAGS} --challenge imported.2 --uncompressed --force
+
+    echo Converting to and from the bellman-ce layout ...
+    cargo run --bin export_bellman ${FLAGS} --kind response --response imported.1 --bellman imported.1.bellman --force
+    cargo run --bin import_bellman ${FLAGS} --kind response --bellman imported.1.bellman --response imported.1.converted --force
+    cmp imported.1 imported.1.converted
+
+    echo Exporting to libff ...
+    cargo run --bin export_libff ${FLAGS} --challenge imported.2 --text --plain --lagrange --force
+fi
@@ -0,0 +1,170 @@
+# A snarkjs .ptau file for a ceremony of 2^2 powers with one contribution,
+# as `snarkjs powersoftau new bn128 2` followed by `snarkjs powersoftau
+# contribute` with an empty name would write it, following snarkjs 0.7
+# (powersoftau_new.js, powersoftau_contribute.js, keypair.js) and
+# ffjavascript (ChaCha, F1Field and G2 fromRng).
+import hashlib, os, random, struct
+
+from gen_test_vectors import q, r, F, Fq2, add, mul, G1, G2
+
+POWER = 2
+N = 1 << POWER
+
+G2_COFACTOR = 0x30644e72e131a029b85045b68181585e06ceecda572a2489345f2299c0f9fa8d
+B2 = Fq2(19485874751759354771024239261021720505790618469301721065564631296452457478373,
+         266929791119991161246907387137283842545076965332900288569378510910307636690)
+R = (1 << 256) % q
+R_INV = pow(R, q - 2, q)
+
+def blake2b(*parts):
+    h = hashlib.blake2b(digest_size=64)
+    for p in parts:
+        h.update(p)
+    return h.digest()
+
+# Points as snarkjs hashes them: big-endian, G2 with the imaginary part
+# first, and the point at infinity flagged with 0x40.
+be = lambda v: v.to_bytes(32, 'big')
+
+def unc_g1(P):
+    if P is None: return bytes([0x40] + [0] * 63)
+    return be(P[0].v) + be(P[1].v)
+
+def unc_g2(P):
+    if P is None: return bytes([0x40] + [0] * 127)
+    x, y = P
+    return be(x.b) + be(x.a) + be(y.b) + be(y.a)
+
+# Points as snarkjs stores them: little-endian Montgomery form, G2 with the
+# real part first, and the point at infinity all zeros.
+lem = lambda v: (v * R % q).to_bytes(32, 'little')
+
+def ptau_g1(P):
+    if P is None: return bytes(64)
+    return lem(P[0].v) + lem(P[1].v)
+
+def ptau_g2(P):
+    if P is None: return bytes(128)
+    x, y = P
+    return lem(x.a) + lem(x.b) + lem(y.a) + lem(y.b)
+
+def first_challenge_hash():
+    g1, g2 = unc_g1(G1), unc_g2(G2)
+    return blake2b(blake2b(), g1 * (2 * N - 1), g2 * N, g1 * (2 * N), g2)
+
+class ChaCha:
+    def __init__(s, key):
+        s.state = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574] + list(key) + [0, 0, 0, 0]
+        s.buf = []
+
+    @staticmethod
+    def quarter(x, a, b, c, d):
+        rotl = lambda v, n: ((v << n) | (v >> (32 - n))) & 0xffffffff
+        x[a] = (x[a] + x[b]) & 0xffffffff; x[d] = rotl(x[d] ^ x[a], 16)
+        x[c] = (x[c] + x[d]) & 0xffffffff; x[b] = rotl(x[b] ^ x[c], 12)
+        x[a] = (x[a] + x[b]) & 0xffffffff; x[d] = rotl(x[d] ^ x[a], 8)
+        x[c] = (x[c] + x[d]) & 0xffffffff; x[b] = rotl(x[b] ^ x[c], 7)
+
+    def next_u32(s):
+        if not s.buf:
+            x = list(s.state)
+            for _ in range(10):
+                for a, b, c, d in [(0, 4, 8, 12), (1, 5, 9, 13), (2, 6, 10, 14), (3, 7, 11, 15),
+                                   (0, 5, 10, 15), (1, 6, 11, 12), (2, 7, 8, 13), (3, 4, 9, 14)]:
+                    s.quarter(x, a, b, c, d)
+            s.buf = [(x[i] + s.state[i]) & 0xffffffff for i in range(16)]
+            s.state[12] = (s.state[12] + 1) & 0xffffffff
+            if s.state[12] == 0:
+                s.state[13] = (s.state[13] + 1) & 0xffffffff
+        return s.buf.pop(0)
+
+def fq_from_rng(rng):
+    # 64-bit limbs, least significant first, each drawn as two words with
+    # the most significant first, masked to 254 bits, until less than q. The
+    # value is the Montgomery form of the element.
+    while True:
+        v = 0
+        for i in range(4):
+            hi = rng.next_u32()
+            lo = rng.next_u32()
+            v |= ((hi << 32) | lo) << (64 * i)
+        v &= (1 << 254) - 1
+        if v < q:
+            return v * R_INV % q
+
+def fq_sqrt(a):
+    s = pow(a, (q + 1) // 4, q)
+    return s if s * s % q == a % q else None
+
+def fq2_sqrt(a):
+    if a.b == 0:
+        s = fq_sqrt(a.a)
+        if s is not None: return Fq2(s, 0)
+        return Fq2(0, fq_sqrt(-a.a % q))
+    n = fq_sqrt((a.a * a.a + a.b * a.b) % q)
+    if n is None: return None
+    half = pow(2, q - 2, q)
+    x0 = fq_sqrt((a.a + n) * half % q)
+    if x0 is None:
+        x0 = fq_sqrt((a.a - n) * half % q)
+    if x0 is None: return None
+    return Fq2(x0, a.b * pow(2 * x0, q - 2, q))
+
+def hash_to_g2(digest):
+    rng = ChaCha(struct.unpack('>8I', digest[:32]))
+    while True:
+        x = Fq2(fq_from_rng(rng), fq_from_rng(rng))
+        greatest = rng.next_u32() & 1 == 1
+        y = fq2_sqrt(x * x * x + B2)
+        if y is None:
+            continue
+        # Ordered by the imaginary part first.
+        key = lambda v: (v.b, v.a)
+        if (key(y) < key(-y)) == greatest:
+            y = -y
+        P = mul((x, y), G2_COFACTOR)
+        if P is not None:
+            return P
+
+def g2_s(g1_s, g1_s_x, personalization, challenge_hash):
+    return hash_to_g2(blake2b(bytes([personalization]), challenge_hash, unc_g1(g1_s), unc_g1(g1_s_x)))
+
+def section(id, data):
+    return struct.pack('<IQ', id, len(data)) + data
+
+def ptau():
+    rng = random.Random(2)
+    tau, alpha, beta = (rng.randrange(1, r) for _ in range(3))
+    challenge_hash = first_challenge_hash()
+
+    key_g1, key_g2 = b'', b''
+    for personalization, x in enumerate([tau, alpha, beta]):
+        g1_s = mul(G1, rng.randrange(1, r))
+        g1_s_x = mul(g1_s, x)
+        key_g1 += ptau_g1(g1_s) + ptau_g1(g1_s_x)
+        key_g2 += ptau_g2(mul(g2_s(g1_s, g1_s_x, personalization, challenge_hash), x))
+
+    powers = [pow(tau, i, r) for i in range(2 * N - 1)]
+    header = struct.pack('<I', 32) + q.to_bytes(32, 'little') + struct.pack('<II', POWER, POWER)
+
+    # The partial hash of the response is not read by this crate, and the
+    # hash of the next challenge is only used to verify a later
+    # contribution, so both are left as zeros.
+    contribution = (ptau_g1(mul(G1, tau)) + ptau_g2(mul(G2, tau)) + ptau_g1(mul(G1, alpha))
+        + ptau_g1(mul(G1, beta)) + ptau_g2(mul(G2, beta)) + key_g1 + key_g2
+        + bytes(216) + bytes(64) + struct.pack('<II', 0, 0))
+
+    return b'ptau' + struct.pack('<II', 1, 7) + b''.join([
+        section(1, header),
+        section(2, b''.join(ptau_g1(mul(G1, p)) for p in powers)),
+        section(3, b''.join(ptau_g2(mul(G2, p)) for p in powers[:N])),
+        section(4, b''.join(ptau_g1(mul(G1, alpha * p % r)) for p in powers[:N])),
+        section(5, b''.join(ptau_g1(mul(G1, beta * p % r)) for p in powers[:N])),
+        section(6, ptau_g2(mul(G2, beta))),
+        section(7, struct.pack('<I', 1) + contribution),
+    ])
+
+if __name__ == '__main__':
+    here = os.path.dirname(os.path.abspath(__file__))
+    with open(os.path.join(here, 'snarkjs_2.ptau'), 'wb') as f:
+        f.write(ptau())
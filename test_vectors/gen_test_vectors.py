@@ -0,0 +1,131 @@
+# Minimal BN254 (alt_bn128) arithmetic in affine coordinates.
+q = 21888242871839275222246405745257275088696311157297823662689037894645226208583
+r = 21888242871839275222246405745257275088548364400416034343698204186575808495617
+
+def inv(a): return pow(a, q - 2, q)
+
+class Fq2:
+    def __init__(s, a, b): s.a, s.b = a % q, b % q
+    def __add__(s, o): return Fq2(s.a + o.a, s.b + o.b)
+    def __sub__(s, o): return Fq2(s.a - o.a, s.b - o.b)
+    def __mul__(s, o):
+        if isinstance(o, int): return Fq2(s.a * o, s.b * o)
+        return Fq2(s.a * o.a - s.b * o.b, s.a * o.b + s.b * o.a)
+    def __neg__(s): return Fq2(-s.a, -s.b)
+    def __eq__(s, o): return s.a == o.a and s.b == o.b
+    def inv(s):
+        d = inv(s.a * s.a + s.b * s.b)
+        return Fq2(s.a * d, -s.b * d)
+    def iszero(s): return s.a == 0 and s.b == 0
+
+class F:
+    def __init__(s, v): s.v = v % q
+    def __add__(s, o): return F(s.v + o.v)
+    def __sub__(s, o): return F(s.v - o.v)
+    def __mul__(s, o): return F(s.v * (o if isinstance(o, int) else o.v))
+    def __neg__(s): return F(-s.v)
+    def __eq__(s, o): return s.v == o.v
+    def inv(s): return F(inv(s.v))
+    def iszero(s): return s.v == 0
+
+def add(P, Q):
+    if P is None: return Q
+    if Q is None: return P
+    (x1, y1), (x2, y2) = P, Q
+    if x1 == x2:
+        if (y1 + y2).iszero(): return None
+        l = x1 * x1 * 3 * (y1 * 2).inv()
+    else:
+        l = (y2 - y1) * (x2 - x1).inv()
+    x3 = l * l - x1 - x2
+    return (x3, l * (x1 - x3) - y1)
+
+def mul(P, k):
+    R = None
+    while k:
+        if k & 1: R = add(R, P)
+        P = add(P, P); k >>= 1
+    return R
+
+G1 = (F(1), F(2))
+G2 = (Fq2(10857046999023057135944570762232829481370756359578518086990519993285655852781,
+          11559732032986387107991004021392285783925812861821192530917403151452391805634),
+      Fq2(8495653923123431417604973247489272438418190587263600148770280649306958101930,
+          4082367875863433681332203403145435568316851327593401208105741076214120093531))
+
+# Exports of Configuration::new(16) (31 powers in G1) with tau = 7.
+import os, struct
+
+TAU = 7
+g1 = [mul(G1, pow(TAU, i, r)) for i in range(31)]
+g2 = [G2, mul(G2, TAU)]
+
+be = lambda v: v.to_bytes(32, 'big')
+le = lambda v: v.to_bytes(32, 'little')
+
+def fp_largest(y):
+    return y > (q - 1) // 2
+
+# gnark-crypto bn254 marshal.go: E2.LexicographicallyLargest decides on the
+# real part A0 first, and on A1 only if A0 is zero. An uncompressed point at
+# infinity is all zeros; a compressed one has the flag 0b01.
+def gnark_fq2_largest(y):
+    return fp_largest(y.b) if y.a == 0 else fp_largest(y.a)
+
+def gnark_g1(P, compressed):
+    if P is None:
+        return bytes([0x40] + [0] * 31) if compressed else bytes(64)
+    x, y = P
+    if not compressed:
+        return be(x.v) + be(y.v)
+    b = bytearray(be(x.v)); b[0] |= 0xc0 if fp_largest(y.v) else 0x80
+    return bytes(b)
+
+def gnark_g2(P, compressed):
+    if P is None:
+        return bytes([0x40] + [0] * 63) if compressed else bytes(128)
+    x, y = P
+    if not compressed:
+        return be(x.b) + be(x.a) + be(y.b) + be(y.a)
+    b = bytearray(be(x.b) + be(x.a)); b[0] |= 0xc0 if gnark_fq2_largest(y) else 0x80
+    return bytes(b)
+
+def gnark(compressed):
+    out = struct.pack('>I', len(g1))
+    out += b''.join(gnark_g1(P, compressed) for P in g1)
+    out += gnark_g2(g2[0], compressed) + gnark_g2(g2[1], compressed)
+    return out + gnark_g1(g1[0], compressed)
+
+# ark-serialize 0.4: QuadExtField orders by c1 first, and the flag in the
+# top bit of the last byte is set if y is the larger of y and -y.
+def ark_fq2_largest(y):
+    return fp_largest(y.b) if y.b != 0 else fp_largest(y.a)
+
+def ark_g1(P, compressed):
+    x, y = P
+    b = bytearray(le(x.v) if compressed else le(x.v) + le(y.v))
+    b[-1] |= 0x80 if fp_largest(y.v) else 0
+    return bytes(b)
+
+def ark_g2(P, compressed):
+    x, y = P
+    b = bytearray(le(x.a) + le(x.b) if compressed else le(x.a) + le(x.b) + le(y.a) + le(y.b))
+    b[-1] |= 0x80 if ark_fq2_largest(y) else 0
+    return bytes(b)
+
+def ark(compressed):
+    out = struct.pack('<Q', len(g1))
+    out += b''.join(ark_g1(P, compressed) for P in g1)
+    out += struct.pack('<Q', 0) + ark_g2(g2[0], compressed) + ark_g2(g2[1], compressed)
+    return out + struct.pack('<Q', 0)
+
+if __name__ == '__main__':
+    here = os.path.dirname(os.path.abspath(__file__))
+    for name, data in [
+        ('gnark_srs_16_compressed', gnark(True)),
+        ('gnark_srs_16_uncompressed', gnark(False)),
+        ('arkworks_params_16_compressed', ark(True)),
+        ('arkworks_params_16_uncompressed', ark(False)),
+    ]:
+        with open(os.path.join(here, name + '.bin'), 'wb') as f:
+            f.write(data)